@@ -1,4 +1,6 @@
 pub mod env;
+pub mod logs;
+pub mod scale;
 
 use {
     super::{Context, err},
@@ -6,11 +8,14 @@ use {
         common::{
             duration::humanize_duration,
             str::ToString,
-            tcp::{Request, Response, json},
+            tcp::{Request, Response, err_with_problems, json},
+        },
+        kurv::{
+            Egg, EggCheck, EggState, EggStatus, HealthStatus, ProcessMetrics, egg::check::EnvKeys,
         },
-        kurv::{Egg, EggState, EggStatus},
     },
     anyhow::{Result, anyhow},
+    log::warn,
     serde::{Deserialize, Serialize},
 };
 
@@ -25,10 +30,34 @@ pub struct EggSummary {
     pub status: EggStatus,
     pub uptime: String,
     pub retry_count: u32,
+    /// health of the egg, only for eggs with a health check
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub health: Option<HealthStatus>,
+    /// number of instances the egg should run
+    #[serde(default)]
+    pub instance_count: usize,
+    #[serde(default)]
+    pub instances: Vec<EggInstanceSummary>,
+    /// resource usage of the running instances, once sampled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metrics: Option<ProcessMetrics>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EggInstanceSummary {
+    pub index: usize,
+    pub pid: u32,
+    pub status: EggStatus,
+    pub uptime: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metrics: Option<ProcessMetrics>,
 }
 
 const WRONG_ID_MSG: &str = "missing or invalid egg id";
 const NOT_FOUND_MSG: &str = "egg not found";
+const CANNOT_RECOLLECT_MSG: &str =
+    "plugins cannot be re-collected; their config comes from the plugin executable";
+const REVEAL_UNAUTHORIZED_MSG: &str = "revealing secret env values requires a request authenticated with the api token (KURV_API_TOKEN)";
 const CANNOT_REMOVE_MSG: &str = "plugins cannot be removed via API; to remove a plugin, stop kurv and delete the executable instead";
 
 pub fn summary(request: &Request, ctx: &Context) -> Result<Response> {
@@ -75,6 +104,29 @@ pub fn summary(request: &Request, ctx: &Context) -> Result<Response> {
                 Some(ref state) => state.try_count,
                 None => 0,
             },
+            health: egg.health.as_ref().map(|_| egg.health_status()),
+            instance_count: egg.instance_count(),
+            instances: match egg.state {
+                Some(ref state) => state
+                    .instances
+                    .iter()
+                    .map(|instance| EggInstanceSummary {
+                        index: instance.index,
+                        pid: instance.pid,
+                        status: instance.status,
+                        uptime: match instance.start_time {
+                            Some(start_time) => {
+                                let now = chrono::Utc::now();
+                                humanize_duration(now.signed_duration_since(start_time))
+                            }
+                            None => "-".to_string(),
+                        },
+                        metrics: instance.metrics,
+                    })
+                    .collect(),
+                None => vec![],
+            },
+            metrics: egg.state.as_ref().and_then(|state| state.metrics),
         };
 
         summary_list.push(summary);
@@ -93,7 +145,16 @@ pub fn get(request: &Request, ctx: &Context) -> Result<Response> {
         if let Some(id) = id
             && let Some(egg) = state.get(id)
         {
-            return Ok(json(200, egg.clone()));
+            // secrets are only sent to those who know the api token
+            if query_flag(request, "reveal") {
+                if !ctx.is_authenticated(request) {
+                    return Ok(err(401, REVEAL_UNAUTHORIZED_MSG.to_string()));
+                }
+
+                return Ok(json(200, egg.clone()));
+            }
+
+            return Ok(json(200, egg.masked()));
         }
 
         return Ok(err(404, format!("{}: {}", NOT_FOUND_MSG, token)));
@@ -133,14 +194,29 @@ pub fn set_status(request: &Request, ctx: &Context, status: EggStatus) -> Result
         if let Some(id) = id
             && let Some(egg) = state.get_mut(id)
         {
+            if egg.is_stopping() && status == EggStatus::Pending {
+                return Ok(err(400, format!("egg {} is still stopping", egg.name)));
+            }
+
             match status {
                 EggStatus::Pending => {
-                    // we can only change to pending if its state is currently Stopped
+                    if egg.is_in_status(EggStatus::Scheduled) {
+                        return Ok(err(400, format!("egg {} is already scheduled", egg.name)));
+                    }
+
+                    // we can only change to pending if its state is currently Stopped or
+                    // Errored (e.g. after its restart policy gave up on it)
                     if let Some(state) = egg.state.clone()
                         && state.status != EggStatus::Stopped
+                        && state.status != EggStatus::Errored
                     {
                         return Ok(err(400, format!("egg {} is already running", egg.name)));
                     }
+
+                    // a manual start gives the egg a fresh set of retries and
+                    // starts all of its instances again
+                    egg.reset_try_count();
+                    egg.clear_instances();
                 }
                 EggStatus::Stopped => {}
                 EggStatus::PendingRemoval => {
@@ -159,8 +235,15 @@ pub fn set_status(request: &Request, ctx: &Context, status: EggStatus) -> Result
                 }
             };
 
-            egg.set_status(status);
-            return Ok(json(200, egg.clone()));
+            // an egg that is already stopping keeps doing so, we just change what
+            // happens once it's done (e.g. a stop followed by a remove)
+            if egg.is_stopping() {
+                egg.set_stop_target(status);
+            } else {
+                egg.set_status(status);
+            }
+
+            return Ok(json(200, egg.masked()));
         }
 
         return Ok(err(404, format!("{}: {}", NOT_FOUND_MSG, token)));
@@ -178,9 +261,7 @@ pub fn collect(request: &Request, ctx: &Context) -> Result<Response> {
             let state = ctx.state.clone();
             let mut state = state.lock().map_err(|_| anyhow!("failed to lock state"))?;
 
-            // TODO: if egg already exists, accept a --recollect flag to update its config
-            //       when provided, --recollect will update the egg config and set it's status
-            //       to `Restarting`
+            // existing eggs are updated through `PUT /eggs/{id}` (see `recollect`)
             if state.contains_key(egg.name.clone()) {
                 return Ok(err(
                     409,
@@ -188,6 +269,23 @@ pub fn collect(request: &Request, ctx: &Context) -> Result<Response> {
                 ));
             }
 
+            if let Some(cycle) = state.find_dependency_cycle(&egg) {
+                return Ok(err(400, format!("dependency cycle detected: {}", cycle.join(" -> "))));
+            }
+
+            let mut check = check(ctx, &egg, &request.body)?;
+            check.problems.extend(state.missing_dependencies(&egg));
+
+            if is_dry_run(request) {
+                return Ok(json(200, check));
+            }
+
+            if !check.is_ok() {
+                return Ok(invalid_egg(check));
+            }
+
+            log_warnings(&check);
+
             // set egg state as pendig
             let egg_state = match egg.state.clone() {
                 Some(state) => {
@@ -198,11 +296,7 @@ pub fn collect(request: &Request, ctx: &Context) -> Result<Response> {
                 }
                 None => EggState {
                     status: EggStatus::Pending,
-                    start_time: None,
-                    try_count: 0,
-                    error: None,
-                    pid: 0,
-                    uncsynced: false,
+                    ..Default::default()
                 },
             };
 
@@ -210,8 +304,109 @@ pub fn collect(request: &Request, ctx: &Context) -> Result<Response> {
             let id = state.collect(&egg);
             egg.id = Some(id);
 
-            Ok(json(200, egg))
+            Ok(json(200, egg.masked()))
         }
         Err(error) => Ok(err(400, format!("Invalid egg: {}", error))),
     }
 }
+
+/// replaces the config of an existing egg (command, args, cwd, env, etc.) with the one in the
+/// request body, keeping its id, and restarts it so the new config takes effect (stopped eggs
+/// stay stopped, see `Egg::recollect`).
+pub fn recollect(request: &Request, ctx: &Context) -> Result<Response> {
+    let Some(token) = request.path_params.get("egg_id") else {
+        return Ok(err(400, WRONG_ID_MSG.to_string()));
+    };
+
+    let config: Egg = match serde_json::from_str(&request.body) {
+        Ok(config) => config,
+        Err(error) => return Ok(err(400, format!("Invalid egg: {}", error))),
+    };
+
+    let state = ctx.state.clone();
+    let mut state = state.lock().map_err(|_| anyhow!("failed to lock state"))?;
+
+    let Some(id) = state.get_id_by_token(token) else {
+        return Ok(err(404, format!("{}: {}", NOT_FOUND_MSG, token)));
+    };
+
+    if let Some(cycle) = state.find_dependency_cycle(&config) {
+        return Ok(err(400, format!("dependency cycle detected: {}", cycle.join(" -> "))));
+    }
+
+    let missing_dependencies = state.missing_dependencies(&config);
+
+    let Some(egg) = state.get_mut(id) else {
+        return Ok(err(404, format!("{}: {}", NOT_FOUND_MSG, token)));
+    };
+
+    if config.name != egg.name {
+        return Ok(err(
+            400,
+            format!("egg {} can't be renamed to {} by re-collecting it", egg.name, config.name),
+        ));
+    }
+
+    if egg.is_plugin() {
+        return Ok(err(403, CANNOT_RECOLLECT_MSG.to_string()));
+    }
+
+    if egg.is_pending_removal() {
+        return Ok(err(409, format!("egg {} is being removed", egg.name)));
+    }
+
+    let mut check = check(ctx, &config, &request.body)?;
+    check.problems.extend(missing_dependencies);
+
+    if is_dry_run(request) {
+        check.changes = egg.masked_config_diff(&config);
+        return Ok(json(200, check));
+    }
+
+    if !check.is_ok() {
+        return Ok(invalid_egg(check));
+    }
+
+    log_warnings(&check);
+    egg.recollect(config);
+
+    Ok(json(200, egg.masked()))
+}
+
+/// `?dry_run=true` asks to check a request without changing anything
+fn is_dry_run(request: &Request) -> bool {
+    query_flag(request, "dry_run")
+}
+
+/// checks if a boolean query param is set (`?name=true` or `?name=1`)
+fn query_flag(request: &Request, name: &str) -> bool {
+    request.query_params.get(name).is_some_and(|value| value == "true" || value == "1")
+}
+
+/// validates the egg and checks that it could be spawned (see `Egg::check`); `body` is the
+/// raw config, where the env vars set more than once can still be told apart
+fn check(ctx: &Context, egg: &Egg, body: &str) -> Result<EggCheck> {
+    let info = ctx.info.lock().map_err(|_| anyhow!("failed to lock info"))?;
+
+    let mut check = egg.check(&info.paths.working_dir);
+    let keys: EnvKeys = serde_json::from_str(body).unwrap_or_default();
+    check.problems.splice(0..0, keys.problems());
+    check.problems.extend(egg.check_memory_sampling(info.metrics_interval));
+
+    Ok(check)
+}
+
+/// logs the warnings found when checking an egg that's being collected
+fn log_warnings(check: &EggCheck) {
+    for problem in check.problems.iter().filter(|problem| !problem.is_error()) {
+        warn!("egg {}: {} ({})", check.egg, problem.message, problem.field);
+    }
+}
+
+/// responds with the problems that prevent an egg from being collected
+fn invalid_egg(check: EggCheck) -> Response {
+    let count = check.problems.iter().filter(|problem| problem.is_error()).count();
+    let msg = format!("invalid egg: {} problem(s) found", count);
+
+    err_with_problems(400, msg, check.problems)
+}
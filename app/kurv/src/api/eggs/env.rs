@@ -1,8 +1,9 @@
 use {
-    super::{Context, err},
+    super::{Context, err, query_flag},
     crate::{
         api::eggs::{NOT_FOUND_MSG, WRONG_ID_MSG},
-        common::tcp::{Request, Response, json},
+        common::tcp::{Problem, Request, Response, err_with_problems, json},
+        kurv::EggStatus,
     },
     anyhow::{Result, anyhow},
     std::collections::HashMap,
@@ -10,15 +11,50 @@ use {
 
 /// merge an egg's environment variable configuration with existing env vars
 pub fn merge(request: &Request, ctx: &Context) -> Result<Response> {
-    update_env(request, ctx, false)
+    let vars: HashMap<String, String> = serde_json::from_str(&request.body)?;
+
+    update_env(request, ctx, |env| env.extend(vars))
 }
 
 /// replace an egg's environment variable configuration
 pub fn replace(request: &Request, ctx: &Context) -> Result<Response> {
-    update_env(request, ctx, true)
+    let vars: HashMap<String, String> = serde_json::from_str(&request.body)?;
+
+    update_env(request, ctx, |env| *env = vars)
+}
+
+/// remove env vars from an egg's environment; the body is the list of their names (the ones
+/// that aren't set are ignored)
+pub fn remove(request: &Request, ctx: &Context) -> Result<Response> {
+    let keys: Vec<String> = serde_json::from_str(&request.body)?;
+
+    update_env(request, ctx, |env| {
+        for key in &keys {
+            env.remove(key);
+        }
+    })
+}
+
+/// remove a single env var from an egg's environment
+pub fn remove_key(request: &Request, ctx: &Context) -> Result<Response> {
+    let Some(key) = request.path_params.get("key").cloned() else {
+        return Ok(err(400, "missing env var name".to_string()));
+    };
+
+    update_env(request, ctx, |env| {
+        env.remove(&key);
+    })
 }
 
-pub fn update_env(request: &Request, ctx: &Context, replace: bool) -> Result<Response> {
+/// applies `change` to the env of the egg, rejecting the new env if it has invalid names.
+///
+/// changes take effect the next time the egg is spawned, unless `?restart=true` is given, in
+/// which case a running egg is restarted right away.
+fn update_env(
+    request: &Request,
+    ctx: &Context,
+    change: impl FnOnce(&mut HashMap<String, String>),
+) -> Result<Response> {
     if let Some(token) = request.path_params.get("egg_id") {
         let state = ctx.state.clone();
         let mut state = state.lock().map_err(|_| anyhow!("failed to lock state"))?;
@@ -27,19 +63,31 @@ pub fn update_env(request: &Request, ctx: &Context, replace: bool) -> Result<Res
         if let Some(id) = id
             && let Some(egg) = state.get_mut(id)
         {
-            let env: HashMap<String, String> = serde_json::from_str(&request.body)?;
-
-            if replace {
-                egg.env = Some(env);
-            } else {
-                let existing_env = egg.env.clone().unwrap_or_default();
-                let merged_env = existing_env.into_iter().chain(env).collect();
-                egg.env = Some(merged_env);
+            let mut env = egg.env.clone().unwrap_or_default();
+            change(&mut env);
+
+            let mut updated = egg.clone();
+            updated.env = if env.is_empty() { None } else { Some(env) };
+
+            let problems: Vec<Problem> = updated
+                .validate()
+                .into_iter()
+                .filter(|problem| problem.is_error() && problem.field.starts_with("env."))
+                .collect();
+
+            if !problems.is_empty() {
+                let msg = format!("invalid env: {} problem(s) found", problems.len());
+                return Ok(err_with_problems(400, msg, problems));
             }
 
+            egg.env = updated.env;
             egg.set_synced(false);
 
-            return Ok(json(200, egg.clone()));
+            if query_flag(request, "restart") && egg.is_running() {
+                egg.set_status(EggStatus::Restarting);
+            }
+
+            return Ok(json(200, egg.masked()));
         }
 
         return Ok(err(404, format!("{}: {}", NOT_FOUND_MSG, token)));
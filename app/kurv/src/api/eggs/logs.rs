@@ -0,0 +1,230 @@
+use {
+    super::{Context, err},
+    crate::{
+        api::eggs::{NOT_FOUND_MSG, WRONG_ID_MSG},
+        common::tcp::{ChunkedWriter, Request, Response, Streamed, json},
+        kurv::{LogFollower, get_log_paths, tail_lines},
+    },
+    anyhow::Result,
+    log::debug,
+    serde::{Deserialize, Serialize},
+    std::{io::Write, path::PathBuf, thread::sleep, time::Duration},
+};
+
+/// number of lines returned when the request doesn't say how many
+const DEFAULT_LINES: usize = 20;
+
+/// how often followed log files are checked for new lines
+const FOLLOW_INTERVAL: Duration = Duration::from_millis(250);
+
+/// one of the two log files of an egg
+#[derive(PartialEq, Eq, Clone, Copy, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum LogStream {
+    Stdout,
+    Stderr,
+}
+
+/// last lines of the log files of an egg
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)] // so error responses aren't mistaken for empty logs
+pub struct EggLogs {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stdout: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stderr: Option<Vec<String>>,
+}
+
+/// a line of a followed log; the stream is sent as one json line per log line
+#[derive(Serialize, Deserialize, Debug)]
+pub struct LogLine {
+    pub stream: LogStream,
+    pub line: String,
+}
+
+/// what a logs request asks for, taken from its query params
+struct LogsQuery {
+    lines: usize,
+    streams: Vec<LogStream>,
+    instance: usize,
+    follow: bool,
+}
+
+impl LogsQuery {
+    fn parse(request: &Request) -> Result<LogsQuery, String> {
+        let params = &request.query_params;
+
+        let lines = match params.get("lines") {
+            Some(lines) => {
+                lines.parse().map_err(|_| format!("invalid number of lines: {lines}"))?
+            }
+            None => DEFAULT_LINES,
+        };
+
+        let streams = match params.get("stream").map(|s| s.as_str()) {
+            Some("stdout") => vec![LogStream::Stdout],
+            Some("stderr") => vec![LogStream::Stderr],
+            Some("both") | None => vec![LogStream::Stdout, LogStream::Stderr],
+            Some(other) => return Err(format!("invalid stream: {other}")),
+        };
+
+        let instance = match params.get("instance") {
+            Some(index) => index.parse().map_err(|_| format!("invalid instance: {index}"))?,
+            None => 0,
+        };
+
+        let follow = params.get("follow").is_some_and(|follow| follow == "true" || follow == "1");
+
+        Ok(LogsQuery {
+            lines,
+            streams,
+            instance,
+            follow,
+        })
+    }
+}
+
+/// returns the last lines of the stdout and/or stderr log files of an egg
+pub fn logs(request: &Request, ctx: &Context) -> Result<Response> {
+    let query = match LogsQuery::parse(request) {
+        Ok(query) => query,
+        Err(msg) => return Ok(err(400, msg)),
+    };
+
+    let paths = match log_paths(request, ctx, &query) {
+        Ok((_, paths)) => paths,
+        Err(response) => return Ok(response),
+    };
+
+    let mut logs = EggLogs::default();
+
+    for (stream, path) in paths {
+        let lines = tail_lines(&path, query.lines)?;
+
+        match stream {
+            LogStream::Stdout => logs.stdout = Some(lines),
+            LogStream::Stderr => logs.stderr = Some(lines),
+        }
+    }
+
+    Ok(json(200, logs))
+}
+
+/// streams the log files of an egg as they are written to (`?follow=true`), starting with
+/// their last lines. The stream ends when the client disconnects or the egg is removed.
+pub fn follow(request: &Request, ctx: &Context) -> Result<Option<Streamed>> {
+    let query = match LogsQuery::parse(request) {
+        Ok(query) if !query.follow => return Ok(None),
+        Ok(query) => query,
+        Err(msg) => return Ok(Some(Streamed::Response(err(400, msg)))),
+    };
+
+    let (id, paths) = match log_paths(request, ctx, &query) {
+        Ok(found) => found,
+        Err(response) => return Ok(Some(Streamed::Response(response))),
+    };
+
+    let state = ctx.state.clone();
+
+    // start following before reading the last lines, so nothing is missed in between
+    let mut followers: Vec<(LogStream, LogFollower)> =
+        paths.iter().map(|(stream, path)| (*stream, LogFollower::new(path))).collect();
+
+    let mut backlog = vec![];
+    for (stream, path) in paths.iter() {
+        for line in tail_lines(path, query.lines)? {
+            backlog.push(LogLine {
+                stream: *stream,
+                line,
+            });
+        }
+    }
+
+    let body = move |writer: &mut ChunkedWriter| {
+        writer.write_all(to_json_lines(backlog).as_bytes())?;
+
+        loop {
+            sleep(FOLLOW_INTERVAL);
+
+            let mut lines = vec![];
+            for (stream, follower) in followers.iter_mut() {
+                match follower.read_lines() {
+                    Ok(new_lines) => lines.extend(new_lines.into_iter().map(|line| LogLine {
+                        stream: *stream,
+                        line,
+                    })),
+                    Err(err) => debug!("failed to read log file: {}", err),
+                }
+            }
+
+            if !lines.is_empty() {
+                writer.write_all(to_json_lines(lines).as_bytes())?;
+                continue;
+            }
+
+            // nothing new, a good time to check if anyone is still listening
+            let egg_exists = state.lock().map(|state| state.get(id).is_some()).unwrap_or(false);
+
+            if !egg_exists || writer.is_closed() {
+                return Ok(());
+            }
+        }
+    };
+
+    Ok(Some(Streamed::Body {
+        headers: vec!["Content-Type: application/x-ndjson".to_string()],
+        body: Box::new(body),
+    }))
+}
+
+/// finds the egg of the request and returns its id and the paths of the requested log files,
+/// or the error response to send if it can't be found.
+fn log_paths(
+    request: &Request,
+    ctx: &Context,
+    query: &LogsQuery,
+) -> Result<(usize, Vec<(LogStream, PathBuf)>), Response> {
+    let Some(token) = request.path_params.get("egg_id") else {
+        return Err(err(400, WRONG_ID_MSG.to_string()));
+    };
+
+    let (id, log_name) = {
+        let state = ctx.state.lock().map_err(|_| err(500, "failed to lock state".to_string()))?;
+
+        let Some((id, egg)) =
+            state.get_id_by_token(token).and_then(|id| state.get(id).map(|egg| (id, egg)))
+        else {
+            return Err(err(404, format!("{}: {}", NOT_FOUND_MSG, token)));
+        };
+
+        if query.instance >= egg.instance_count() {
+            let msg = format!("egg {} has no instance {}", egg.name, query.instance);
+            return Err(err(400, msg));
+        }
+
+        (id, egg.instance_log_name(query.instance))
+    };
+
+    let info = ctx.info.lock().map_err(|_| err(500, "failed to lock info".to_string()))?;
+    let (stdout, stderr) = get_log_paths(&log_name, &info.paths.logs_dir);
+
+    let paths = query
+        .streams
+        .iter()
+        .map(|stream| match stream {
+            LogStream::Stdout => (*stream, stdout.clone()),
+            LogStream::Stderr => (*stream, stderr.clone()),
+        })
+        .collect();
+
+    Ok((id, paths))
+}
+
+/// serializes the given lines as json, one per line
+fn to_json_lines(lines: Vec<LogLine>) -> String {
+    lines
+        .iter()
+        .filter_map(|line| serde_json::to_string(line).ok())
+        .map(|line| line + "\n")
+        .collect()
+}
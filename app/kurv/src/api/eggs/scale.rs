@@ -0,0 +1,49 @@
+use {
+    super::{Context, err},
+    crate::{
+        api::eggs::{NOT_FOUND_MSG, WRONG_ID_MSG},
+        common::tcp::{Request, Response, json},
+    },
+    anyhow::{Result, anyhow},
+    serde::{Deserialize, Serialize},
+};
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ScaleRequest {
+    pub instances: u32,
+}
+
+/// changes the number of instances of an egg; new instances are spawned and extra
+/// instances are stopped on the next tick of the main loop
+pub fn scale(request: &Request, ctx: &Context) -> Result<Response> {
+    if let Some(token) = request.path_params.get("egg_id") {
+        let state = ctx.state.clone();
+        let mut state = state.lock().map_err(|_| anyhow!("failed to lock state"))?;
+        let id = state.get_id_by_token(token);
+
+        if let Some(id) = id
+            && let Some(egg) = state.get_mut(id)
+        {
+            let scale: ScaleRequest = match serde_json::from_str(&request.body) {
+                Ok(scale) => scale,
+                Err(error) => return Ok(err(400, format!("Invalid scale request: {}", error))),
+            };
+
+            if scale.instances == 0 {
+                return Ok(err(
+                    400,
+                    "an egg needs at least 1 instance; use stop to stop it instead".to_string(),
+                ));
+            }
+
+            egg.instances = Some(scale.instances);
+            egg.set_synced(false);
+
+            return Ok(json(200, egg.masked()));
+        }
+
+        return Ok(err(404, format!("{}: {}", NOT_FOUND_MSG, token)));
+    }
+
+    Ok(err(400, WRONG_ID_MSG.to_string()))
+}
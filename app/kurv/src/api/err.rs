@@ -12,6 +12,7 @@ pub fn not_allowed(_request: &Request, _ctx: &Context) -> Result<Response> {
             code: 405,
             status: "Method Not Allowed".to_string(),
             message: "The method specified in the request is not allowed.".to_string(),
+            problems: vec![],
         },
     ))
 }
@@ -0,0 +1,182 @@
+use {
+    super::Context,
+    crate::{
+        common::{
+            Info,
+            tcp::{Request, Response},
+        },
+        kurv::{Egg, EggStatus, KurvState},
+    },
+    anyhow::{Result, anyhow},
+    chrono::Local,
+    std::fmt::Write,
+};
+
+const CONTENT_TYPE: &str = "Content-Type: text/plain; version=0.0.4; charset=utf-8";
+
+/// every status an egg can be in, for the `kurv_egg_status` gauges
+const STATUSES: [EggStatus; 8] = [
+    EggStatus::Pending,
+    EggStatus::Running,
+    EggStatus::Stopping,
+    EggStatus::Stopped,
+    EggStatus::Errored,
+    EggStatus::PendingRemoval,
+    EggStatus::Restarting,
+    EggStatus::Scheduled,
+];
+
+/// metrics of the server and its eggs, in the prometheus text exposition format
+pub fn metrics(_request: &Request, ctx: &Context) -> Result<Response> {
+    let state = ctx.state.clone();
+    let state = state.lock().map_err(|_| anyhow!("failed to lock state"))?;
+    let info = ctx.info.clone();
+    let info = info.lock().map_err(|_| anyhow!("failed to lock info"))?;
+
+    Ok(Response {
+        status: 200,
+        headers: vec![CONTENT_TYPE.to_string()],
+        body: render(&info, &state).into_bytes(),
+    })
+}
+
+/// renders the metrics of the server and its eggs
+pub fn render(info: &Info, state: &KurvState) -> String {
+    let mut out = Exposition::default();
+    let eggs: Vec<&Egg> = state.eggs.values().collect();
+
+    out.family("kurv_info", "gauge", "build information of the kurv server");
+    out.sample(
+        "kurv_info",
+        &[("version", &info.version), ("version_name", info.version_name.unwrap_or(""))],
+        1.0,
+    );
+
+    out.family("kurv_eggs", "gauge", "number of eggs, plugins excluded");
+    out.sample("kurv_eggs", &[], state.get_eggs().len() as f64);
+
+    out.family("kurv_plugins", "gauge", "number of plugins");
+    out.sample("kurv_plugins", &[], state.get_plugins().len() as f64);
+
+    out.family("kurv_egg_up", "gauge", "whether the egg is running (1) or not (0)");
+    for egg in &eggs {
+        out.sample("kurv_egg_up", &labels(egg), if egg.is_running() { 1.0 } else { 0.0 });
+    }
+
+    out.family("kurv_egg_status", "gauge", "current status of the egg, 1 for the one it's in");
+    for egg in &eggs {
+        let current = egg.state.as_ref().map(|state| state.status).unwrap_or_default();
+
+        for status in STATUSES {
+            let status_label = format!("{status:?}").to_lowercase();
+            let mut labels = labels(egg);
+            labels.push(("status", &status_label));
+
+            out.sample("kurv_egg_status", &labels, if status == current { 1.0 } else { 0.0 });
+        }
+    }
+
+    out.family("kurv_egg_instances", "gauge", "number of running instances of the egg");
+    for egg in &eggs {
+        let running = egg
+            .state
+            .iter()
+            .flat_map(|state| state.instances.iter())
+            .filter(|instance| instance.status == EggStatus::Running)
+            .count();
+
+        out.sample("kurv_egg_instances", &labels(egg), running as f64);
+    }
+
+    out.family("kurv_egg_restarts_total", "counter", "number of times the egg was restarted");
+    for egg in &eggs {
+        let restarts = egg.state.as_ref().map(|state| state.restarts).unwrap_or(0);
+        out.sample("kurv_egg_restarts_total", &labels(egg), restarts as f64);
+    }
+
+    out.family("kurv_egg_retries", "gauge", "consecutive failed attempts to run the egg");
+    for egg in &eggs {
+        let retries = egg.state.as_ref().map(|state| state.try_count).unwrap_or(0);
+        out.sample("kurv_egg_retries", &labels(egg), retries as f64);
+    }
+
+    out.family("kurv_egg_uptime_seconds", "gauge", "seconds since the egg was started");
+    for egg in &eggs {
+        let uptime = match egg.state.as_ref().and_then(|state| state.start_time) {
+            Some(start_time) if egg.is_running() => (Local::now() - start_time).num_seconds(),
+            _ => 0,
+        };
+
+        out.sample("kurv_egg_uptime_seconds", &labels(egg), uptime.max(0) as f64);
+    }
+
+    out.family("kurv_egg_last_exit_code", "gauge", "exit code of the last instance that exited");
+    for egg in &eggs {
+        if let Some(code) = egg.state.as_ref().and_then(|state| state.exit_code) {
+            out.sample("kurv_egg_last_exit_code", &labels(egg), code as f64);
+        }
+    }
+
+    let sampled: Vec<_> = eggs
+        .iter()
+        .filter_map(|egg| egg.state.as_ref().and_then(|state| state.metrics).map(|m| (egg, m)))
+        .collect();
+
+    out.family("kurv_egg_cpu_percent", "gauge", "cpu usage of the egg, in % of one cpu");
+    for (egg, metrics) in &sampled {
+        out.sample("kurv_egg_cpu_percent", &labels(egg), metrics.cpu);
+    }
+
+    out.family("kurv_egg_memory_bytes", "gauge", "resident memory of the egg");
+    for (egg, metrics) in &sampled {
+        out.sample("kurv_egg_memory_bytes", &labels(egg), metrics.memory as f64);
+    }
+
+    out.family("kurv_egg_threads", "gauge", "number of threads of the egg");
+    for (egg, metrics) in &sampled {
+        out.sample("kurv_egg_threads", &labels(egg), metrics.threads as f64);
+    }
+
+    out.family("kurv_egg_open_fds", "gauge", "number of files the egg has open");
+    for (egg, metrics) in &sampled {
+        out.sample("kurv_egg_open_fds", &labels(egg), metrics.fds as f64);
+    }
+
+    out.text
+}
+
+/// the labels that identify an egg
+fn labels(egg: &Egg) -> Vec<(&'static str, &str)> {
+    let kind = if egg.is_plugin() { "plugin" } else { "egg" };
+    vec![("egg", egg.name.as_str()), ("kind", kind)]
+}
+
+/// writes metrics in the prometheus text exposition format
+#[derive(Default)]
+struct Exposition {
+    text: String,
+}
+
+impl Exposition {
+    /// starts a metric family, which its samples have to follow
+    fn family(&mut self, name: &str, kind: &str, help: &str) {
+        let _ = writeln!(self.text, "# HELP {name} {help}");
+        let _ = writeln!(self.text, "# TYPE {name} {kind}");
+    }
+
+    fn sample(&mut self, name: &str, labels: &[(&str, &str)], value: f64) {
+        let labels: Vec<String> =
+            labels.iter().map(|(label, value)| format!("{label}=\"{}\"", escape(value))).collect();
+
+        if labels.is_empty() {
+            let _ = writeln!(self.text, "{name} {value}");
+        } else {
+            let _ = writeln!(self.text, "{name}{{{}}} {value}", labels.join(","));
+        }
+    }
+}
+
+/// escapes a label value: backslashes, double quotes and line feeds
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
@@ -1,10 +1,11 @@
 pub mod eggs;
 pub mod err;
+pub mod metrics;
 pub mod status;
 
 use {
     crate::{
-        common::tcp::{Handler, Request, Response, err, handle as handle_tcp},
+        common::tcp::{Handler, Request, Response, Streamed, err, handle as handle_tcp},
         kurv::{InfoMtx, KurvStateMtx},
     },
     anyhow::Result,
@@ -12,15 +13,53 @@ use {
     std::net::TcpListener,
 };
 
+const UNAUTHORIZED_MSG: &str =
+    "changing eggs requires a request authenticated with the api token (KURV_API_TOKEN)";
+
 pub struct Context {
     pub state: KurvStateMtx,
     pub info: InfoMtx,
 }
 
+impl Context {
+    /// checks if the request carries the api token of the server
+    /// (`Authorization: Bearer <token>`); if the server has no token, no request is authenticated
+    pub fn is_authenticated(&self, request: &Request) -> bool {
+        let Some(token) = self.info.lock().ok().and_then(|info| info.api_token.clone()) else {
+            return false;
+        };
+
+        request.headers.iter().any(|header| {
+            let Some((name, value)) = header.split_once(':') else {
+                return false;
+            };
+
+            name.trim().eq_ignore_ascii_case("authorization")
+                && value.trim().strip_prefix("Bearer ").is_some_and(|given| {
+                    // compared in constant time, to not give away how much of it matched
+                    given.len() == token.len()
+                        && given.bytes().zip(token.bytes()).fold(0, |diff, (a, b)| diff | (a ^ b))
+                            == 0
+                })
+        })
+    }
+
+    /// checks if the request can change anything: once the server has an api token, only the
+    /// requests that carry it can, as whoever changes an egg (e.g. its command) can get its
+    /// secrets too
+    pub fn can_change(&self, request: &Request) -> bool {
+        let has_token = self.info.lock().is_ok_and(|info| info.api_token.is_some());
+
+        !has_token || self.is_authenticated(request)
+    }
+}
+
 type RouteHandler = fn(&Request, &Context) -> Result<Response>;
 type RouteRegex = &'static str;
 type RouteMethod = &'static str;
 type RouteDef = (RouteMethod, RouteRegex, RouteHandler);
+type StreamRouteHandler = fn(&Request, &Context) -> Result<Option<Streamed>>;
+type StreamRouteDef = (RouteMethod, RouteRegex, StreamRouteHandler);
 
 struct Router {
     info: InfoMtx,
@@ -34,28 +73,40 @@ impl Router {
             ("OPTIONS", ".*", Self::handle_cors_preflight), // Handle CORS preflight
             ("GET", "/", status::status),
             ("GET", "/status", status::status),
+            ("GET", "/metrics", metrics::metrics),
             ("GET", "/eggs", eggs::summary),
             ("POST", "/eggs", eggs::collect),
             ("POST", "/eggs/(?P<egg_id>.*)/stop", eggs::stop),
             ("POST", "/eggs/(?P<egg_id>.*)/start", eggs::start),
             ("POST", "/eggs/(?P<egg_id>.*)/restart", eggs::restart),
             ("POST", "/eggs/(?P<egg_id>.*)/remove", eggs::remove),
+            ("POST", "/eggs/(?P<egg_id>.*)/scale", eggs::scale::scale),
             ("PUT", "/eggs/(?P<egg_id>.*)/env", eggs::env::replace),
             ("PATCH", "/eggs/(?P<egg_id>.*)/env", eggs::env::merge),
+            ("DELETE", "/eggs/(?P<egg_id>.*)/env", eggs::env::remove),
+            ("DELETE", "/eggs/(?P<egg_id>.*)/env/(?P<key>[^/]+)", eggs::env::remove_key),
+            ("GET", "/eggs/(?P<egg_id>.*)/logs", eggs::logs::logs),
             ("GET", "/eggs/(?P<egg_id>.*)", eggs::get),
+            ("PUT", "/eggs/(?P<egg_id>.*)", eggs::recollect),
             (".*", ".*", err::not_allowed), // last resort
         ]
     }
 
+    /// returns the routes whose response can be streamed; their handlers return `None` when
+    /// the request doesn't ask for a stream, so it's handled by the regular routes instead
+    fn stream_routes(&self) -> Vec<StreamRouteDef> {
+        vec![("GET", "/eggs/(?P<egg_id>.*)/logs", eggs::logs::follow)]
+    }
+
     fn compiled_routes(&self) -> Vec<(regex_lite::Regex, RouteHandler)> {
-        self.routes()
-            .iter()
-            .map(|&(method, regex_raw, handler)| {
-                let route_re = regex_lite::Regex::new(format!("^{method} {regex_raw}/?$").as_str())
-                    .expect("Invalid regex pattern on route");
-                (route_re, handler)
-            })
-            .collect()
+        compile(self.routes())
+    }
+
+    fn context(&self) -> Context {
+        Context {
+            state: self.state.clone(),
+            info: self.info.clone(),
+        }
     }
 
     /// handles CORS preflight requests (OPTIONS method)
@@ -77,6 +128,10 @@ impl Handler for Router {
 
         let mut result = err(500, "internal server error".to_string());
 
+        if !matches!(method, "GET" | "HEAD" | "OPTIONS") && !self.context().can_change(request) {
+            return err(401, UNAUTHORIZED_MSG.to_string());
+        }
+
         for (route_re, handler) in compiled_routes {
             let route = format!("{method} {path}");
             let route_str = route.as_str();
@@ -88,11 +143,7 @@ impl Handler for Router {
                     request.path_params.insert(key.to_string(), value.to_string());
                 }
 
-                let ctx = Context {
-                    state: self.state.clone(),
-                    info: self.info.clone(),
-                };
-                result = match handler(request, &ctx) {
+                result = match handler(request, &self.context()) {
                     Ok(response) => response,
                     Err(e) => err(500, format!("{}", e)),
                 };
@@ -102,6 +153,38 @@ impl Handler for Router {
 
         result
     }
+
+    fn stream(&self, request: &mut Request) -> Option<Streamed> {
+        let route = format!("{} {}", request.method, request.path);
+
+        for (route_re, handler) in compile(self.stream_routes()) {
+            if let Some(capture) = route_re.captures(&route) {
+                for key in route_re.capture_names().flatten() {
+                    let value = capture.name(key).map(|v| v.as_str()).unwrap_or("");
+                    request.path_params.insert(key.to_string(), value.to_string());
+                }
+
+                return match handler(request, &self.context()) {
+                    Ok(streamed) => streamed,
+                    Err(e) => Some(Streamed::Response(err(500, format!("{}", e)))),
+                };
+            }
+        }
+
+        None
+    }
+}
+
+/// compiles the regex of each route, matching its method and path
+fn compile<H: Copy>(routes: Vec<(RouteMethod, RouteRegex, H)>) -> Vec<(regex_lite::Regex, H)> {
+    routes
+        .iter()
+        .map(|&(method, regex_raw, handler)| {
+            let route_re = regex_lite::Regex::new(format!("^{method} {regex_raw}/?$").as_str())
+                .expect("Invalid regex pattern on route");
+            (route_re, handler)
+        })
+        .collect()
 }
 
 /// starts the api server
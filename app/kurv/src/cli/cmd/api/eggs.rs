@@ -1,8 +1,17 @@
 use {
-    super::{Api, ParsedResponse, parse_response},
-    crate::{api, kurv::Egg, printth},
-    anyhow::Result,
-    api::eggs::EggsSummaryList,
+    super::{Api, ApiResponse, ParsedResponse, parse_response},
+    crate::{
+        api,
+        cli::cmd::validate::print_problems,
+        common::tcp::ErrorResponse,
+        kurv::{ConfigChange, Egg, EggCheck},
+        printth,
+    },
+    anyhow::{Result, anyhow},
+    api::eggs::{
+        EggsSummaryList,
+        logs::{EggLogs, LogLine},
+    },
     std::{collections::HashMap, process::exit},
 };
 
@@ -36,16 +45,49 @@ impl Api {
         Ok(eggs_summary_list)
     }
 
-    pub fn egg(&self, id: &str) -> Result<Egg> {
+    /// gets an egg; with `reveal`, the values of its secret env vars aren't masked (which
+    /// requires the api token)
+    pub fn egg(&self, id: &str, reveal: bool) -> Result<Egg> {
+        let query = if reveal { "?reveal=true" } else { "" };
+        let response = self.get(format!("/eggs/{}{}", id, query).as_ref())?;
+        let maybe_egg: ParsedResponse<Egg> = parse_response(&response)?;
+
+        match maybe_egg {
+            ParsedResponse::Failure(err) => {
+                printth!("<error>[err: {}]</error> {}\n", err.code, err.message);
+                exit(1)
+            }
+
+            ParsedResponse::Success(egg) => Ok(egg),
+        }
+    }
+
+    /// looks for an egg, returning `None` if it doesn't exist
+    pub fn find_egg(&self, id: &str) -> Result<Option<Egg>> {
         let response = self.get(format!("/eggs/{}", id).as_ref())?;
         let maybe_egg: ParsedResponse<Egg> = parse_response(&response)?;
 
         match maybe_egg {
+            ParsedResponse::Failure(err) if err.code == 404 => Ok(None),
             ParsedResponse::Failure(err) => {
                 printth!("<error>[err: {}]</error> {}\n", err.code, err.message);
                 exit(1)
             }
 
+            ParsedResponse::Success(egg) => Ok(Some(egg)),
+        }
+    }
+
+    pub fn eggs_put(&self, route: &str, body: &str) -> Result<Egg> {
+        let response = self.put(format!("/eggs{route}").as_ref(), body)?;
+        let maybe_egg: ParsedResponse<Egg> = parse_response(&response)?;
+
+        match maybe_egg {
+            ParsedResponse::Failure(err) => {
+                print_failure(&err);
+                exit(1)
+            }
+
             ParsedResponse::Success(egg) => Ok(egg),
         }
     }
@@ -56,7 +98,7 @@ impl Api {
 
         match maybe_egg {
             ParsedResponse::Failure(err) => {
-                printth!("<error>[err: {}]</error> {}\n", err.code, err.message);
+                print_failure(&err);
                 exit(1)
             }
 
@@ -64,33 +106,142 @@ impl Api {
         }
     }
 
-    /// update egg environment variables, either merging or replacing them
+    /// checks collecting an egg, or re-collecting it if a route like `/{id}` is given, without
+    /// changing anything
+    pub fn eggs_dry_run(&self, route: &str, body: &str) -> Result<ParsedResponse<EggCheck>> {
+        let path = format!("/eggs{route}?dry_run=true");
+
+        let response = if route.is_empty() {
+            self.post(&path, body)?
+        } else {
+            self.put(&path, body)?
+        };
+
+        parse_response(&response)
+    }
+
+    /// returns what would change in the config of the egg with the given id if it was
+    /// re-collected with `egg`, as the server sees it (the values of secrets are masked)
+    pub fn egg_changes(&self, id: usize, egg: &Egg) -> Result<Vec<ConfigChange>> {
+        let body = serde_json::to_string(egg)?;
+
+        match self.eggs_dry_run(&format!("/{id}"), &body)? {
+            ParsedResponse::Failure(err) => {
+                print_failure(&err);
+                exit(1)
+            }
+
+            ParsedResponse::Success(check) => Ok(check.changes),
+        }
+    }
+
+    /// update egg environment variables, either merging or replacing them; with `restart`,
+    /// a running egg is restarted to apply the changes right away
     pub fn update_egg_env(
         &self,
         id: &str,
         env: &HashMap<String, String>,
         replace: bool,
+        restart: bool,
     ) -> Result<Egg> {
         // merge: HTTP PATCH /eggs/{id}/env
         // replace: HTTP PUT /eggs/{id}/env
 
         let body = serde_json::to_string(&env)?;
+        let path = format!("/eggs/{}/env{}", id, restart_query(restart));
 
         let response = if replace {
-            self.put(format!("/eggs/{}/env", id).as_ref(), body.as_str())?
+            self.put(&path, body.as_str())?
         } else {
-            self.patch(format!("/eggs/{}/env", id).as_ref(), body.as_str())?
+            self.patch(&path, body.as_str())?
         };
 
-        let maybe_egg: ParsedResponse<Egg> = parse_response(&response)?;
+        parse_egg_env_response(&response)
+    }
 
-        match maybe_egg {
+    /// removes env vars from an egg (the ones that aren't set are ignored); with `restart`, a
+    /// running egg is restarted to apply the changes right away
+    pub fn remove_egg_env(&self, id: &str, keys: &[String], restart: bool) -> Result<Egg> {
+        // HTTP DELETE /eggs/{id}/env
+        let body = serde_json::to_string(&keys)?;
+        let path = format!("/eggs/{}/env{}", id, restart_query(restart));
+        let response = self.delete(&path, body.as_str())?;
+
+        parse_egg_env_response(&response)
+    }
+
+    /// gets the last lines of the logs of an egg; `query` holds the query params of the
+    /// request (e.g. `lines=10&stream=stderr`)
+    pub fn egg_logs(&self, id: &str, query: &str) -> Result<EggLogs> {
+        let response = self.get(format!("/eggs/{}/logs?{}", id, query).as_ref())?;
+        let maybe_logs: ParsedResponse<EggLogs> = parse_response(&response)?;
+
+        match maybe_logs {
             ParsedResponse::Failure(err) => {
                 printth!("<error>[err: {}]</error> {}\n", err.code, err.message);
                 exit(1)
             }
 
-            ParsedResponse::Success(egg) => Ok(egg),
+            ParsedResponse::Success(logs) => Ok(logs),
+        }
+    }
+
+    /// follows the logs of an egg, calling `on_line` for each new line until the server ends
+    /// the stream (e.g. because the egg was removed)
+    pub fn follow_egg_logs(
+        &self,
+        id: &str,
+        query: &str,
+        mut on_line: impl FnMut(LogLine),
+    ) -> Result<()> {
+        let path = format!("/eggs/{}/logs?{}&follow=true", id, query);
+
+        let response = self.get_stream(&path, |line| {
+            let line: LogLine = serde_json::from_str(line)
+                .map_err(|_| anyhow!("couldn't parse kurv server response"))?;
+            on_line(line);
+            Ok(())
+        })?;
+
+        // not streamed: the server couldn't follow the logs, let's see why
+        if let Some(response) = response {
+            match parse_response::<EggLogs>(&response)? {
+                ParsedResponse::Failure(err) => {
+                    printth!("<error>[err: {}]</error> {}\n", err.code, err.message);
+                    exit(1)
+                }
+                ParsedResponse::Success(_) => {}
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// prints an error response, with the problems it lists (e.g. for an invalid egg)
+fn restart_query(restart: bool) -> &'static str {
+    if restart { "?restart=true" } else { "" }
+}
+
+/// returns the egg from the response of a change to its env, exiting if it failed
+fn parse_egg_env_response(response: &ApiResponse) -> Result<Egg> {
+    let maybe_egg: ParsedResponse<Egg> = parse_response(response)?;
+
+    match maybe_egg {
+        ParsedResponse::Failure(err) => {
+            print_failure(&err);
+            exit(1)
         }
+
+        ParsedResponse::Success(egg) => Ok(egg),
+    }
+}
+
+fn print_failure(err: &ErrorResponse) {
+    printth!("<error>[err: {}]</error> {}\n", err.code, err.message);
+
+    if !err.problems.is_empty() {
+        print_problems(&err.problems);
+        println!();
     }
 }
@@ -1,10 +1,10 @@
 mod eggs;
 use {
-    crate::common::tcp::ErrorResponse,
+    crate::common::{API_TOKEN_KEY, tcp::ErrorResponse},
     anyhow::{Result, anyhow},
     serde::Deserialize,
     std::{
-        io::{Read, Write},
+        io::{BufRead, BufReader, Read, Write},
         net::TcpStream,
         str,
     },
@@ -23,6 +23,9 @@ pub(crate) struct ApiResponse {
 pub struct Api {
     pub host: String,
     pub port: u16,
+
+    /// token sent to authenticate the requests, from the `KURV_API_TOKEN` env var
+    pub token: Option<String>,
 }
 
 impl Api {
@@ -33,7 +36,17 @@ impl Api {
             .parse::<u16>()
             .unwrap_or(58787);
 
-        Api { host, port }
+        let token = std::env::var(API_TOKEN_KEY).ok().filter(|token| !token.is_empty());
+
+        Api { host, port, token }
+    }
+
+    /// the request header lines that go after the `Host` header
+    fn extra_headers(&self) -> String {
+        match &self.token {
+            Some(token) => format!("Authorization: Bearer {token}\r\n"),
+            None => String::new(),
+        }
     }
 
     // Private helper method to perform HTTP request and get response
@@ -46,8 +59,14 @@ impl Api {
             None => String::from("\r\n"),
         };
 
-        let request =
-            format!("{} {} HTTP/1.1\r\nHost: {}\r\n{}\r\n", method, path, self.host, body_str);
+        let request = format!(
+            "{} {} HTTP/1.1\r\nHost: {}\r\n{}{}\r\n",
+            method,
+            path,
+            self.host,
+            self.extra_headers(),
+            body_str
+        );
 
         stream
             .write_all(request.as_bytes())
@@ -67,6 +86,70 @@ impl Api {
         Ok(ApiResponse { headers, body })
     }
 
+    /// performs an HTTP GET request whose response is streamed (chunked), calling `on_line`
+    /// for each line of the body as soon as it arrives, until the server ends the stream.
+    ///
+    /// if the server answers with a regular (not streamed) response, it's returned instead
+    /// so it can be parsed as usual.
+    pub(crate) fn get_stream(
+        &self,
+        path: &str,
+        mut on_line: impl FnMut(&str) -> Result<()>,
+    ) -> Result<Option<ApiResponse>> {
+        let mut stream = TcpStream::connect(format!("{}:{}", self.host, self.port))
+            .map_err(|_| anyhow!("failed to connect to api server"))?;
+
+        let request =
+            format!("GET {} HTTP/1.1\r\nHost: {}\r\n{}\r\n", path, self.host, self.extra_headers());
+        stream
+            .write_all(request.as_bytes())
+            .map_err(|_| anyhow!("failed to write to api server"))?;
+
+        let mut reader = BufReader::new(stream);
+        let read_err = |_| anyhow!("failed to read from api server");
+
+        // read the headers
+        let mut headers = String::new();
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line).map_err(read_err)? == 0 || line.trim().is_empty() {
+                break;
+            }
+            headers.push_str(&line);
+        }
+
+        if !headers.to_lowercase().contains("transfer-encoding: chunked") {
+            let mut body = String::new();
+            reader.read_to_string(&mut body).map_err(read_err)?;
+            return Ok(Some(ApiResponse { headers, body }));
+        }
+
+        // read the chunks, handing over each complete line
+        let mut pending: Vec<u8> = Vec::new();
+        loop {
+            let mut size_line = String::new();
+            reader.read_line(&mut size_line).map_err(read_err)?;
+
+            let size = usize::from_str_radix(size_line.trim(), 16)
+                .map_err(|_| anyhow!("invalid chunk received from api server"))?;
+
+            if size == 0 {
+                break;
+            }
+
+            let mut chunk = vec![0u8; size + 2]; // chunk + \r\n
+            reader.read_exact(&mut chunk).map_err(read_err)?;
+            pending.extend_from_slice(&chunk[..size]);
+
+            while let Some(pos) = pending.iter().position(|&byte| byte == b'\n') {
+                let line: Vec<u8> = pending.drain(..=pos).collect();
+                on_line(String::from_utf8_lossy(&line[..pos]).as_ref())?;
+            }
+        }
+
+        Ok(None)
+    }
+
     // Method to perform HTTP GET request
     pub(crate) fn get(&self, path: &str) -> Result<ApiResponse> {
         self.request("GET", path, None)
@@ -83,15 +166,13 @@ impl Api {
     }
 
     // Method to perform HTTP PUT request
-    #[allow(dead_code)]
     pub(crate) fn put(&self, path: &str, body: &str) -> Result<ApiResponse> {
         self.request("PUT", path, Some(body))
     }
 
     // Method to perform HTTP DELETE request
-    #[allow(dead_code)]
-    pub(crate) fn delete(&self, path: &str) -> Result<ApiResponse> {
-        self.request("DELETE", path, None)
+    pub(crate) fn delete(&self, path: &str, body: &str) -> Result<ApiResponse> {
+        self.request("DELETE", path, Some(body))
     }
 }
 
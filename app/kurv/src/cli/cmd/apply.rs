@@ -0,0 +1,381 @@
+use {
+    crate::{
+        cli::{
+            cmd::{
+                EggKind,
+                api::{Api, ParsedResponse},
+                collect::{err_problems, print_changes, print_check},
+                is_option_or_flag, wants_help, wants_raw,
+            },
+            components::{Component, Help},
+        },
+        common::tcp::{ErrorResponse, Problem},
+        kurv::{Basket, ConfigChange, Egg, EggCheck, EggStatus, MISSING_DEPENDENCY_MSG},
+        printth,
+    },
+    anyhow::{Result, anyhow},
+    indoc::indoc,
+    pico_args::Arguments,
+    serde::Serialize,
+    std::{path::PathBuf, process::exit},
+};
+
+/// what `kurv apply` will do with an egg
+enum Step {
+    /// collect a new egg
+    Add(Egg),
+    /// re-collect an egg whose config changed
+    Update {
+        id: usize,
+        egg: Egg,
+        changes: Vec<ConfigChange>,
+    },
+    /// leave an egg as it is
+    Keep(String),
+    /// remove an egg that isn't in the basket anymore
+    Prune { id: usize, name: String },
+}
+
+/// a step of the plan, as printed with `--json`
+#[derive(Serialize)]
+struct PlannedStep<'a> {
+    action: &'static str,
+    egg: &'a str,
+    #[serde(skip_serializing_if = "<[ConfigChange]>::is_empty")]
+    changes: &'a [ConfigChange],
+}
+
+impl Step {
+    fn planned(&self) -> PlannedStep<'_> {
+        let (action, egg, changes): (_, &str, &[ConfigChange]) = match self {
+            Step::Add(egg) => ("add", &egg.name, &[]),
+            Step::Update { egg, changes, .. } => ("update", &egg.name, changes),
+            Step::Keep(name) => ("keep", name, &[]),
+            Step::Prune { name, .. } => ("prune", name, &[]),
+        };
+
+        PlannedStep {
+            action,
+            egg,
+            changes,
+        }
+    }
+
+    fn changes_anything(&self) -> bool {
+        !matches!(self, Step::Keep(_))
+    }
+}
+
+/// applies a basket file: collects its new eggs, re-collects the changed ones and, with
+/// `--prune`, removes the eggs it doesn't list anymore
+pub fn run(args: &mut Arguments) -> Result<()> {
+    if wants_help(args) {
+        return help();
+    }
+
+    let api = Api::new();
+    let raw = wants_raw(args);
+    let prune = args.contains("--prune");
+    let dry_run = args.contains("--dry-run");
+    let cmd_arg: Result<Option<String>> =
+        args.opt_free_from_str().map_err(|_| anyhow!("wrong usage"));
+
+    let Ok(Some(path)) = cmd_arg else {
+        return help();
+    };
+
+    if is_option_or_flag(&path) {
+        return Err(anyhow!("wrong usage"));
+    }
+
+    let basket = Basket::load(&PathBuf::from(path))?;
+    let steps = plan(&api, &basket, prune)?;
+
+    // the server checks the dependencies of one egg at a time, so the ones of the whole basket
+    // are checked before changing anything
+    let others = remaining_eggs(&api, &basket, &steps)?;
+    let dependency_problems = basket.check_dependencies(&others);
+
+    if dry_run {
+        return dry_run_steps(&api, &steps, &dependency_problems, raw);
+    }
+
+    if let Some((name, problem)) = dependency_problems.first() {
+        return Err(anyhow!("egg {name} can't be applied: {}", problem.message));
+    }
+
+    if raw {
+        let planned: Vec<PlannedStep> = steps.iter().map(Step::planned).collect();
+        printth!("{}", serde_json::to_string_pretty(&planned)?);
+    } else {
+        print_plan(&steps);
+    }
+
+    if !steps.iter().any(Step::changes_anything) {
+        if !raw {
+            printth!("<dim>nothing to do, all eggs are up to date</dim>\n");
+        }
+
+        return Ok(());
+    }
+
+    for step in steps {
+        match step {
+            Step::Add(egg) => {
+                api.eggs_post("", &serde_json::to_string(&egg)?)?;
+            }
+            Step::Update { id, egg, .. } => {
+                api.eggs_put(&format!("/{id}"), &serde_json::to_string(&egg)?)?;
+            }
+            Step::Prune { id, .. } => {
+                api.eggs_post(&format!("/{id}/remove"), "")?;
+            }
+            Step::Keep(_) => {}
+        }
+    }
+
+    if !raw {
+        printth!(
+            "{}",
+            indoc! {
+                "basket applied
+
+                <head><b>i</b></head> you can check the status of the eggs by running:
+                    <dim>$</dim> <white><b>kurv</b></white> list
+                "
+            }
+        );
+    }
+
+    Ok(())
+}
+
+/// compares the eggs of the basket with the ones in the server, returning what has to be done
+/// with each of them
+fn plan(api: &Api, basket: &Basket, prune: bool) -> Result<Vec<Step>> {
+    let mut steps = vec![];
+
+    for egg in basket.eggs.iter() {
+        let Some(current) = api.find_egg(&egg.name)? else {
+            steps.push(Step::Add(egg.clone()));
+            continue;
+        };
+
+        if current.is_plugin() {
+            return Err(anyhow!("{} is a plugin, it can't be applied from a basket", egg.name));
+        }
+
+        if current.is_pending_removal() {
+            return Err(anyhow!("egg {} is being removed, try again later", egg.name));
+        }
+
+        // the server tells what changes, as only it knows the values of secrets
+        let changes = api.egg_changes(current.id.unwrap_or(0), egg)?;
+
+        if changes.is_empty() {
+            steps.push(Step::Keep(egg.name.clone()));
+        } else {
+            steps.push(Step::Update {
+                id: current.id.unwrap_or(0),
+                egg: egg.clone(),
+                changes,
+            });
+        }
+    }
+
+    if prune {
+        for summary in api.eggs_summary(&EggKind::Eggs)?.0 {
+            let listed = basket.eggs.iter().any(|egg| egg.name == summary.name);
+
+            if !listed && summary.status != EggStatus::PendingRemoval {
+                steps.push(Step::Prune {
+                    id: summary.id,
+                    name: summary.name,
+                });
+            }
+        }
+    }
+
+    Ok(steps)
+}
+
+/// returns the eggs in the server that the plan leaves as they are (the ones that aren't in
+/// the basket nor pruned), which the eggs of the basket might depend on
+fn remaining_eggs(api: &Api, basket: &Basket, steps: &[Step]) -> Result<Vec<Egg>> {
+    // without dependencies in the basket, there's nothing to check them against
+    if basket.eggs.iter().all(|egg| egg.dependencies().is_empty()) {
+        return Ok(vec![]);
+    }
+
+    let mut eggs = vec![];
+
+    for kind in [EggKind::Eggs, EggKind::Plugins] {
+        for summary in api.eggs_summary(&kind)?.0 {
+            let listed = basket.eggs.iter().any(|egg| egg.name == summary.name);
+            let pruned = steps
+                .iter()
+                .any(|step| matches!(step, Step::Prune { id, .. } if *id == summary.id));
+
+            if listed || pruned || summary.status == EggStatus::PendingRemoval {
+                continue;
+            }
+
+            eggs.push(api.egg(&summary.id.to_string(), false)?);
+        }
+    }
+
+    Ok(eggs)
+}
+
+/// checks the eggs that would be collected or re-collected, without changing anything, and
+/// exits with an error code if any of them wouldn't work
+fn dry_run_steps(
+    api: &Api,
+    steps: &[Step],
+    dependency_problems: &[(String, Problem)],
+    raw: bool,
+) -> Result<()> {
+    let mut checks = vec![];
+
+    for step in steps {
+        let (route, egg) = match step {
+            Step::Add(egg) => (String::new(), egg),
+            Step::Update { id, egg, .. } => (format!("/{id}"), egg),
+            Step::Keep(name) => {
+                // an egg that doesn't change can still lose a dependency to `--prune`
+                let problems: Vec<Problem> = dependency_problems
+                    .iter()
+                    .filter(|(egg, _)| egg == name)
+                    .map(|(_, problem)| problem.clone())
+                    .collect();
+
+                if !problems.is_empty() {
+                    let err = ErrorResponse {
+                        code: 400,
+                        status: "Bad Request".to_string(),
+                        message: "invalid dependencies".to_string(),
+                        problems,
+                    };
+                    checks.push((name.as_str(), ParsedResponse::Failure(err)));
+                }
+
+                continue;
+            }
+            Step::Prune { .. } => continue,
+        };
+
+        let mut check = api.eggs_dry_run(&route, &serde_json::to_string(egg)?)?;
+
+        if let ParsedResponse::Success(check) = &mut check {
+            // the server only knows the eggs it has, the ones of the basket are checked above
+            check.problems.retain(|problem| !problem.message.ends_with(MISSING_DEPENDENCY_MSG));
+
+            let problems = dependency_problems.iter().filter(|(name, _)| name == &egg.name);
+            check.problems.extend(problems.map(|(_, problem)| problem.clone()));
+        }
+
+        checks.push((egg.name.as_str(), check));
+    }
+
+    let ok = checks
+        .iter()
+        .all(|(_, check)| matches!(check, ParsedResponse::Success(check) if check.is_ok()));
+
+    if raw {
+        let checks: Vec<EggCheck> = checks
+            .into_iter()
+            .map(|(name, check)| match check {
+                ParsedResponse::Success(check) => check,
+                ParsedResponse::Failure(err) => EggCheck {
+                    egg: name.to_string(),
+                    problems: err_problems(&err),
+                    ..Default::default()
+                },
+            })
+            .collect();
+
+        let planned: Vec<PlannedStep> = steps.iter().map(Step::planned).collect();
+        let json = serde_json::json!({ "plan": planned, "checks": checks });
+        printth!("{}", serde_json::to_string_pretty(&json)?);
+    } else {
+        print_plan(steps);
+        printth!("<yellow>⬮</yellow> <dim>dry run, nothing will be changed</dim>\n");
+
+        for (name, check) in checks.iter() {
+            print_check(name, check);
+        }
+
+        println!();
+    }
+
+    if !ok {
+        exit(1);
+    }
+
+    Ok(())
+}
+
+fn print_plan(steps: &[Step]) {
+    printth!("\n<yellow>⬮</yellow> <dim>plan</dim>\n");
+
+    for step in steps {
+        match step {
+            Step::Add(egg) => {
+                printth!("<green>+</green> {}", egg.name);
+            }
+            Step::Update { egg, changes, .. } => {
+                printth!("<yellow>~</yellow> {}", egg.name);
+                print_changes(changes);
+            }
+            Step::Keep(name) => {
+                printth!("<dim>= {}</dim>", name);
+            }
+            Step::Prune { name, .. } => {
+                printth!("<error>-</error> {}", name);
+            }
+        }
+    }
+
+    println!();
+}
+
+fn help() -> Result<()> {
+    printth!(
+        "{}",
+        Help {
+            command: "kurv apply",
+            summary: Some(indoc! {
+                "applies a basket file: collects the eggs it lists that don't exist yet, and
+                re-collects (and restarts) the ones whose config changed. The plan is printed
+                before applying it.
+
+                a basket lists many eggs, plus defaults shared by all of them:
+
+                  <dim>defaults:</dim>
+                  <dim>  cwd: /srv/app</dim>
+                  <dim>  env:</dim>
+                  <dim>    NODE_ENV: production</dim>
+                  <dim>eggs:</dim>
+                  <dim>  - name: api</dim>
+                  <dim>    command: node</dim>
+                  <dim>    args: [server.js]</dim>
+
+                <head><b>example:</b></head>
+                  <dim>$</dim> <white><b>kurv</b></white> apply <green>./basket.yaml</green>
+                  <dim>$</dim> <white><b>kurv</b></white> apply <green>./basket.yaml</green> --prune
+                  <dim>$</dim> <white><b>kurv</b></white> apply <green>./basket.yaml</green> --dry-run  <dim># only check it</dim>",
+            }),
+            error: None,
+            options: Some(vec![
+                ("-h, --help", vec![], "Prints this help message"),
+                ("-j, --json", vec![], "Prints the plan in json format"),
+                ("--prune", vec![], "Removes the eggs that aren't listed in the basket"),
+                ("--dry-run", vec![], "Checks the eggs without changing anything; exits with an error if any wouldn't work")
+            ]),
+            subcommands: None
+        }
+        .render()
+    );
+
+    Ok(())
+}
@@ -1,10 +1,17 @@
 use {
     crate::{
         cli::{
-            cmd::{api::Api, is_option_or_flag, wants_help, wants_raw},
+            cmd::{
+                api::{Api, ParsedResponse},
+                is_option_or_flag,
+                validate::print_problems,
+                wants_help, wants_raw,
+            },
+            color::theme::themed,
             components::{Component, Help},
         },
-        kurv::Egg,
+        common::tcp::{ErrorResponse, Problem},
+        kurv::{ConfigChange, Egg, EggCheck},
         printth,
     },
     anyhow::{Result, anyhow},
@@ -20,6 +27,8 @@ pub fn run(args: &mut Arguments) -> Result<()> {
     }
 
     let api = Api::new();
+    let recollect = args.contains("--recollect");
+    let dry_run = args.contains("--dry-run");
     let cmd_arg: Result<Option<String>> =
         args.opt_free_from_str().map_err(|_| anyhow!("wrong usage"));
 
@@ -30,38 +39,22 @@ pub fn run(args: &mut Arguments) -> Result<()> {
                     return Err(anyhow!("wrong usage"));
                 }
 
-                printth!("\n<yellow>⬮</yellow> <dim>collecting new egg</dim>\n");
+                // a dry run reports why the egg file is invalid, instead of just failing
+                if dry_run {
+                    let egg = Egg::load(PathBuf::from(path))?;
+                    return dry_run_egg(&api, args, &egg, recollect);
+                }
 
                 match Egg::load(PathBuf::from(path)) {
-                    Ok(egg) => {
-                        let body = serde_json::to_string(&egg).unwrap();
-
-                        // call the api
-                        let response = api.eggs_post("", body.as_ref());
-
-                        // check response
-                        if let Ok(egg) = response {
-                            if wants_raw(args) {
-                                printth!("{}", serde_json::to_string_pretty(&egg)?);
-                                return Ok(());
-                            }
-
-                            printth!(
-                                "{}",
-                                formatdoc! {
-                                    "egg <green>{}</green> has been collected with id {} and
-                                    scheduled to be started
-                                        
-                                    <head><b>i</b></head> you can check its status by running:
-                                        <dim>$</dim> <white><b>kurv</b></white> egg <green>{}</green>
-                                    ",
-                                    egg.name,
-                                    egg.id.unwrap_or(0),
-                                    egg.id.unwrap_or(0),
-                                }
-                            );
+                    Ok(egg) if recollect => {
+                        // eggs that don't exist yet are just collected
+                        if let Some(current) = api.find_egg(&egg.name)? {
+                            return recollect_egg(&api, args, current, egg);
                         }
+
+                        collect_egg(&api, args, egg)?;
                     }
+                    Ok(egg) => collect_egg(&api, args, egg)?,
                     Err(_) => exit(1),
                 }
 
@@ -74,23 +67,212 @@ pub fn run(args: &mut Arguments) -> Result<()> {
     }
 }
 
+/// collects a new egg
+fn collect_egg(api: &Api, args: &mut Arguments, egg: Egg) -> Result<()> {
+    printth!("\n<yellow>⬮</yellow> <dim>collecting new egg</dim>\n");
+
+    let body = serde_json::to_string(&egg).unwrap();
+
+    // call the api
+    let response = api.eggs_post("", body.as_ref());
+
+    // check response
+    if let Ok(egg) = response {
+        if wants_raw(args) {
+            printth!("{}", serde_json::to_string_pretty(&egg)?);
+            return Ok(());
+        }
+
+        printth!(
+            "{}",
+            formatdoc! {
+                "egg <green>{}</green> has been collected with id {} and
+                scheduled to be started
+                    
+                <head><b>i</b></head> you can check its status by running:
+                    <dim>$</dim> <white><b>kurv</b></white> egg <green>{}</green>
+                ",
+                egg.name,
+                egg.id.unwrap_or(0),
+                egg.id.unwrap_or(0),
+            }
+        );
+    }
+
+    Ok(())
+}
+
+/// replaces the config of the `current` egg with the one of `egg` and prints what changed
+fn recollect_egg(api: &Api, args: &mut Arguments, current: Egg, egg: Egg) -> Result<()> {
+    printth!("\n<yellow>⬮</yellow> <dim>re-collecting egg</dim>\n");
+
+    // the server tells what changes, as only it knows the values of secrets
+    let id = current.id.unwrap_or(0);
+    let changes = api.egg_changes(id, &egg)?;
+
+    let body = serde_json::to_string(&egg).unwrap();
+    let updated = api.eggs_put(&format!("/{id}"), body.as_ref())?;
+
+    if wants_raw(args) {
+        printth!("{}", serde_json::to_string_pretty(&updated)?);
+        return Ok(());
+    }
+
+    if changes.is_empty() {
+        printth!(
+            "<dim>the config of egg</dim> <green>{}</green> <dim>didn't change</dim>\n",
+            updated.name
+        );
+    } else {
+        print_changes(&changes);
+        println!();
+    }
+
+    printth!(
+        "{}",
+        formatdoc! {
+            "egg <green>{}</green> (id {}) has been re-collected and scheduled to be restarted
+
+            <head><b>i</b></head> you can check its status by running:
+                <dim>$</dim> <white><b>kurv</b></white> egg <green>{}</green>
+            ",
+            updated.name,
+            updated.id.unwrap_or(0),
+            updated.id.unwrap_or(0),
+        }
+    );
+
+    Ok(())
+}
+
+/// checks collecting (or re-collecting) the egg without changing anything, and exits with an
+/// error code if it wouldn't work
+fn dry_run_egg(api: &Api, args: &mut Arguments, egg: &Egg, recollect: bool) -> Result<()> {
+    let route = match api.find_egg(&egg.name)? {
+        Some(current) if recollect => format!("/{}", current.id.unwrap_or(0)),
+        _ => String::new(),
+    };
+
+    let body = serde_json::to_string(egg)?;
+    let check = api.eggs_dry_run(&route, &body)?;
+
+    if wants_raw(args) {
+        let ok = match &check {
+            ParsedResponse::Success(check) => {
+                printth!("{}", serde_json::to_string_pretty(check)?);
+                check.is_ok()
+            }
+            ParsedResponse::Failure(err) => {
+                printth!("{}", serde_json::to_string_pretty(err)?);
+                false
+            }
+        };
+
+        if !ok {
+            exit(1);
+        }
+
+        return Ok(());
+    }
+
+    printth!("\n<yellow>⬮</yellow> <dim>dry run, nothing will be changed</dim>\n");
+
+    if !print_check(&egg.name, &check) {
+        exit(1);
+    }
+
+    Ok(())
+}
+
+/// prints the result of a dry run of an egg; returns whether it can be collected
+pub(crate) fn print_check(name: &str, check: &ParsedResponse<EggCheck>) -> bool {
+    let (check, problems) = match check {
+        ParsedResponse::Success(check) => (Some(check), check.problems.clone()),
+        ParsedResponse::Failure(err) => (None, err_problems(err)),
+    };
+
+    let ok = !problems.iter().any(Problem::is_error);
+
+    if ok {
+        printth!("<green>✓</green> {}", name);
+    } else {
+        printth!("<error>✗</error> {}", name);
+    }
+
+    if let Some(check) = check {
+        println!("    {} {}", themed("<dim>cwd    </dim>"), check.cwd.display());
+
+        if let Some(command) = &check.command {
+            println!("    {} {}", themed("<dim>command</dim>"), command.display());
+        }
+    }
+
+    print_problems(&problems);
+
+    ok
+}
+
+/// returns the problems of an error response, or its message if it doesn't list any
+pub(crate) fn err_problems(err: &ErrorResponse) -> Vec<Problem> {
+    if err.problems.is_empty() {
+        return vec![Problem::error("", err.message.clone())];
+    }
+
+    err.problems.clone()
+}
+
+/// prints the changes between two configs of an egg, one per line
+pub(crate) fn print_changes(changes: &[ConfigChange]) {
+    for change in changes {
+        // values are printed as they are, they might contain anything
+        let line = match (&change.old, &change.new) {
+            (None, Some(new)) => {
+                format!("{} {}: {}", themed("  <green>+</green>"), change.field, new)
+            }
+            (Some(old), None) => {
+                format!("{} {}: {}", themed("  <error>-</error>"), change.field, old)
+            }
+            (old, new) => format!(
+                "{} {}: {} {} {}",
+                themed("  <yellow>~</yellow>"),
+                change.field,
+                old.as_deref().unwrap_or_default(),
+                themed("<dim>→</dim>"),
+                new.as_deref().unwrap_or_default(),
+            ),
+        };
+
+        println!("{line}");
+    }
+}
+
 fn help() -> Result<()> {
     printth!(
         "{}",
         Help {
-            command: "kurv stop",
+            command: "kurv collect",
             summary: Some(indoc! {
                 "collects an egg and schedules it to be started.
                 
                 <head><b>example:</b></head>
                   <dim>-> if we want to collect the egg <green>./my.egg</green>:</dim>
                   
-                  <dim>$</dim> <white><b>kurv</b></white> collect <green>./my.egg</green>",
+                  <dim>$</dim> <white><b>kurv</b></white> collect <green>./my.egg</green>
+
+                  <dim>-> if we changed it and want to update the collected egg:</dim>
+
+                  <dim>$</dim> <white><b>kurv</b></white> collect --recollect <green>./my.egg</green>
+
+                  <dim>-> if we just want to check that it would work:</dim>
+
+                  <dim>$</dim> <white><b>kurv</b></white> collect --dry-run <green>./my.egg</green>",
             }),
             error: None,
             options: Some(vec![
                 ("-h, --help", vec![], "Prints this help message"),
-                ("-j, --json", vec![], "Prints the response in json format")
+                ("-j, --json", vec![], "Prints the response in json format"),
+                ("--recollect", vec![], "Updates the config of the egg if it already exists, and restarts it unless it's stopped"),
+                ("--dry-run", vec![], "Checks the egg without collecting it; exits with an error if it wouldn't work")
             ]),
             subcommands: None
         }
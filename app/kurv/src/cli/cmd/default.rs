@@ -55,7 +55,11 @@ fn help(err: Option<&str>) {
                 ("restart", vec![], "restarts a running egg"),
                 ("remove", vec![], "removes an egg"),
                 ("collect", vec![], "collects and starts a new egg"),
+                ("apply", vec![], "collects or updates the eggs of a basket file"),
+                ("validate", vec![], "checks an egg file for problems"),
+                ("scale", vec![], "changes the number of instances of an egg"),
                 ("env", vec![], "manages environment variables for eggs"),
+                ("logs", vec![], "prints or follows the logs of an egg"),
             ]),
         }
         .render()
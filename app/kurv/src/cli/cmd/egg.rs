@@ -5,7 +5,7 @@ use {
             components::{Component, Help},
         },
         common::str::ToString,
-        kurv::{Egg, EggStatus},
+        kurv::{Egg, EggStatus, HealthCheck, HealthStatus},
         printth,
     },
     anyhow::{Result, anyhow},
@@ -21,6 +21,7 @@ pub fn run(args: &mut Arguments) -> Result<()> {
     }
 
     let api = Api::new();
+    let reveal = args.contains("--reveal");
     let cmd_arg: Result<Option<String>> =
         args.opt_free_from_str().map_err(|_| anyhow!("wrong usage"));
 
@@ -29,7 +30,7 @@ pub fn run(args: &mut Arguments) -> Result<()> {
             return Err(anyhow!("wrong usage"));
         }
 
-        let response = api.egg(id.as_str());
+        let response = api.egg(id.as_str(), reveal);
 
         if let Ok(egg) = response {
             if wants_raw(args) {
@@ -62,11 +63,20 @@ pub fn run(args: &mut Arguments) -> Result<()> {
                 }
             );
 
+            print_run_as(&egg);
+            print_limits(&egg);
+            print_cgroup(&egg);
+            print_max_memory(&egg);
+            print_dependencies(&egg);
             print_env(&egg);
             println!();
             print_paths(&egg);
             println!();
             print_state(&egg);
+            print_schedule(&egg);
+            print_health(&egg);
+            print_instances(&egg);
+            print_history(&egg);
         }
     } else {
         help()?;
@@ -80,10 +90,12 @@ fn print_state(egg: &Egg) {
         let status_color = match state.status {
             EggStatus::Pending => "dim",
             EggStatus::Running => "green",
+            EggStatus::Stopping => "warn",
             EggStatus::Stopped => "warn",
             EggStatus::Errored => "error",
             EggStatus::PendingRemoval => "warn",
             EggStatus::Restarting => "magenta",
+            EggStatus::Scheduled => "blue",
         };
 
         let status = state.status.str().to_lowercase();
@@ -98,6 +110,7 @@ fn print_state(egg: &Egg) {
                   <white><b>pid        </b></white>{}
                   <white><b>start time </b></white>{}
                   <white><b>try count  </b></white>{}
+                  <white><b>next try   </b></white>{}
                   <white><b>error      </b></white>{}
                 ",
                 status_color,
@@ -106,12 +119,201 @@ fn print_state(egg: &Egg) {
                 state.pid,
                 state.start_time.unwrap_or_default(),
                 state.try_count,
+                state.next_attempt_at.map(|at| at.to_string()).unwrap_or("-".to_string()),
                 state.error.clone().unwrap_or("".to_string()),
             }
         );
     }
 }
 
+fn print_schedule(egg: &Egg) {
+    let state = egg.state.clone().unwrap_or_default();
+
+    if let Some(cron) = &egg.cron_restart {
+        printth!(
+            "<magenta><b>cron restart </b></magenta>{} <dim>next at {}</dim>\n",
+            cron,
+            state.next_restart_at.map(|at| at.to_string()).unwrap_or("-".to_string()),
+        );
+    }
+
+    let Some(cron) = &egg.schedule else {
+        return;
+    };
+
+    let last_run = state.last_run;
+
+    printth!(
+        "{}",
+        formatdoc! {
+            "
+            <magenta><b>schedule:  </b></magenta>
+              <white><b>cron       </b></white>{}
+              <white><b>next run   </b></white>{}
+              <white><b>last run   </b></white>{}
+              <white><b>duration   </b></white>{}
+              <white><b>exit code  </b></white>{}
+            ",
+            cron,
+            state.next_run_at.map(|at| at.to_string()).unwrap_or("-".to_string()),
+            last_run.as_ref().map(|run| run.started_at.to_string()).unwrap_or("-".to_string()),
+            last_run.as_ref().map(|run| run.duration.to_string()).unwrap_or("-".to_string()),
+            last_run
+                .and_then(|run| run.exit_code)
+                .map(|code| code.to_string())
+                .unwrap_or("-".to_string()),
+        }
+    );
+}
+
+fn print_health(egg: &Egg) {
+    let Some(config) = &egg.health else {
+        return;
+    };
+
+    let health = egg.state.as_ref().and_then(|state| state.health.clone()).unwrap_or_default();
+
+    let status_color = match health.status {
+        HealthStatus::Healthy => "green",
+        HealthStatus::Unhealthy => "error",
+        HealthStatus::Unknown => "dim",
+    };
+
+    let check = match &config.check {
+        HealthCheck::Http { port, host, path } => format!("http://{host}:{port}{path}"),
+        HealthCheck::Tcp { port, host } => format!("tcp://{host}:{port}"),
+        HealthCheck::Exec { command, args } => format!("{} {}", command, args.join(" ")),
+    };
+
+    printth!(
+        "{}",
+        formatdoc! {
+            "<magenta><b>health:    </b></magenta>
+              <white><b>status     </b></white><{}>{}</{}>
+              <white><b>check      </b></white>{} <dim>every {}</dim>
+              <white><b>failures   </b></white>{}/{}
+              <white><b>last check </b></white>{}
+              <white><b>message    </b></white>{}
+            ",
+            status_color,
+            health.status,
+            status_color,
+            check,
+            config.interval,
+            health.failures,
+            config.failure_threshold,
+            health.last_check.map(|at| at.to_string()).unwrap_or("-".to_string()),
+            health.message.unwrap_or_default(),
+        }
+    );
+}
+
+fn print_instances(egg: &Egg) {
+    let Some(state) = &egg.state else {
+        return;
+    };
+
+    if egg.instance_count() == 1 && state.instances.len() <= 1 {
+        return;
+    }
+
+    printth!(
+        "<magenta><b>instances:</b></magenta> <dim>({} desired)</dim>",
+        egg.instance_count()
+    );
+
+    for instance in state.instances.iter() {
+        let status = instance.status.str().to_lowercase();
+
+        printth!(
+            "  <white><b>#{:<3}</b></white>{:<10} <dim>pid</dim> {:<8} {}",
+            instance.index,
+            status.trim_end(),
+            instance.pid,
+            instance.error.clone().unwrap_or_default(),
+        );
+    }
+
+    println!();
+}
+
+fn print_history(egg: &Egg) {
+    if egg.history().is_empty() {
+        return;
+    }
+
+    printth!("<magenta><b>history:</b></magenta>");
+
+    for event in egg.history() {
+        printth!("  <dim>{}</dim> {}", event.time.format("%Y-%m-%d %H:%M:%S"), event.message);
+    }
+
+    println!();
+}
+
+fn print_run_as(egg: &Egg) {
+    let run_as = match (&egg.user, &egg.group) {
+        (Some(user), Some(group)) => format!("{user}:{group}"),
+        (Some(user), None) => user.clone(),
+        (None, Some(group)) => format!(":{group}"),
+        (None, None) => return,
+    };
+
+    printth!("<magenta><b>runs as    </b></magenta>{}\n", run_as);
+}
+
+fn print_limits(egg: &Egg) {
+    let limits = egg.limits().describe();
+
+    if limits.is_empty() {
+        return;
+    }
+
+    printth!("<magenta><b>limits:</b></magenta>");
+
+    for (name, value) in limits {
+        printth!("  <white><b>{:<11}</b></white>{}", name, value);
+    }
+
+    println!();
+}
+
+fn print_cgroup(egg: &Egg) {
+    let limits = egg.cgroup().describe();
+
+    if limits.is_empty() {
+        return;
+    }
+
+    printth!("<magenta><b>cgroup:</b></magenta>");
+
+    for (name, value) in limits {
+        printth!("  <white><b>{:<11}</b></white>{}", name, value);
+    }
+
+    println!();
+}
+
+fn print_max_memory(egg: &Egg) {
+    let Some(limit) = egg.max_memory_restart else {
+        return;
+    };
+
+    printth!(
+        "<magenta><b>max memory </b></magenta>{} <dim>for {}, then restarts</dim>\n",
+        limit,
+        egg.max_memory_period()
+    );
+}
+
+fn print_dependencies(egg: &Egg) {
+    if egg.dependencies().is_empty() {
+        return;
+    }
+
+    printth!("<magenta><b>depends on </b></magenta>{}\n", egg.dependencies().join(", "));
+}
+
 fn print_env(egg: &Egg) {
     if let Some(env) = &egg.env {
         printth!("{}", "<magenta><b>env:</b></magenta>");
@@ -139,6 +341,10 @@ fn print_paths(egg: &Egg) {
         if let Some(stderr) = maybe_stderr {
             printth!("  <white><b>stderr</b></white> {}", stderr);
         }
+
+        if let Some(combined) = paths.combined.as_ref().and_then(|path| path.to_str()) {
+            printth!("  <white><b>log   </b></white> {}", combined);
+        }
     }
 }
 
@@ -158,7 +364,8 @@ fn help() -> Result<()> {
             error: None,
             options: Some(vec![
                 ("-h, --help", vec![], "Prints this help message"), 
-                ("-j, --json", vec![], "Prints the response in json format")
+                ("-j, --json", vec![], "Prints the response in json format"),
+                ("--reveal", vec![], "Shows the values of secret env vars (needs KURV_API_TOKEN)")
             ]),
             subcommands: None
         }
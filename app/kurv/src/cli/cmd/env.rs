@@ -4,13 +4,14 @@ use {
             cmd::{api::Api, is_option_or_flag, wants_help, wants_raw},
             components::{Component, Help},
         },
-        kurv::Egg,
+        common::dotenv,
+        kurv::{Egg, EggStatus},
         printth,
     },
     anyhow::{Result, anyhow},
     indoc::{formatdoc, indoc},
     pico_args::Arguments,
-    std::{collections::HashMap, fs::File, path::PathBuf},
+    std::{collections::HashMap, fs, fs::File, path::PathBuf},
 };
 
 /// manage environment variables for eggs
@@ -21,58 +22,133 @@ pub fn run(args: &mut Arguments) -> Result<()> {
 
     let api = Api::new();
     let replace = should_replace(args);
-    let cmd_id: Result<Option<String>> =
-        args.opt_free_from_str().map_err(|_| anyhow!("wrong usage"));
-    let cmd_path: Result<Option<String>> =
-        args.opt_free_from_str().map_err(|_| anyhow!("wrong usage"));
-
-    if let (Ok(Some(id)), Ok(Some(path))) = (cmd_id, cmd_path) {
-        if is_option_or_flag(&id) || is_option_or_flag(&path) {
-            return Err(anyhow!("wrong usage"));
+    let restart = args.contains("--restart");
+    let reveal = args.contains("--reveal");
+    let raw = wants_raw(args);
+
+    let free: Vec<String> =
+        args.clone().finish().into_iter().map(|arg| arg.to_string_lossy().to_string()).collect();
+
+    let Some((id, rest)) = free.split_first() else {
+        return Err(anyhow!("wrong usage"));
+    };
+
+    if free.iter().any(|arg| is_option_or_flag(arg)) {
+        return Err(anyhow!("wrong usage"));
+    }
+
+    let (action, params) = match rest.split_first() {
+        Some((action, params)) => (Some(action.as_str()), params),
+        None => (None, &[][..]),
+    };
+
+    let egg = match (action, params) {
+        (None, _) => return show_env(&api.egg(id, reveal)?, raw),
+        (Some("get"), [key]) => return get_var(&api.egg(id, reveal)?, key, reveal),
+        (Some("set"), vars) if !vars.is_empty() => {
+            api.update_egg_env(id, &parse_assignments(vars)?, false, restart)?
+        }
+        (Some("unset"), keys) if !keys.is_empty() => api.remove_egg_env(id, keys, restart)?,
+        (Some("import"), [path]) => {
+            api.update_egg_env(id, &get_env_from_dotenv(path)?, replace, restart)?
+        }
+        (Some("get" | "set" | "unset" | "import"), _) => return Err(anyhow!("wrong usage")),
+        (Some(path), []) => {
+            // read the file as json and parse it as a hashmap str,str for validation
+            api.update_egg_env(id, &get_env_from_file(path)?, replace, restart)?
         }
+        _ => return Err(anyhow!("wrong usage")),
+    };
+
+    if raw {
+        printth!("{}", serde_json::to_string_pretty(&egg)?);
+        return Ok(());
+    }
 
-        // read the file as json and parse it as a hashmap str,str for validation
-        let env_file = get_env_from_file(&path)?;
-        let response = api.update_egg_env(&id, &env_file, replace);
-
-        if let Ok(egg) = response {
-            if wants_raw(args) {
-                printth!("{}", serde_json::to_string_pretty(&egg)?);
-                return Ok(());
-            }
-
-            printth!(
-                "{}",
-                formatdoc! {
-                    "
-
-                    <yellow>⬮</yellow> » <b><white>{}</white></b>
-                    
-                    <green>Successfully updated environment variables for egg '{}'!</green>
-                    ",
-                    egg.name,
-                    egg.name,
-                }
-            );
-
-            print_env(&egg);
-
-            printth!(
-                "{}",
-                formatdoc! {
-                    "
-                    
-                    <orange>Changes will take effect the next time the egg is (re)started.</orange>
-                    "
-                }
-            );
-            return Ok(());
+    printth!(
+        "{}",
+        formatdoc! {
+            "
+
+            <yellow>⬮</yellow> » <b><white>{}</white></b>
+
+            <green>Successfully updated environment variables for egg '{}'!</green>
+            ",
+            egg.name,
+            egg.name,
         }
+    );
 
-        Ok(())
+    print_env(&egg);
+
+    let restarting = egg.state.as_ref().is_some_and(|state| state.status == EggStatus::Restarting);
+    let note = if restart && restarting {
+        "<orange>The egg is being restarted to apply the changes.</orange>"
     } else {
-        Err(anyhow!("wrong usage"))
+        "<orange>Changes will take effect the next time the egg is (re)started.</orange>"
+    };
+
+    printth!("\n{}\n", note);
+
+    Ok(())
+}
+
+/// prints the env of an egg
+fn show_env(egg: &Egg, raw: bool) -> Result<()> {
+    if raw {
+        printth!("{}", serde_json::to_string_pretty(&egg.env.clone().unwrap_or_default())?);
+        return Ok(());
     }
+
+    printth!("\n<yellow>⬮</yellow> » <b><white>{}</white></b>\n", egg.name);
+
+    if egg.env.as_ref().is_none_or(HashMap::is_empty) {
+        printth!("<dim>no environment variables set</dim>\n");
+        return Ok(());
+    }
+
+    print_env(egg);
+    println!();
+
+    Ok(())
+}
+
+/// prints the value of an env var of an egg, as is (so it can be used in scripts)
+fn get_var(egg: &Egg, key: &str, reveal: bool) -> Result<()> {
+    match egg.env.as_ref().and_then(|env| env.get(key)) {
+        // the value of a secret is masked, printing the mask would pass it off as the value
+        Some(_) if !reveal && egg.is_secret_env(key) => Err(anyhow!(
+            "env var {} of egg {} is a secret, use --reveal to get its value",
+            key,
+            egg.name
+        )),
+        Some(value) => {
+            println!("{value}");
+            Ok(())
+        }
+        None => Err(anyhow!("env var {} is not set for egg {}", key, egg.name)),
+    }
+}
+
+/// parses `KEY=VALUE` arguments
+fn parse_assignments(vars: &[String]) -> Result<HashMap<String, String>> {
+    vars.iter()
+        .map(|var| match var.split_once('=') {
+            Some((key, value)) => Ok((key.to_string(), value.to_string())),
+            None => Err(anyhow!("expected KEY=VALUE, got '{}'", var)),
+        })
+        .collect()
+}
+
+fn get_env_from_dotenv(path: &str) -> Result<HashMap<String, String>> {
+    let path_buf = PathBuf::from(path);
+    let content = fs::read_to_string(&path_buf)
+        .map_err(|_| anyhow!("failed to open file: {}", path_buf.display()))?;
+    let vars = dotenv::parse(&content)
+        .map_err(|err| anyhow!("failed to parse {}: {}", path_buf.display(), err))?;
+
+    // later lines win
+    Ok(vars.into_iter().collect())
 }
 
 fn get_env_from_file(path: &str) -> Result<HashMap<String, String>> {
@@ -90,8 +166,10 @@ fn print_env(egg: &Egg) {
         printth!("{}", "<magenta><b>env:</b></magenta>");
 
         let max_key_len = env.keys().map(|k| k.len()).max().unwrap_or(0);
+        let mut vars: Vec<(&String, &String)> = env.iter().collect();
+        vars.sort();
 
-        for (key, value) in env {
+        for (key, value) in vars {
             let padding = " ".repeat(max_key_len - key.len());
             printth!("  <white><b>{}</b></white>{} {}", key, padding, value);
         }
@@ -105,17 +183,22 @@ fn help() -> Result<()> {
             command: "kurv env",
             summary: Some(indoc! {
                 "manages environment variables for eggs
-                
+
                 <magenta><b>example:</b></magenta>
-                  <dim>$</dim> <white><b>kurv</b></white> env <green>1</green> my-env.json          <dim># by id</dim>
-                  <dim>$</dim> <white><b>kurv</b></white> env <green>myprocess</green> my-env.json  <dim># by name</dim>
-                  <dim>$</dim> <white><b>kurv</b></white> env <green>9778</green> my-env.json       <dim># by pid</dim>" 
+                  <dim>$</dim> <white><b>kurv</b></white> env <green>myprocess</green>                      <dim># shows them</dim>
+                  <dim>$</dim> <white><b>kurv</b></white> env <green>myprocess</green> get PORT             <dim># prints one</dim>
+                  <dim>$</dim> <white><b>kurv</b></white> env <green>myprocess</green> set PORT=80 DEBUG=1  <dim># sets some</dim>
+                  <dim>$</dim> <white><b>kurv</b></white> env <green>myprocess</green> unset DEBUG          <dim># removes some</dim>
+                  <dim>$</dim> <white><b>kurv</b></white> env <green>myprocess</green> import .env          <dim># from a .env file</dim>
+                  <dim>$</dim> <white><b>kurv</b></white> env <green>1</green> my-env.json                  <dim># from a json file</dim>"
             }),
             error: None,
             options: Some(vec![
-                ("-h, --help", vec![], "Prints this help message"), 
+                ("-h, --help", vec![], "Prints this help message"),
                 ("-j, --json", vec![], "Prints the response in json format"),
-                ("--replace", vec![], "Replaces the entire environment variables with the provided ones")
+                ("--replace", vec![], "Replaces the entire environment variables with the provided ones (import and json files)"),
+                ("--restart", vec![], "Restarts the egg to apply the changes right away, if it's running"),
+                ("--reveal", vec![], "Shows the values of secret env vars (needs KURV_API_TOKEN)")
             ]),
             subcommands: None
         }
@@ -1,5 +1,6 @@
 use {
     crate::{
+        api::eggs::EggSummary,
         cli::{
             cmd::{
                 api::{Api, EggKind},
@@ -7,7 +8,7 @@ use {
             },
             components::{Component, Help},
         },
-        common::str::ToString,
+        common::{size::humanize_size, str::ToString},
         kurv::EggStatus,
         printth,
     },
@@ -77,8 +78,11 @@ pub fn run(args: &mut Arguments, kind: EggKind) -> Result<()> {
                     .bold(true)
                     .foreground_color(color_by_status(egg.status))
                     .dimmed(dim_by_status(egg.status)),
+                instances(egg).cell().justify(Justify::Center),
                 egg.retry_count.cell().justify(Justify::Center),
                 egg.uptime.clone().cell().justify(Justify::Center),
+                cpu(egg).cell().justify(Justify::Right),
+                mem(egg).cell().justify(Justify::Right),
             ]
         })
         .collect();
@@ -91,8 +95,11 @@ pub fn run(args: &mut Arguments, kind: EggKind) -> Result<()> {
             "pid".cell().bold(true).foreground_color(Some(Color::Blue)),
             "name".cell().bold(true).foreground_color(Some(Color::Blue)),
             "status".cell().bold(true).foreground_color(Some(Color::Blue)),
+            "⧉".cell().bold(true).foreground_color(Some(Color::Blue)).justify(Justify::Center),
             "↺".cell().bold(true).foreground_color(Some(Color::Blue)).justify(Justify::Center),
             "uptime".cell().bold(true).foreground_color(Some(Color::Blue)).justify(Justify::Center),
+            "cpu".cell().bold(true).foreground_color(Some(Color::Blue)).justify(Justify::Right),
+            "mem".cell().bold(true).foreground_color(Some(Color::Blue)).justify(Justify::Right),
         ])
         .border(border.build())
         .separator(separator.build());
@@ -130,14 +137,40 @@ fn help(kind: &EggKind) -> Result<()> {
     Ok(())
 }
 
+/// returns the running/desired instances of the egg (e.g. `3/4`)
+fn instances(egg: &EggSummary) -> String {
+    let running =
+        egg.instances.iter().filter(|instance| instance.status == EggStatus::Running).count();
+
+    format!("{}/{}", running, egg.instance_count)
+}
+
+/// returns the cpu usage of the egg (e.g. `12.5%`), or `-` if it hasn't been sampled
+fn cpu(egg: &EggSummary) -> String {
+    match egg.metrics {
+        Some(metrics) => format!("{:.1}%", metrics.cpu),
+        None => "-".to_string(),
+    }
+}
+
+/// returns the memory used by the egg (e.g. `34.2M`), or `-` if it hasn't been sampled
+fn mem(egg: &EggSummary) -> String {
+    match egg.metrics {
+        Some(metrics) => humanize_size(metrics.memory),
+        None => "-".to_string(),
+    }
+}
+
 fn color_by_status(status: EggStatus) -> Option<Color> {
     match status {
         EggStatus::Running => Some(Color::Green),
         EggStatus::Errored => Some(Color::Red),
+        EggStatus::Stopping => Some(Color::Yellow),
         EggStatus::Stopped => Some(Color::Yellow),
         EggStatus::Pending => Some(Color::Blue),
         EggStatus::PendingRemoval => Some(Color::Red),
         EggStatus::Restarting => Some(Color::Magenta),
+        EggStatus::Scheduled => Some(Color::Cyan),
     }
 }
 
@@ -145,10 +178,12 @@ fn dim_by_status(status: EggStatus) -> bool {
     match status {
         EggStatus::PendingRemoval => true,
         EggStatus::Restarting => true,
+        EggStatus::Stopping => true,
         EggStatus::Pending => true,
         EggStatus::Running => false,
         EggStatus::Errored => false,
         EggStatus::Stopped => false,
+        EggStatus::Scheduled => false,
     }
 }
 
@@ -0,0 +1,173 @@
+use {
+    crate::{
+        api::eggs::logs::{LogLine, LogStream},
+        cli::{
+            cmd::{api::Api, is_option_or_flag, wants_help, wants_raw},
+            color::theme::themed,
+            components::{Component, Help},
+        },
+        kurv::JsonLogLine,
+        printth,
+    },
+    anyhow::{Result, anyhow},
+    chrono::DateTime,
+    indoc::indoc,
+    pico_args::Arguments,
+};
+
+/// prints the last lines of the logs of an egg, optionally following them
+pub fn run(args: &mut Arguments) -> Result<()> {
+    if wants_help(args) {
+        return help();
+    }
+
+    let api = Api::new();
+    let raw = wants_raw(args);
+    let follow = args.contains(["-f", "--follow"]);
+    let lines: Option<usize> = args
+        .opt_value_from_str(["-n", "--lines"])
+        .map_err(|_| anyhow!("the number of lines must be a positive number"))?;
+    let instance: Option<usize> = args
+        .opt_value_from_str(["-i", "--instance"])
+        .map_err(|_| anyhow!("the instance must be a positive number"))?;
+
+    // `--both` is the default, it wins if combined with any of the others
+    let (stdout, stderr, both) =
+        (args.contains("--stdout"), args.contains("--stderr"), args.contains("--both"));
+    let stream = match (stdout, stderr) {
+        (true, false) if !both => "stdout",
+        (false, true) if !both => "stderr",
+        _ => "both",
+    };
+
+    let cmd_id: Result<Option<String>> =
+        args.opt_free_from_str().map_err(|_| anyhow!("wrong usage"));
+
+    let Ok(Some(id)) = cmd_id else {
+        return help();
+    };
+
+    if is_option_or_flag(&id) {
+        return Err(anyhow!("wrong usage"));
+    }
+
+    let mut query = format!("stream={stream}");
+
+    if let Some(lines) = lines {
+        query.push_str(&format!("&lines={lines}"));
+    }
+
+    if let Some(instance) = instance {
+        query.push_str(&format!("&instance={instance}"));
+    }
+
+    if follow {
+        return api.follow_egg_logs(&id, &query, |line| {
+            if raw {
+                println!("{}", serde_json::to_string(&line).unwrap_or_default());
+            } else {
+                print_line(&line, stream == "both");
+            }
+        });
+    }
+
+    let logs = api.egg_logs(&id, &query)?;
+
+    if raw {
+        printth!("{}", serde_json::to_string_pretty(&logs)?);
+        return Ok(());
+    }
+
+    match (logs.stdout, logs.stderr) {
+        (Some(stdout), Some(stderr)) => {
+            print_block("stdout", &stdout);
+            println!();
+            print_block("stderr", &stderr);
+        }
+        (Some(lines), None) | (None, Some(lines)) => {
+            for line in lines {
+                println!("{}", pretty(&line));
+            }
+        }
+        (None, None) => {}
+    }
+
+    Ok(())
+}
+
+/// prints the lines of one of the log files, under a header with its name
+fn print_block(name: &str, lines: &[String]) {
+    printth!("<dim>─── {} · last {} line(s) ───</dim>", name, lines.len());
+
+    for line in lines {
+        println!("{}", pretty(line));
+    }
+}
+
+/// prints a followed log line, tagged with the log file it comes from if both are followed
+fn print_line(line: &LogLine, tagged: bool) {
+    if !tagged {
+        println!("{}", pretty(&line.line));
+        return;
+    }
+
+    let tag = match line.stream {
+        LogStream::Stdout => themed("<dim>out │</dim>"),
+        LogStream::Stderr => themed("<error>err</error> <dim>│</dim>"),
+    };
+
+    println!("{} {}", tag, pretty(&line.line));
+}
+
+/// formats a line of a json log (see `log_format`) as `<time> <message>`, showing the
+/// instance for clustered eggs; other lines are returned as they are.
+fn pretty(line: &str) -> String {
+    let Ok(json) = serde_json::from_str::<JsonLogLine>(line) else {
+        return line.to_string();
+    };
+
+    let ts = match DateTime::parse_from_rfc3339(&json.ts) {
+        Ok(ts) => ts.format("%Y-%m-%d %H:%M:%S%.3f").to_string(),
+        Err(_) => json.ts,
+    };
+
+    let instance = match json.instance {
+        0 => String::new(),
+        index => format!("[{index}] "),
+    };
+
+    format!("{}{}", themed(&format!("<dim>{ts}</dim> {instance}")), json.msg)
+}
+
+fn help() -> Result<()> {
+    printth!(
+        "{}",
+        Help {
+            command: "kurv logs",
+            summary: Some(indoc! {
+                "prints the last lines of the logs of an egg; with <white>--follow</white>, keeps
+                printing new lines as they are written (works with remote servers too).
+
+                <head><b>example:</b></head>
+                    <dim>$</dim> <white><b>kurv</b></white> logs <green>myprocess</green>                <dim># last 20 lines of stdout and stderr</dim>
+                    <dim>$</dim> <white><b>kurv</b></white> logs <green>1</green> -n 100 --stderr      <dim># last 100 lines of stderr</dim>
+                    <dim>$</dim> <white><b>kurv</b></white> logs <green>myprocess</green> -f             <dim># follow both logs</dim>"
+            }),
+            error: None,
+            options: Some(vec![
+                ("-h, --help", vec![], "Prints this help message"),
+                ("-n, --lines", vec![], "Number of lines to print (defaults to 20)"),
+                ("-f, --follow", vec![], "Keeps printing new lines as they are written"),
+                ("--stdout", vec![], "Only prints the stdout log"),
+                ("--stderr", vec![], "Only prints the stderr log"),
+                ("--both", vec![], "Prints both logs (default)"),
+                ("-i, --instance", vec![], "Instance to print the logs of, for clustered eggs (defaults to 0)"),
+                ("-j, --json", vec![], "Prints the response in json format")
+            ]),
+            subcommands: None
+        }
+        .render()
+    );
+
+    Ok(())
+}
@@ -1,13 +1,17 @@
 use pico_args::Arguments;
 
 mod api;
+pub mod apply;
 pub mod collect;
 pub mod default;
 pub mod egg;
 pub mod env;
 pub mod list;
+pub mod logs;
+pub mod scale;
 pub mod server_help;
 pub mod stop_start;
+pub mod validate;
 
 pub(crate) use api::EggKind;
 
@@ -0,0 +1,94 @@
+use {
+    crate::{
+        cli::{
+            cmd::{api::Api, is_option_or_flag, wants_help, wants_raw},
+            components::{Component, Help},
+        },
+        printth,
+    },
+    anyhow::{Result, anyhow},
+    indoc::{formatdoc, indoc},
+    pico_args::Arguments,
+};
+
+/// changes the number of instances an egg runs
+pub fn run(args: &mut Arguments) -> Result<()> {
+    if wants_help(args) {
+        return help();
+    }
+
+    let api = Api::new();
+    let cmd_id: Result<Option<String>> =
+        args.opt_free_from_str().map_err(|_| anyhow!("wrong usage"));
+    let cmd_count: Result<Option<String>> =
+        args.opt_free_from_str().map_err(|_| anyhow!("wrong usage"));
+
+    if let (Ok(Some(id)), Ok(Some(count))) = (cmd_id, cmd_count) {
+        if is_option_or_flag(&id) || is_option_or_flag(&count) {
+            return Err(anyhow!("wrong usage"));
+        }
+
+        let instances: u32 = count
+            .parse()
+            .map_err(|_| anyhow!("the number of instances must be a positive number"))?;
+
+        let body = serde_json::to_string(&serde_json::json!({ "instances": instances }))?;
+        let response = api.eggs_post(format!("/{}/scale", id).as_str(), &body);
+
+        if let Ok(egg) = response {
+            if wants_raw(args) {
+                printth!("{}", serde_json::to_string_pretty(&egg)?);
+                return Ok(());
+            }
+
+            printth!(
+                "{}",
+                formatdoc! {
+                    "
+
+                    <yellow>⬮</yellow> » <b><white>{}</white></b>
+
+                    egg <green>{}</green> has been scheduled to run <b>{}</b> instance(s)
+
+                    <head><b>i</b></head> you can check its status by running:
+                      <dim>$</dim> <white><b>kurv</b></white> egg <green>{}</green>
+                    ",
+                    egg.name,
+                    egg.name,
+                    egg.instance_count(),
+                    egg.name,
+                }
+            );
+        }
+
+        Ok(())
+    } else {
+        help()
+    }
+}
+
+fn help() -> Result<()> {
+    printth!(
+        "{}",
+        Help {
+            command: "kurv scale",
+            summary: Some(indoc! {
+                "changes the number of instances (processes) an egg runs.
+                new instances are started right away, extra instances are stopped.
+
+                <head><b>example:</b></head>
+                    <dim>$</dim> <white><b>kurv</b></white> scale <green>myprocess</green> <b>4</b>   <dim># run 4 instances</dim>
+                    <dim>$</dim> <white><b>kurv</b></white> scale <green>1</green> <b>1</b>           <dim># back to a single one</dim>"
+            }),
+            error: None,
+            options: Some(vec![
+                ("-h, --help", vec![], "Prints this help message"),
+                ("-j, --json", vec![], "Prints the response in json format")
+            ]),
+            subcommands: None
+        }
+        .render()
+    );
+
+    Ok(())
+}
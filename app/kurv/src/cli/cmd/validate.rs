@@ -0,0 +1,122 @@
+use {
+    crate::{
+        cli::{
+            cmd::{is_option_or_flag, wants_help, wants_raw},
+            color::theme::themed,
+            components::{Component, Help},
+        },
+        common::tcp::{Problem, Severity},
+        kurv::Egg,
+        printth,
+    },
+    anyhow::{Result, anyhow},
+    indoc::indoc,
+    pico_args::Arguments,
+    std::{env, fs, path::PathBuf, process::exit},
+};
+
+/// validates an egg file, printing the problems found in it
+pub fn run(args: &mut Arguments) -> Result<()> {
+    if wants_help(args) {
+        return help();
+    }
+
+    let raw = wants_raw(args);
+    let cmd_arg: Result<Option<String>> =
+        args.opt_free_from_str().map_err(|_| anyhow!("wrong usage"));
+
+    let Ok(Some(path)) = cmd_arg else {
+        return help();
+    };
+
+    if is_option_or_flag(&path) {
+        return Err(anyhow!("wrong usage"));
+    }
+
+    let path = PathBuf::from(path);
+    let yaml = fs::read_to_string(&path)
+        .map_err(|err| anyhow!("failed to read {}: {}", path.display(), err))?;
+
+    let problems = match Egg::parse(&yaml) {
+        // the default cwd is where the server runs, here the best guess is where we are
+        Ok((egg, mut problems)) => {
+            problems.extend(egg.check(&env::current_dir()?).problems);
+            problems
+        }
+        Err(err) if raw => vec![Problem::error("", err.without_snippet().to_string())],
+        Err(err) => {
+            printth!("\n<error>✗</error> {} <dim>is not a valid egg file</dim>\n", path.display());
+            println!("{err}\n");
+            exit(1);
+        }
+    };
+
+    let ok = !problems.iter().any(Problem::is_error);
+
+    if raw {
+        printth!("{}", serde_json::to_string_pretty(&problems)?);
+    } else if problems.is_empty() {
+        printth!("\n<green>✓</green> {} <dim>is a valid egg file</dim>\n", path.display());
+    } else {
+        let marker = if ok {
+            "<yellow>!</yellow>"
+        } else {
+            "<error>✗</error>"
+        };
+        printth!("\n{} {}\n", marker, path.display());
+        print_problems(&problems);
+        println!();
+    }
+
+    if !ok {
+        exit(1);
+    }
+
+    Ok(())
+}
+
+/// prints problems found in an egg, one per line
+pub(crate) fn print_problems(problems: &[Problem]) {
+    for problem in problems {
+        let marker = match problem.severity {
+            Severity::Error => themed("<error>✗</error>"),
+            Severity::Warning => themed("<yellow>!</yellow>"),
+        };
+
+        let field = if problem.field.is_empty() {
+            String::new()
+        } else {
+            themed(&format!(" <dim>({})</dim>", problem.field))
+        };
+
+        println!("    {} {}{}", marker, problem.message, field);
+    }
+}
+
+fn help() -> Result<()> {
+    printth!(
+        "{}",
+        Help {
+            command: "kurv validate",
+            summary: Some(indoc! {
+                "validates an egg file: its fields, its cwd and its command, which is looked up in
+                the <white>PATH</white>. Exits with an error code if there are errors (warnings are fine).
+
+                the checks are done on this machine, without asking the server. To check the egg
+                against a server, use <white>kurv collect --dry-run</white>.
+
+                <head><b>example:</b></head>
+                  <dim>$</dim> <white><b>kurv</b></white> validate <green>./my.egg</green>",
+            }),
+            error: None,
+            options: Some(vec![
+                ("-h, --help", vec![], "Prints this help message"),
+                ("-j, --json", vec![], "Prints the problems in json format"),
+            ]),
+            subcommands: None
+        }
+        .render()
+    );
+
+    Ok(())
+}
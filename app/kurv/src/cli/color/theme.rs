@@ -141,6 +141,20 @@ pub fn initialize_theme() {
     });
 }
 
+/// applies the global theme to a string without printing it; useful to style text that is
+/// printed along with text that must be left untouched (e.g. the output of an egg)
+pub fn themed(text: &str) -> String {
+    initialize_theme();
+
+    unsafe {
+        let theme_ptr = std::ptr::addr_of!(GLOBAL_THEME);
+        match (*theme_ptr).as_ref() {
+            Some(theme) => theme.apply(text),
+            None => text.to_string(),
+        }
+    }
+}
+
 /// prints a string by using the global theme
 #[macro_export]
 macro_rules! printth {
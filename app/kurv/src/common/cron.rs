@@ -0,0 +1,233 @@
+use {
+    anyhow::{Result, anyhow},
+    chrono::{DateTime, Datelike, Duration, Local, NaiveDate, NaiveDateTime, TimeZone, Timelike},
+    serde::{
+        Deserialize, Deserializer, Serialize, Serializer,
+        de::{self, Visitor},
+    },
+    std::{fmt::Display, str::FromStr},
+};
+
+const MONTHS: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+
+const WEEKDAYS: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+/// how far ahead to look for the next match, so expressions that never match (e.g. the 30th
+/// of february) don't loop forever; leap days are at most 8 years apart
+const MAX_YEARS_AHEAD: i32 = 9;
+
+/// a cron expression made of 5 fields: minute, hour, day of month, month and day of week, like
+/// `0 3 * * *` (every day at 3am) or `*/15 9-17 * * mon-fri`. Fields accept `*`, numbers,
+/// ranges (`1-5`), steps (`*/10`, `0-30/5`) and lists (`1,15`); months and days of the week
+/// can also be written by name (`jan`, `mon`). `@hourly`, `@daily`, `@weekly`, `@monthly` and
+/// `@yearly` are also accepted.
+///
+/// as in the classic cron, when both the day of month and the day of week are restricted, a
+/// day matches if either of them does. Times are in the local time zone of the server.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct CronExpr {
+    /// the expression as written, kept to show it back
+    source: String,
+
+    /// allowed values of each field, one bit per value
+    minutes: u64,
+    hours: u32,
+    days: u32,
+    months: u16,
+    weekdays: u8,
+
+    /// whether the day of month / day of week fields are unrestricted (start with `*`)
+    any_day: bool,
+    any_weekday: bool,
+}
+
+impl CronExpr {
+    /// returns the first time after `time` (excluded) that matches the expression, if any
+    pub fn next_after(&self, time: DateTime<Local>) -> Option<DateTime<Local>> {
+        let start = time.naive_local().with_second(0)?.with_nanosecond(0)? + Duration::minutes(1);
+        let limit = start.year() + MAX_YEARS_AHEAD;
+        let mut candidate = start;
+
+        while candidate.year() <= limit {
+            if !has_bit(self.months as u64, candidate.month()) {
+                candidate = first_of_next_month(candidate)?;
+                continue;
+            }
+
+            if !self.matches_day(candidate.date()) {
+                candidate = (candidate.date() + Duration::days(1)).and_hms_opt(0, 0, 0)?;
+                continue;
+            }
+
+            if !has_bit(self.hours as u64, candidate.hour()) {
+                candidate = candidate.with_minute(0)? + Duration::hours(1);
+                continue;
+            }
+
+            if !has_bit(self.minutes, candidate.minute()) {
+                candidate += Duration::minutes(1);
+                continue;
+            }
+
+            // local times skipped by a DST change don't exist, the next match will do
+            match Local.from_local_datetime(&candidate).earliest() {
+                Some(next) if next > time => return Some(next),
+                _ => candidate += Duration::minutes(1),
+            }
+        }
+
+        None
+    }
+
+    fn matches_day(&self, date: NaiveDate) -> bool {
+        let day = has_bit(self.days as u64, date.day());
+        let weekday = has_bit(self.weekdays as u64, date.weekday().num_days_from_sunday());
+
+        match (self.any_day, self.any_weekday) {
+            (false, false) => day || weekday,
+            _ => day && weekday,
+        }
+    }
+}
+
+fn has_bit(bits: u64, value: u32) -> bool {
+    bits & (1 << value) != 0
+}
+
+fn first_of_next_month(time: NaiveDateTime) -> Option<NaiveDateTime> {
+    let (year, month) = match time.month() {
+        12 => (time.year() + 1, 1),
+        month => (time.year(), month + 1),
+    };
+
+    NaiveDate::from_ymd_opt(year, month, 1)?.and_hms_opt(0, 0, 0)
+}
+
+/// parses a field of the expression into a bitset of the values it allows, between `min` and
+/// `max`; `names` are the names of the values, starting at `min`
+fn parse_field(field: &str, min: u32, max: u32, names: &[&str]) -> Result<u64> {
+    let value = |s: &str| -> Result<u32> {
+        let lower = s.to_lowercase();
+
+        let value = match names.iter().position(|name| *name == lower) {
+            Some(position) => position as u32 + min,
+            None => s.parse().map_err(|_| anyhow!("invalid value '{s}'"))?,
+        };
+
+        if value < min || value > max {
+            return Err(anyhow!("{value} is out of range ({min}-{max})"));
+        }
+
+        Ok(value)
+    };
+
+    let mut bits = 0;
+
+    for item in field.split(',') {
+        let (range, step) = match item.split_once('/') {
+            Some((range, step)) => {
+                let step: u32 = step.parse().map_err(|_| anyhow!("invalid step '{step}'"))?;
+
+                if step == 0 {
+                    return Err(anyhow!("step can't be 0"));
+                }
+
+                (range, Some(step))
+            }
+            None => (item, None),
+        };
+
+        let (start, end) = match range.split_once('-') {
+            _ if range == "*" => (min, max),
+            Some((start, end)) => (value(start)?, value(end)?),
+            // `5/10` goes from 5 to the end
+            None if step.is_some() => (value(range)?, max),
+            None => (value(range)?, value(range)?),
+        };
+
+        if start > end {
+            return Err(anyhow!("invalid range '{range}'"));
+        }
+
+        for value in (start..=end).step_by(step.unwrap_or(1) as usize) {
+            bits |= 1 << value;
+        }
+    }
+
+    Ok(bits)
+}
+
+impl FromStr for CronExpr {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let source = s.trim();
+
+        let expanded = match source.to_lowercase().as_str() {
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            "@monthly" => "0 0 1 * *",
+            "@weekly" => "0 0 * * 0",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@hourly" => "0 * * * *",
+            _ => source,
+        };
+
+        let fields: Vec<&str> = expanded.split_whitespace().collect();
+        let [minutes, hours, days, months, weekdays] = fields[..] else {
+            return Err(anyhow!("invalid cron expression '{source}': expected 5 fields"));
+        };
+
+        let field = |name: &str, field: &str, min, max, names| {
+            parse_field(field, min, max, names)
+                .map_err(|err| anyhow!("invalid {name} in cron expression '{source}': {err}"))
+        };
+
+        // sunday can be written as 0 or 7
+        let weekday_bits = field("day of week", weekdays, 0, 7, &WEEKDAYS)?;
+
+        Ok(CronExpr {
+            source: source.to_string(),
+            minutes: field("minute", minutes, 0, 59, &[])?,
+            hours: field("hour", hours, 0, 23, &[])? as u32,
+            days: field("day of month", days, 1, 31, &[])? as u32,
+            months: field("month", months, 1, 12, &MONTHS)? as u16,
+            weekdays: ((weekday_bits | weekday_bits >> 7) & 0x7f) as u8,
+            any_day: days.starts_with('*'),
+            any_weekday: weekdays.starts_with('*'),
+        })
+    }
+}
+
+impl Display for CronExpr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+impl Serialize for CronExpr {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.source)
+    }
+}
+
+impl<'de> Deserialize<'de> for CronExpr {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct CronExprVisitor;
+
+        impl Visitor<'_> for CronExprVisitor {
+            type Value = CronExpr;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                write!(f, "a cron expression like '0 3 * * *'")
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+                v.parse().map_err(E::custom)
+            }
+        }
+
+        deserializer.deserialize_str(CronExprVisitor)
+    }
+}
@@ -0,0 +1,73 @@
+use anyhow::{Result, anyhow};
+
+/// parses the content of a `.env` file: one `KEY=VALUE` per line, optionally prefixed with
+/// `export`. Blank lines and lines starting with `#` are skipped.
+///
+/// values can be unquoted (trimmed, and cut at a ` #` comment), single quoted (taken as they
+/// are) or double quoted (where `\n`, `\t`, `\"` and `\\` are unescaped). The variables are
+/// returned in the order they appear; if a key is repeated, the last one should win.
+pub fn parse(content: &str) -> Result<Vec<(String, String)>> {
+    let mut vars = vec![];
+
+    for (index, line) in content.lines().enumerate() {
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let line = line.strip_prefix("export ").map(str::trim_start).unwrap_or(line);
+
+        let Some((key, value)) = line.split_once('=') else {
+            return Err(anyhow!("line {}: expected KEY=VALUE", index + 1));
+        };
+
+        let key = key.trim();
+
+        if key.is_empty() || key.contains(char::is_whitespace) {
+            return Err(anyhow!("line {}: invalid variable name '{}'", index + 1, key));
+        }
+
+        let value = parse_value(value.trim())
+            .ok_or_else(|| anyhow!("line {}: unclosed quote", index + 1))?;
+
+        vars.push((key.to_string(), value));
+    }
+
+    Ok(vars)
+}
+
+/// parses a (trimmed) value; `None` if it has an unclosed quote
+fn parse_value(value: &str) -> Option<String> {
+    if let Some(quoted) = value.strip_prefix('\'') {
+        let end = quoted.find('\'')?;
+        return Some(quoted[..end].to_string());
+    }
+
+    if let Some(quoted) = value.strip_prefix('"') {
+        let mut unescaped = String::new();
+        let mut chars = quoted.chars();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '"' => return Some(unescaped),
+                '\\' => match chars.next()? {
+                    'n' => unescaped.push('\n'),
+                    't' => unescaped.push('\t'),
+                    'r' => unescaped.push('\r'),
+                    other => unescaped.push(other),
+                },
+                c => unescaped.push(c),
+            }
+        }
+
+        return None;
+    }
+
+    let value = match value.find(" #") {
+        Some(comment) => &value[..comment],
+        None => value,
+    };
+
+    Some(value.trim_end().to_string())
+}
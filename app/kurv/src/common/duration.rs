@@ -1,4 +1,12 @@
-use chrono::Duration;
+use {
+    anyhow::anyhow,
+    chrono::Duration,
+    serde::{
+        Deserialize, Deserializer, Serialize, Serializer,
+        de::{self, Visitor},
+    },
+    std::{fmt::Display, str::FromStr},
+};
 
 pub fn humanize_duration(duration: Duration) -> String {
     if duration.num_days() >= 30 {
@@ -28,3 +36,101 @@ pub fn humanize_duration(duration: Duration) -> String {
 
     "< 1 second".to_string()
 }
+
+/// a duration that can be written in config files either as a number of milliseconds or as a
+/// human readable string made of a number and a unit (`ms`, `s`, `m`, `h` or `d`), like `500ms`,
+/// `30s` or `1h`.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub struct HumanDuration(pub std::time::Duration);
+
+impl HumanDuration {
+    pub const fn from_millis(millis: u64) -> HumanDuration {
+        HumanDuration(std::time::Duration::from_millis(millis))
+    }
+
+    pub const fn from_secs(secs: u64) -> HumanDuration {
+        HumanDuration(std::time::Duration::from_secs(secs))
+    }
+
+    /// returns the duration as a `chrono::Duration`
+    pub fn to_chrono(self) -> Duration {
+        Duration::from_std(self.0).unwrap_or(Duration::MAX)
+    }
+}
+
+impl FromStr for HumanDuration {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let split_at = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+        let (amount, unit) = s.split_at(split_at);
+
+        let amount: u64 = amount.parse().map_err(|_| anyhow!("invalid duration: '{s}'"))?;
+
+        let unit_millis: u64 = match unit.trim() {
+            "" | "ms" => 1,
+            "s" => 1000,
+            "m" => 60 * 1000,
+            "h" => 60 * 60 * 1000,
+            "d" => 24 * 60 * 60 * 1000,
+            unit => return Err(anyhow!("invalid duration unit '{unit}' in '{s}'")),
+        };
+
+        let millis = amount
+            .checked_mul(unit_millis)
+            .ok_or_else(|| anyhow!("duration is too long: '{s}'"))?;
+
+        Ok(HumanDuration::from_millis(millis))
+    }
+}
+
+impl Display for HumanDuration {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let millis = self.0.as_millis();
+
+        match millis {
+            0 => write!(f, "0s"),
+            m if m % 1000 != 0 => write!(f, "{m}ms"),
+            m if m % (60 * 60 * 1000) == 0 => write!(f, "{}h", m / (60 * 60 * 1000)),
+            m if m % (60 * 1000) == 0 => write!(f, "{}m", m / (60 * 1000)),
+            m => write!(f, "{}s", m / 1000),
+        }
+    }
+}
+
+impl Serialize for HumanDuration {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for HumanDuration {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct HumanDurationVisitor;
+
+        impl Visitor<'_> for HumanDurationVisitor {
+            type Value = HumanDuration;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                write!(f, "a number of milliseconds or a duration string like '30s'")
+            }
+
+            fn visit_u64<E: de::Error>(self, v: u64) -> Result<Self::Value, E> {
+                Ok(HumanDuration::from_millis(v))
+            }
+
+            fn visit_i64<E: de::Error>(self, v: i64) -> Result<Self::Value, E> {
+                u64::try_from(v)
+                    .map(HumanDuration::from_millis)
+                    .map_err(|_| E::custom("duration can't be negative"))
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+                v.parse().map_err(E::custom)
+            }
+        }
+
+        deserializer.deserialize_any(HumanDurationVisitor)
+    }
+}
@@ -1,4 +1,5 @@
 use {
+    super::{duration::HumanDuration, rotation::LogRotation},
     anyhow::Result,
     env::{current_dir, current_exe},
     serde::{Deserialize, Serialize},
@@ -6,6 +7,12 @@ use {
 };
 
 const KURV_HOME_KEY: &str = "KURV_HOME";
+pub const API_TOKEN_KEY: &str = "KURV_API_TOKEN";
+const CGROUP_KEY: &str = "KURV_CGROUP";
+const METRICS_INTERVAL_KEY: &str = "KURV_METRICS_INTERVAL";
+
+/// how often the resource usage of the eggs is sampled by default
+const DEFAULT_METRICS_INTERVAL: HumanDuration = HumanDuration::from_secs(5);
 
 /// Important paths for the application
 #[derive(PartialEq, Eq, Clone, Deserialize, Serialize)]
@@ -29,6 +36,11 @@ pub struct Paths {
 
     /// logs directory
     pub logs_dir: PathBuf,
+
+    /// the cgroup (v2) delegated to kurv, taken from the `KURV_CGROUP` env var; when set,
+    /// each instance of each egg runs in its own cgroup inside it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cgroup: Option<PathBuf>,
 }
 
 /// General information about the app
@@ -58,6 +70,20 @@ pub struct Info {
 
     /// important paths for the application
     pub paths: Paths,
+
+    /// global log rotation settings for the eggs' log files
+    #[serde(default)]
+    pub log_rotation: LogRotation,
+
+    /// how often the resource usage of the eggs is sampled, from the `KURV_METRICS_INTERVAL`
+    /// env var (5s by default); `None` if it's disabled (`0`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metrics_interval: Option<HumanDuration>,
+
+    /// token that authenticates api requests (`Authorization: Bearer <token>`), taken from
+    /// the `KURV_API_TOKEN` env var; never sent in responses
+    #[serde(skip)]
+    pub api_token: Option<String>,
 }
 
 impl Default for Info {
@@ -81,9 +107,25 @@ impl Info {
             description: env!("CARGO_PKG_DESCRIPTION").to_string(),
             pid: std::process::id(),
             paths: Info::get_paths().expect("could not get paths"),
+            log_rotation: LogRotation::from_env(),
+            metrics_interval: Info::get_metrics_interval(),
+            api_token: env::var(API_TOKEN_KEY).ok().filter(|token| !token.is_empty()),
         }
     }
 
+    /// Gets the interval at which the resource usage of the eggs is sampled
+    fn get_metrics_interval() -> Option<HumanDuration> {
+        let interval = match env::var(METRICS_INTERVAL_KEY) {
+            Ok(interval) => interval.parse().unwrap_or_else(|err| {
+                log::warn!("invalid {}, using the default: {}", METRICS_INTERVAL_KEY, err);
+                DEFAULT_METRICS_INTERVAL
+            }),
+            Err(_) => DEFAULT_METRICS_INTERVAL,
+        };
+
+        Some(interval).filter(|interval| !interval.0.is_zero())
+    }
+
     /// Gets the paths for the application
     fn get_paths() -> Result<Paths> {
         let executable = current_exe().expect("could not get executable path");
@@ -109,6 +151,9 @@ impl Info {
         // the path to the plugins directory inside the kurv home directory
         let plugins_dir = kurv_home.join("plugins");
 
+        // e.g. /sys/fs/cgroup/kurv.slice, it has to exist and be writable by kurv
+        let cgroup = env::var(CGROUP_KEY).ok().filter(|dir| !dir.is_empty()).map(PathBuf::from);
+
         Ok(Paths {
             executable,
             working_dir,
@@ -116,6 +161,7 @@ impl Info {
             kurv_file,
             plugins_dir,
             logs_dir,
+            cgroup,
         })
     }
 }
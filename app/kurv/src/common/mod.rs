@@ -1,8 +1,12 @@
 mod info;
 
+pub mod cron;
+pub mod dotenv;
 pub mod duration;
 pub mod log;
+pub mod rotation;
+pub mod size;
 pub mod str;
 pub mod tcp;
 
-pub use info::Info;
+pub use info::{API_TOKEN_KEY, Info};
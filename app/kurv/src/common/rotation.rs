@@ -0,0 +1,64 @@
+use {
+    super::{duration::HumanDuration, size::ByteSize},
+    serde::{Deserialize, Serialize},
+    std::env,
+};
+
+/// default number of rotated log files to keep
+const DEFAULT_MAX_FILES: u32 = 5;
+
+/// log rotation settings; set globally through `KURV_LOG_*` environment variables and
+/// overridable per egg, field by field.
+#[derive(PartialEq, Eq, Clone, Serialize, Deserialize, Default, Debug)]
+pub struct LogRotation {
+    /// rotate a log file once it grows past this size
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_size: Option<ByteSize>,
+
+    /// rotate a log file once it has been written to for this long
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub interval: Option<HumanDuration>,
+
+    /// number of rotated files to keep, the oldest ones are deleted (defaults to 5)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_files: Option<u32>,
+
+    /// gzip rotated files (defaults to false)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compress: Option<bool>,
+}
+
+impl LogRotation {
+    /// reads the global log rotation settings from the environment:
+    /// `KURV_LOG_MAX_SIZE`, `KURV_LOG_INTERVAL`, `KURV_LOG_MAX_FILES` and `KURV_LOG_COMPRESS`.
+    ///
+    /// invalid values are ignored.
+    pub fn from_env() -> LogRotation {
+        LogRotation {
+            max_size: env::var("KURV_LOG_MAX_SIZE").ok().and_then(|v| v.parse().ok()),
+            interval: env::var("KURV_LOG_INTERVAL").ok().and_then(|v| v.parse().ok()),
+            max_files: env::var("KURV_LOG_MAX_FILES").ok().and_then(|v| v.parse().ok()),
+            compress: env::var("KURV_LOG_COMPRESS").ok().map(|v| v == "true" || v == "1"),
+        }
+    }
+
+    /// returns a copy of these settings, with the unset fields taken from `fallback`
+    pub fn or(&self, fallback: &LogRotation) -> LogRotation {
+        LogRotation {
+            max_size: self.max_size.or(fallback.max_size),
+            interval: self.interval.or(fallback.interval),
+            max_files: self.max_files.or(fallback.max_files),
+            compress: self.compress.or(fallback.compress),
+        }
+    }
+
+    /// returns the number of rotated files to keep
+    pub fn max_files(&self) -> u32 {
+        self.max_files.unwrap_or(DEFAULT_MAX_FILES)
+    }
+
+    /// checks if rotated files should be gzipped
+    pub fn compress(&self) -> bool {
+        self.compress.unwrap_or(false)
+    }
+}
@@ -0,0 +1,98 @@
+use {
+    anyhow::anyhow,
+    serde::{
+        Deserialize, Deserializer, Serialize, Serializer,
+        de::{self, Visitor},
+    },
+    std::{fmt::Display, str::FromStr},
+};
+
+const KB: u64 = 1024;
+const MB: u64 = 1024 * KB;
+const GB: u64 = 1024 * MB;
+
+/// returns a size in bytes in a human readable way, with one decimal (e.g. `12.3M`)
+pub fn humanize_size(bytes: u64) -> String {
+    let (amount, unit) = match bytes {
+        b if b >= GB => (b as f64 / GB as f64, "G"),
+        b if b >= MB => (b as f64 / MB as f64, "M"),
+        b if b >= KB => (b as f64 / KB as f64, "K"),
+        b => return format!("{b}B"),
+    };
+
+    format!("{amount:.1}{unit}")
+}
+
+/// a size in bytes that can be written in config files either as a number of bytes or as a
+/// human readable string made of a number and a unit (`B`, `K`, `M` or `G`, optionally
+/// followed by `B`), like `512K`, `10MB` or `1G`. Units are powers of 1024.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug, Default)]
+pub struct ByteSize(pub u64);
+
+impl FromStr for ByteSize {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let split_at = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+        let (amount, unit) = s.split_at(split_at);
+
+        let amount: u64 = amount.parse().map_err(|_| anyhow!("invalid size: '{s}'"))?;
+
+        let multiplier = match unit.trim().to_ascii_uppercase().as_str() {
+            "" | "B" => 1,
+            "K" | "KB" => KB,
+            "M" | "MB" => MB,
+            "G" | "GB" => GB,
+            _ => return Err(anyhow!("invalid size unit '{}' in '{s}'", unit.trim())),
+        };
+
+        amount.checked_mul(multiplier).map(ByteSize).ok_or_else(|| anyhow!("size too big: '{s}'"))
+    }
+}
+
+impl Display for ByteSize {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0 {
+            0 => write!(f, "0B"),
+            b if b % GB == 0 => write!(f, "{}G", b / GB),
+            b if b % MB == 0 => write!(f, "{}M", b / MB),
+            b if b % KB == 0 => write!(f, "{}K", b / KB),
+            b => write!(f, "{b}B"),
+        }
+    }
+}
+
+impl Serialize for ByteSize {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for ByteSize {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ByteSizeVisitor;
+
+        impl Visitor<'_> for ByteSizeVisitor {
+            type Value = ByteSize;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                write!(f, "a number of bytes or a size string like '10M'")
+            }
+
+            fn visit_u64<E: de::Error>(self, v: u64) -> Result<Self::Value, E> {
+                Ok(ByteSize(v))
+            }
+
+            fn visit_i64<E: de::Error>(self, v: i64) -> Result<Self::Value, E> {
+                u64::try_from(v).map(ByteSize).map_err(|_| E::custom("size can't be negative"))
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+                v.parse().map_err(E::custom)
+            }
+        }
+
+        deserializer.deserialize_any(ByteSizeVisitor)
+    }
+}
@@ -4,8 +4,9 @@ use {
     std::{
         collections::HashMap,
         fmt::Display,
-        io::{BufReader, Read, Write, prelude::BufRead},
+        io::{self, BufReader, ErrorKind, Read, Write, prelude::BufRead},
         net::TcpStream,
+        thread,
     },
 };
 
@@ -71,10 +72,124 @@ pub struct ErrorResponse {
     pub code: u16,
     pub status: String,
     pub message: String,
+
+    /// what's wrong with the request, field by field (e.g. an invalid egg config)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub problems: Vec<Problem>,
+}
+
+/// how bad a `Problem` is
+#[derive(PartialEq, Eq, Clone, Copy, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// the request can't be done
+    Error,
+    /// the request can be done, but probably not as intended
+    Warning,
+}
+
+/// a problem found in a field of a request
+#[derive(PartialEq, Eq, Clone, Serialize, Deserialize, Debug)]
+pub struct Problem {
+    /// path of the field, e.g. `cwd` or `env.PORT`
+    pub field: String,
+    pub message: String,
+    pub severity: Severity,
+}
+
+impl Problem {
+    pub fn error(field: &str, message: String) -> Problem {
+        Problem {
+            field: field.to_string(),
+            message,
+            severity: Severity::Error,
+        }
+    }
+
+    pub fn warning(field: &str, message: String) -> Problem {
+        Problem {
+            field: field.to_string(),
+            message,
+            severity: Severity::Warning,
+        }
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+/// body of a streamed response, written chunk by chunk on its own thread until it's done or
+/// the client goes away.
+pub type StreamBody = Box<dyn FnOnce(&mut ChunkedWriter) -> io::Result<()> + Send>;
+
+/// answer of a streaming handler: either a streamed body or, when the request can't be
+/// streamed (e.g. it targets an unknown egg), a regular response.
+pub enum Streamed {
+    Body {
+        headers: Vec<String>,
+        body: StreamBody,
+    },
+    Response(Response),
 }
 
 pub trait Handler {
     fn handle(&self, request: &mut Request) -> Response;
+
+    /// takes over requests whose response is streamed (e.g. following a log file); requests
+    /// for which it returns `None` are answered by `handle`.
+    fn stream(&self, _request: &mut Request) -> Option<Streamed> {
+        None
+    }
+}
+
+/// writes a response body using chunked transfer encoding, one chunk per `write`.
+pub struct ChunkedWriter {
+    stream: TcpStream,
+}
+
+impl ChunkedWriter {
+    /// checks if the client closed the connection, without blocking
+    pub fn is_closed(&self) -> bool {
+        if self.stream.set_nonblocking(true).is_err() {
+            return true;
+        }
+
+        let closed = match self.stream.peek(&mut [0u8; 1]) {
+            Ok(0) => true,
+            Ok(_) => false,
+            Err(err) => err.kind() != ErrorKind::WouldBlock,
+        };
+
+        let _ = self.stream.set_nonblocking(false);
+        closed
+    }
+
+    /// writes the last (empty) chunk
+    fn finish(mut self) -> io::Result<()> {
+        self.stream.write_all(b"0\r\n\r\n")?;
+        self.stream.flush()
+    }
+}
+
+impl Write for ChunkedWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // an empty chunk would end the body
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        self.stream.write_all(format!("{:x}\r\n", buf.len()).as_bytes())?;
+        self.stream.write_all(buf)?;
+        self.stream.write_all(b"\r\n")?;
+        self.stream.flush()?;
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush()
+    }
 }
 
 /// Returns a JSON response with the given body and status code.
@@ -89,12 +204,18 @@ pub fn json<T: Serialize>(status: u16, body: T) -> Response {
 }
 
 pub fn err(status: u16, msg: String) -> Response {
+    err_with_problems(status, msg, vec![])
+}
+
+/// Returns an error response listing the problems found in the request.
+pub fn err_with_problems(status: u16, msg: String, problems: Vec<Problem>) -> Response {
     json(
         status,
         ErrorResponse {
             code: status,
             status: get_status_text(status),
             message: msg,
+            problems,
         },
     )
 }
@@ -168,8 +289,15 @@ pub fn handle(mut stream: TcpStream, handler: &impl Handler) {
         path_params: HashMap::new(),
     };
 
-    // Handle the request and get the response
-    let response = handler.handle(&mut request);
+    // streamed responses take over the connection on their own thread, so they don't block
+    // the rest of the requests
+    let response = match handler.stream(&mut request) {
+        Some(Streamed::Body { headers, body }) => {
+            return stream_response(stream, headers, body);
+        }
+        Some(Streamed::Response(response)) => response,
+        None => handler.handle(&mut request),
+    };
 
     let http_response = format!(
         "HTTP/1.1 {} {}\r\n{}\r\n\r\n{}",
@@ -183,17 +311,48 @@ pub fn handle(mut stream: TcpStream, handler: &impl Handler) {
     stream.write_all(http_response.as_bytes()).unwrap();
 }
 
+/// sends the headers of a streamed response and then writes its body on a new thread.
+fn stream_response(mut stream: TcpStream, headers: Vec<String>, body: StreamBody) {
+    let head = format!(
+        "HTTP/1.1 200 {}\r\n{}\r\n\r\n",
+        get_status_text(200),
+        get_common_headers(headers, "Transfer-Encoding: chunked".to_string()),
+    );
+
+    if let Err(err) = stream.write_all(head.as_bytes()) {
+        trace!("failed to write streamed response: {}", err);
+        return;
+    }
+
+    thread::spawn(move || {
+        let mut writer = ChunkedWriter { stream };
+
+        match body(&mut writer) {
+            Ok(()) => {
+                let _ = writer.finish();
+            }
+            Err(err) => trace!("streamed response ended: {}", err),
+        }
+    });
+}
+
 /// Returns the final headers string including content-length and other defaults.
 fn get_headers(user_headers: Vec<String>, body: &[u8]) -> String {
+    get_common_headers(user_headers, format!("Content-Length: {}", body.len()))
+}
+
+/// Returns the final headers string with the given body length header and other defaults.
+fn get_common_headers(user_headers: Vec<String>, length_header: String) -> String {
     let mut headers = Vec::new();
     headers.push("Server: kurv".to_string());
-    headers.push(format!("Content-Length: {}", body.len()));
+    headers.push(length_header);
     headers.push(format!("Date: {}", chrono::Utc::now().to_rfc2822()));
 
     // cors headers
     headers.push("Access-Control-Allow-Origin: *".to_string());
-    headers.push("Access-Control-Allow-Methods: GET, POST, OPTIONS".to_string());
-    headers.push("Access-Control-Allow-Headers: Content-Type".to_string());
+    headers
+        .push("Access-Control-Allow-Methods: GET, POST, PUT, PATCH, DELETE, OPTIONS".to_string());
+    headers.push("Access-Control-Allow-Headers: Content-Type, Authorization".to_string());
 
     headers.extend(user_headers);
     headers.join("\r\n")
@@ -0,0 +1,294 @@
+// each instance of each egg runs in its own cgroup (v2), inside the one delegated to kurv
+// (`KURV_CGROUP`): `<KURV_CGROUP>/<egg name>/<instance index>`. The cgroup holds every
+// process the instance spawns, even those that leave its process group, so it's used to
+// limit them as a whole, to kill all of them, and to know if the OOM killer stepped in.
+
+use {
+    super::egg::{EggCgroup, StopSignal},
+    crate::common::duration::HumanDuration,
+    chrono::{DateTime, Local},
+    log::{debug, warn},
+    std::{
+        fs,
+        io::ErrorKind,
+        path::{Path, PathBuf},
+        process::Command,
+    },
+};
+
+/// the controllers kurv needs for the limits of the eggs
+const CONTROLLERS: [&str; 3] = ["memory", "cpu", "pids"];
+
+/// the cgroup an instance of an egg runs in
+pub(crate) struct InstanceCgroup {
+    path: PathBuf,
+}
+
+/// the cgroup of an instance that is gone, with processes that have been asked to stop but
+/// might still be around; it's removed once they're gone, and they're killed once their time
+/// is up. It's checked on every tick, so the main loop never waits for them.
+pub(crate) struct LeftoverCgroup {
+    cgroup: InstanceCgroup,
+    egg_name: String,
+    index: usize,
+    kill_at: DateTime<Local>,
+}
+
+impl LeftoverCgroup {
+    /// checks if this is the cgroup of the instance with the given `index` of the egg
+    pub fn is_instance(&self, egg_name: &str, index: usize) -> bool {
+        self.egg_name == egg_name && self.index == index
+    }
+
+    /// tries to remove the cgroup, killing its processes if their time is up; returns
+    /// whether it's gone
+    pub fn clean_up(&self) -> bool {
+        if Local::now() >= self.kill_at {
+            self.cgroup.kill();
+        }
+
+        self.cgroup.remove()
+    }
+}
+
+/// enables the controllers kurv needs in the cgroup delegated to it, so they are available
+/// to the cgroups of the eggs; the ones that can't be enabled are just logged, as they are
+/// only needed by eggs that set the matching limit.
+pub(crate) fn enable_controllers(root: &Path) {
+    if !root.join("cgroup.controllers").is_file() {
+        warn!("{} is not a cgroup (v2), eggs won't be able to run", root.display());
+        return;
+    }
+
+    if let Err(err) = delegate_controllers(root) {
+        warn!("can't enable the cgroup controllers of {}: {}", root.display(), err);
+    }
+}
+
+/// enables the available controllers kurv needs in the subtree of the given cgroup
+fn delegate_controllers(cgroup: &Path) -> Result<(), String> {
+    let available =
+        fs::read_to_string(cgroup.join("cgroup.controllers")).map_err(|err| err.to_string())?;
+
+    let missing: Vec<&str> = CONTROLLERS
+        .into_iter()
+        .filter(|controller| !available.split_whitespace().any(|name| name == *controller))
+        .collect();
+
+    for controller in CONTROLLERS.iter().filter(|controller| !missing.contains(controller)) {
+        fs::write(cgroup.join("cgroup.subtree_control"), format!("+{controller}"))
+            .map_err(|err| format!("{controller}: {err}"))?;
+    }
+
+    if missing.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("{} not available", missing.join(", ")))
+    }
+}
+
+impl InstanceCgroup {
+    /// returns the cgroup of the instance with the given `index` of the egg
+    pub fn new(root: &Path, egg_name: &str, index: usize) -> InstanceCgroup {
+        InstanceCgroup {
+            path: root.join(egg_name.replace('/', "_")).join(index.to_string()),
+        }
+    }
+
+    /// creates the cgroup with the given limits; a cgroup left over from a previous run of
+    /// the instance is cleared first, so its counters (e.g. OOM kills) start from zero.
+    pub fn create(&self, limits: &EggCgroup) -> Result<(), String> {
+        if self.path.exists() {
+            self.kill();
+
+            // the killed processes take a moment to be gone, it's tried again later
+            if !self.remove() {
+                return Err(format!(
+                    "the processes left in cgroup {} are still being killed",
+                    self.path.display()
+                ));
+            }
+        }
+
+        let egg_dir = self.path.parent().unwrap_or(&self.path);
+        let is_new = !egg_dir.exists();
+
+        fs::create_dir_all(&self.path)
+            .map_err(|err| format!("can't create cgroup {}: {}", self.path.display(), err))?;
+
+        // the missing ones have already been reported for the cgroup of kurv
+        if is_new && let Err(err) = delegate_controllers(egg_dir) {
+            debug!("can't enable the cgroup controllers of {}: {}", egg_dir.display(), err);
+        }
+
+        for (file, value) in limits.files() {
+            let path = self.path.join(file);
+
+            // the interface files of a controller are only there if it's enabled
+            let result = match &value {
+                Some(_) if !path.exists() => {
+                    let controller = file.split('.').next().unwrap_or(file);
+                    Err(format!("the {controller} controller is not enabled"))
+                }
+                Some(value) => fs::write(&path, value).map_err(|err| err.to_string()),
+                // the limits that aren't set are reset, if their controller is there
+                None => {
+                    let _ = fs::write(&path, "max");
+                    Ok(())
+                }
+            };
+
+            if let Err(reason) = result {
+                let _ = self.remove();
+                return Err(format!(
+                    "can't set {file} of cgroup {}: {reason}",
+                    self.path.display()
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// sets up a command so its process moves itself into this cgroup before anything else,
+    /// so every process it spawns is in it too
+    pub fn attach(&self, command: &mut Command) -> Result<(), String> {
+        attach_to_cgroup(command, &self.path.join("cgroup.procs"))
+    }
+
+    /// checks if the OOM killer killed any process of the cgroup
+    fn oom_killed(&self) -> bool {
+        let Ok(events) = fs::read_to_string(self.path.join("memory.events")) else {
+            return false;
+        };
+
+        events.lines().any(|line| {
+            line.strip_prefix("oom_kill ")
+                .and_then(|count| count.trim().parse::<u64>().ok())
+                .is_some_and(|count| count > 0)
+        })
+    }
+
+    /// kills every process of the cgroup
+    pub fn kill(&self) {
+        // cgroup.kill is there since linux 5.14, before that they are killed one by one
+        if fs::write(self.path.join("cgroup.kill"), "1").is_ok() {
+            return;
+        }
+
+        for pid in self.pids() {
+            signal_pid(pid, StopSignal::Kill);
+        }
+    }
+
+    /// asks the processes left in the cgroup once the instance is gone (e.g. the ones that
+    /// left its process group) to stop with the egg's stop `signal`, and removes it if there
+    /// are none. Returns whether the OOM killer stepped in, and the cgroup if it's still
+    /// there: its processes are killed after `kill_timeout` (see `LeftoverCgroup`).
+    pub fn release(
+        self,
+        egg_name: &str,
+        index: usize,
+        signal: StopSignal,
+        kill_timeout: HumanDuration,
+    ) -> (bool, Option<LeftoverCgroup>) {
+        let oom_killed = self.oom_killed();
+
+        if self.remove() {
+            return (oom_killed, None);
+        }
+
+        for pid in self.pids() {
+            signal_pid(pid, signal);
+        }
+
+        let leftover = LeftoverCgroup {
+            cgroup: self,
+            egg_name: egg_name.to_string(),
+            index,
+            kill_at: Local::now() + kill_timeout.to_chrono(),
+        };
+
+        (oom_killed, Some(leftover))
+    }
+
+    /// removes the cgroup (and the one of the egg, if it was its last instance), unless it
+    /// still has processes; returns whether it's gone
+    pub fn remove(&self) -> bool {
+        match fs::remove_dir(&self.path) {
+            Ok(()) => {}
+            Err(err) if err.kind() == ErrorKind::NotFound => {}
+            Err(err) if err.kind() == ErrorKind::ResourceBusy => return false,
+            Err(err) => {
+                warn!("can't remove cgroup {}: {}", self.path.display(), err);
+            }
+        }
+
+        // it only goes away once all the instances are gone
+        if let Some(egg_dir) = self.path.parent() {
+            let _ = fs::remove_dir(egg_dir);
+        }
+
+        true
+    }
+
+    /// returns the pids of the processes in the cgroup
+    fn pids(&self) -> Vec<u32> {
+        fs::read_to_string(self.path.join("cgroup.procs"))
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|pid| pid.trim().parse().ok())
+            .collect()
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn attach_to_cgroup(command: &mut Command, procs: &Path) -> Result<(), String> {
+    use std::{ffi::CString, os::unix::ffi::OsStrExt, os::unix::process::CommandExt};
+
+    let path = CString::new(procs.as_os_str().as_bytes())
+        .map_err(|_| format!("invalid cgroup path {}", procs.display()))?;
+
+    // SAFETY: the closure runs in the forked child, where allocating isn't safe. The `path`
+    // CString is built above, before the fork, and the closure only passes its pointer to
+    // `open`, `write` and `close`, which are async-signal-safe and take no locks
+    unsafe {
+        command.pre_exec(move || {
+            let fd = libc::open(path.as_ptr(), libc::O_WRONLY | libc::O_CLOEXEC);
+            if fd < 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+
+            // "0" stands for the process that writes it
+            let written = libc::write(fd, b"0".as_ptr().cast(), 1);
+            let result = if written < 0 {
+                Err(std::io::Error::last_os_error())
+            } else {
+                Ok(())
+            };
+
+            libc::close(fd);
+            result
+        });
+    }
+
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn attach_to_cgroup(_command: &mut Command, _procs: &Path) -> Result<(), String> {
+    Err("cgroups are only supported on linux".to_string())
+}
+
+#[cfg(unix)]
+fn signal_pid(pid: u32, signal: StopSignal) {
+    let signal = command_group::Signal::from(signal) as libc::c_int;
+
+    // SAFETY: it's just a syscall
+    unsafe {
+        libc::kill(pid as libc::pid_t, signal);
+    }
+}
+
+#[cfg(not(unix))]
+fn signal_pid(_pid: u32, _signal: StopSignal) {}
@@ -0,0 +1,138 @@
+use {
+    super::Egg,
+    crate::{common::tcp::Problem, kurv::KurvState},
+    anyhow::{Context, Result, anyhow},
+    serde::Deserialize,
+    serde_json::{Map, Value},
+    std::{
+        collections::{BTreeMap, HashSet},
+        fs,
+        path::Path,
+    },
+};
+
+/// 🧺 » a basket file lists many eggs, plus defaults shared by all of them:
+///
+/// ```yaml
+/// defaults:
+///   cwd: /srv/app
+///   env:
+///     NODE_ENV: production
+/// eggs:
+///   - name: api
+///     command: node
+///     args: [server.js]
+///   - name: worker
+///     command: node
+///     args: [worker.js]
+/// ```
+#[derive(Deserialize)]
+struct BasketFile {
+    #[serde(default)]
+    defaults: Map<String, Value>,
+    eggs: Vec<Map<String, Value>>,
+}
+
+/// the eggs of a basket file, with its defaults applied
+pub struct Basket {
+    pub eggs: Vec<Egg>,
+}
+
+impl Basket {
+    /// loads a basket from the given path.
+    pub fn load(path: &Path) -> Result<Basket> {
+        if !path.exists() {
+            return Err(anyhow!("file {} not found", path.display()));
+        }
+
+        let yaml = fs::read_to_string(path)
+            .with_context(|| format!("failed to open basket file: {}", path.display()))?;
+
+        Basket::parse(&yaml).context(format!("failed to parse basket file: {}", path.display()))
+    }
+
+    /// parses a basket from its yaml.
+    ///
+    /// the `defaults` are merged into each egg: fields set in the egg win, and nested settings
+    /// (e.g. `env` or `restart`) are merged field by field.
+    pub fn parse(yaml: &str) -> Result<Basket> {
+        // the eggs are read loosely (as json values) to merge them with the defaults, so only
+        // `true`/`false` are taken as booleans; e.g. `DEBUG: yes` stays a string for the env
+        let options = serde_saphyr::Options {
+            strict_booleans: true,
+            ..Default::default()
+        };
+
+        let file: BasketFile = serde_saphyr::from_str_with_options(yaml, options)?;
+
+        Basket::from_values(file.defaults, file.eggs)
+    }
+
+    /// checks the dependencies of the eggs of the basket as they'd be once it's applied, along
+    /// with the `others` eggs (the ones in the server that the basket leaves as they are).
+    ///
+    /// returns the problems found, by egg name: dependencies on eggs that wouldn't exist and
+    /// dependency cycles, which the server can only see one egg at a time.
+    pub fn check_dependencies(&self, others: &[Egg]) -> Vec<(String, Problem)> {
+        let eggs = others.iter().chain(self.eggs.iter());
+        let state = KurvState {
+            eggs: eggs.map(|egg| (egg.name.clone(), egg.clone())).collect::<BTreeMap<_, _>>(),
+        };
+
+        let mut problems = vec![];
+
+        for egg in self.eggs.iter() {
+            for dep in egg.dependencies() {
+                if state.get_by_name(dep).is_none() {
+                    let msg = format!("depends on {dep}, which isn't in the basket nor the server");
+                    problems.push((egg.name.clone(), Problem::error("depends_on", msg)));
+                }
+            }
+
+            if let Some(cycle) = state.find_dependency_cycle(egg) {
+                let msg = format!("dependency cycle detected: {}", cycle.join(" -> "));
+                problems.push((egg.name.clone(), Problem::error("depends_on", msg)));
+            }
+        }
+
+        problems
+    }
+
+    fn from_values(defaults: Map<String, Value>, eggs: Vec<Map<String, Value>>) -> Result<Basket> {
+        let mut names = HashSet::new();
+        let mut basket = Basket { eggs: vec![] };
+
+        for (index, fields) in eggs.into_iter().enumerate() {
+            let mut merged = defaults.clone();
+            merge(&mut merged, fields);
+
+            // parsed back as yaml (json is valid yaml) so the egg is read exactly like an egg
+            // file would be, e.g. `PORT: 8080` is fine for an env var
+            let yaml = serde_json::to_string(&Value::Object(merged))?;
+            let mut egg: Egg = serde_saphyr::from_str(&yaml)
+                .with_context(|| format!("invalid egg #{} in basket", index + 1))?;
+
+            if !names.insert(egg.name.clone()) {
+                return Err(anyhow!("egg {} is listed more than once in the basket", egg.name));
+            }
+
+            // ids are assigned by the server
+            egg.id = None;
+            basket.eggs.push(egg);
+        }
+
+        Ok(basket)
+    }
+}
+
+/// merges `fields` into `target`, recursively for objects; everything else is replaced
+fn merge(target: &mut Map<String, Value>, fields: Map<String, Value>) {
+    for (key, value) in fields {
+        match (target.get_mut(&key), value) {
+            (Some(Value::Object(target)), Value::Object(value)) => merge(target, value),
+            (_, value) => {
+                target.insert(key, value);
+            }
+        }
+    }
+}
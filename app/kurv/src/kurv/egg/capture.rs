@@ -0,0 +1,51 @@
+use {
+    chrono::format::{Item, StrftimeItems},
+    serde::{Deserialize, Serialize},
+};
+
+/// default format of the timestamps prefixed to each log line
+pub const DEFAULT_TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S%.3f";
+
+/// format of the lines written to the log files of an egg
+#[derive(PartialEq, Eq, Clone, Copy, Serialize, Deserialize, Default, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// the output as is (optionally prefixed, see `LogCapture`)
+    #[default]
+    Raw,
+    /// one json object per line, with the fields `ts`, `egg`, `id`, `instance`, `stream`,
+    /// `pid` and `msg`
+    Json,
+}
+
+/// how the output of an egg is written to its log files.
+///
+/// by default each line is written as is to `<name>.stdout` and `<name>.stderr`.
+#[derive(PartialEq, Eq, Clone, Serialize, Deserialize, Default, Debug)]
+pub struct LogCapture {
+    /// prefixes each line with the time it was written at and the stream it comes from
+    /// (`[out]` or `[err]`)
+    #[serde(default)]
+    pub timestamps: bool,
+
+    /// strftime format of the timestamps (defaults to `%Y-%m-%d %H:%M:%S%.3f`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp_format: Option<String>,
+
+    /// also writes both streams, interleaved and tagged, to `<name>.log`
+    #[serde(default)]
+    pub combined: bool,
+}
+
+impl LogCapture {
+    /// returns the format of the timestamps, or an error if it isn't a valid strftime format
+    pub fn timestamp_format(&self) -> Result<String, String> {
+        let format = self.timestamp_format.as_deref().unwrap_or(DEFAULT_TIMESTAMP_FORMAT);
+
+        if StrftimeItems::new(format).any(|item| matches!(item, Item::Error)) {
+            return Err(format!("invalid log timestamp format: {format}"));
+        }
+
+        Ok(format.to_string())
+    }
+}
@@ -0,0 +1,172 @@
+use {
+    super::Egg,
+    crate::common::{size::ByteSize, tcp::Problem},
+    serde::{
+        Deserialize, Deserializer, Serialize, Serializer,
+        de::{self, Visitor},
+    },
+    std::fmt::Display,
+};
+
+/// limits of the cgroup (v2) each instance of an egg runs in, when kurv is given one to
+/// manage (`KURV_CGROUP`)
+#[derive(PartialEq, Eq, Clone, Serialize, Deserialize, Default, Debug)]
+pub struct EggCgroup {
+    /// max memory of all the processes of the instance (`memory.max`); the kernel's OOM
+    /// killer steps in when it's exceeded
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memory_max: Option<ByteSize>,
+
+    /// max cpu time the instance gets, in cpus (e.g. `1.5` or `"50%"`) (`cpu.max`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cpu_max: Option<CpuMax>,
+
+    /// max number of processes (and threads) of the instance (`pids.max`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pids_max: Option<u64>,
+}
+
+/// an amount of cpu time, in thousandths of a cpu
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct CpuMax(pub u64);
+
+/// the period `cpu.max` quotas are written for, in microseconds
+const CPU_PERIOD: u64 = 100_000;
+
+impl Egg {
+    /// returns the cgroup limits of the egg (none by default)
+    pub fn cgroup(&self) -> EggCgroup {
+        self.cgroup.clone().unwrap_or_default()
+    }
+}
+
+impl EggCgroup {
+    /// checks the values of the limits
+    pub fn validate(&self) -> Vec<Problem> {
+        let mut problems = vec![];
+
+        if self.memory_max == Some(ByteSize(0)) {
+            let msg = "memory_max has to be greater than 0".to_string();
+            problems.push(Problem::error("cgroup.memory_max", msg));
+        }
+
+        // the kernel doesn't take quotas under 1ms per period
+        if let Some(cpu_max) = self.cpu_max
+            && cpu_max.0 < 10
+        {
+            let msg = format!("cpu_max has to be at least 0.01, not {cpu_max}");
+            problems.push(Problem::error("cgroup.cpu_max", msg));
+        }
+
+        if self.pids_max == Some(0) {
+            let msg = "pids_max has to be greater than 0".to_string();
+            problems.push(Problem::error("cgroup.pids_max", msg));
+        }
+
+        problems
+    }
+
+    /// returns the limits that are set, as `(field, value)` pairs to be displayed
+    pub fn describe(&self) -> Vec<(&'static str, String)> {
+        let mut limits = vec![];
+
+        if let Some(memory_max) = self.memory_max {
+            limits.push(("memory", memory_max.to_string()));
+        }
+
+        if let Some(cpu_max) = self.cpu_max {
+            limits.push(("cpus", cpu_max.to_string()));
+        }
+
+        if let Some(pids_max) = self.pids_max {
+            limits.push(("pids", pids_max.to_string()));
+        }
+
+        limits
+    }
+
+    /// returns the cgroup interface files these limits are written to, with the value of the
+    /// limit, or `None` if it isn't set (and the file should be reset to `max`)
+    pub fn files(&self) -> Vec<(&'static str, Option<String>)> {
+        vec![
+            ("memory.max", self.memory_max.map(|size| size.0.to_string())),
+            ("cpu.max", self.cpu_max.map(|cpu| cpu.quota())),
+            ("pids.max", self.pids_max.map(|pids| pids.to_string())),
+        ]
+    }
+}
+
+impl CpuMax {
+    /// returns the value of `cpu.max` for this amount of cpu time: `<quota> <period>`
+    pub fn quota(&self) -> String {
+        format!("{} {}", self.0 * CPU_PERIOD / 1000, CPU_PERIOD)
+    }
+
+    /// parses a number of cpus (`"1.5"`) or a percentage of one (`"150%"`)
+    fn parse(value: &str) -> Option<CpuMax> {
+        let value = value.trim();
+
+        let cpus = match value.strip_suffix('%') {
+            Some(percent) => percent.trim().parse::<f64>().ok()? / 100.0,
+            None => value.parse::<f64>().ok()?,
+        };
+
+        CpuMax::from_cpus(cpus)
+    }
+
+    fn from_cpus(cpus: f64) -> Option<CpuMax> {
+        if cpus.is_finite() && cpus >= 0.0 {
+            Some(CpuMax((cpus * 1000.0).round() as u64))
+        } else {
+            None
+        }
+    }
+}
+
+impl Display for CpuMax {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0 as f64 / 1000.0)
+    }
+}
+
+impl Serialize for CpuMax {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_f64(self.0 as f64 / 1000.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for CpuMax {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct CpuMaxVisitor;
+
+        impl Visitor<'_> for CpuMaxVisitor {
+            type Value = CpuMax;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                write!(f, "a number of cpus like 1.5 or a percentage like '150%'")
+            }
+
+            fn visit_u64<E: de::Error>(self, v: u64) -> Result<Self::Value, E> {
+                Ok(CpuMax(v.saturating_mul(1000)))
+            }
+
+            fn visit_i64<E: de::Error>(self, v: i64) -> Result<Self::Value, E> {
+                self.visit_f64(v as f64)
+            }
+
+            fn visit_f64<E: de::Error>(self, v: f64) -> Result<Self::Value, E> {
+                CpuMax::from_cpus(v).ok_or_else(|| {
+                    E::custom(format!("invalid cpu_max {v}, expected cpus like 1.5"))
+                })
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+                CpuMax::parse(v).ok_or_else(|| {
+                    E::custom(format!("invalid cpu_max '{v}', expected cpus like 1.5 or '150%'"))
+                })
+            }
+        }
+
+        deserializer.deserialize_any(CpuMaxVisitor)
+    }
+}
@@ -0,0 +1,337 @@
+use {
+    super::{ConfigChange, Egg, InheritEnv, interpolate},
+    crate::common::{size::ByteSize, tcp::Problem},
+    chrono::Local,
+    serde::{
+        Deserialize, Deserializer, Serialize,
+        de::{IgnoredAny, MapAccess, Visitor},
+    },
+    std::{
+        collections::HashSet,
+        env,
+        ffi::OsString,
+        fmt,
+        path::{Path, PathBuf},
+    },
+};
+
+/// how the server would run an egg, as found by a dry run (see `Egg::check`)
+#[derive(PartialEq, Eq, Clone, Serialize, Deserialize, Default, Debug)]
+pub struct EggCheck {
+    pub egg: String,
+
+    /// working directory the egg would run in
+    pub cwd: PathBuf,
+
+    /// executable the command resolves to, `None` if it couldn't be found
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<PathBuf>,
+
+    /// problems found in the config of the egg; it can't be collected if any is an error
+    #[serde(default)]
+    pub problems: Vec<Problem>,
+
+    /// when re-collecting, what would change in the config of the egg (secrets masked)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub changes: Vec<ConfigChange>,
+}
+
+impl EggCheck {
+    /// returns whether the egg can be collected (there can be warnings)
+    pub fn is_ok(&self) -> bool {
+        !self.problems.iter().any(Problem::is_error)
+    }
+}
+
+/// names of the env vars in the `env` of a raw egg config (yaml or json), in order and as many
+/// times as they're set: a parsed egg only keeps the last value of each one
+#[derive(Deserialize, Default)]
+pub struct EnvKeys {
+    #[serde(default)]
+    env: Option<KeyList>,
+}
+
+impl EnvKeys {
+    /// returns an error for each env var that's set more than once
+    pub fn problems(&self) -> Vec<Problem> {
+        let mut seen = HashSet::new();
+        let mut reported = HashSet::new();
+        let mut problems = vec![];
+
+        for key in self.env.iter().flat_map(|keys| keys.0.iter()) {
+            if !seen.insert(key) && reported.insert(key) {
+                let msg = format!("{key} is set more than once");
+                problems.push(Problem::error(&format!("env.{key}"), msg));
+            }
+        }
+
+        problems
+    }
+}
+
+#[derive(Default)]
+struct KeyList(Vec<String>);
+
+impl<'de> Deserialize<'de> for KeyList {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct KeyVisitor;
+
+        impl<'de> Visitor<'de> for KeyVisitor {
+            type Value = KeyList;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a map of env vars")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<KeyList, A::Error> {
+                let mut keys = vec![];
+
+                while let Some(key) = map.next_key::<String>()? {
+                    map.next_value::<IgnoredAny>()?;
+                    keys.push(key);
+                }
+
+                Ok(KeyList(keys))
+            }
+        }
+
+        deserializer.deserialize_map(KeyVisitor)
+    }
+}
+
+impl Egg {
+    /// checks the config of the egg, without looking at the filesystem (see `Egg::check`)
+    pub fn validate(&self) -> Vec<Problem> {
+        let mut problems = vec![];
+
+        if self.name.trim().is_empty() {
+            problems.push(Problem::error("name", "name is empty".to_string()));
+        } else if self.name.contains(|c: char| c.is_whitespace() || c == '/' || c == '\\') {
+            // the name is used for the log files
+            let msg = format!("name {} can't contain spaces or slashes", self.name);
+            problems.push(Problem::error("name", msg));
+        } else if self.name.parse::<usize>().is_ok() {
+            let msg = format!("name {} would be taken for an id, it can't be a number", self.name);
+            problems.push(Problem::error("name", msg));
+        }
+
+        if self.command.trim().is_empty() {
+            problems.push(Problem::error("command", "command is empty".to_string()));
+        }
+
+        if let Some(cwd) = &self.cwd
+            && cwd.is_relative()
+        {
+            let msg = format!(
+                "cwd {} is relative, it's resolved from where the kurv server runs",
+                cwd.display()
+            );
+            problems.push(Problem::warning("cwd", msg));
+        }
+
+        if let Some(env) = &self.env {
+            let mut keys: Vec<&String> = env.keys().collect();
+            keys.sort();
+
+            for key in keys {
+                if key.is_empty() || key.contains(['=', '\0']) {
+                    let msg = format!("invalid env var name: {key:?}");
+                    problems.push(Problem::error(&format!("env.{key}"), msg));
+                }
+            }
+        }
+
+        if let Some(InheritEnv::Only(names)) = &self.inherit_env {
+            for (index, name) in names.iter().enumerate() {
+                if name.trim().is_empty() {
+                    let msg = "env var name is empty".to_string();
+                    problems.push(Problem::error(&format!("inherit_env.{index}"), msg));
+                }
+            }
+        }
+
+        for (field, value) in [("user", &self.user), ("group", &self.group)] {
+            if value.as_ref().is_some_and(|value| value.trim().is_empty()) {
+                problems.push(Problem::error(field, format!("{field} is empty")));
+            }
+        }
+
+        if let Some(restart) = &self.restart {
+            problems.extend(restart.validate());
+        }
+
+        if let Some(limits) = &self.limits {
+            problems.extend(limits.validate());
+        }
+
+        if let Some(cgroup) = &self.cgroup {
+            problems.extend(cgroup.validate());
+        }
+
+        if self.max_memory_restart == Some(ByteSize(0)) {
+            let msg = "max_memory_restart has to be greater than 0".to_string();
+            problems.push(Problem::error("max_memory_restart", msg));
+        }
+
+        if self.max_memory_period.is_some() && self.max_memory_restart.is_none() {
+            let msg = "max_memory_period does nothing without max_memory_restart".to_string();
+            problems.push(Problem::warning("max_memory_period", msg));
+        }
+
+        for (field, cron) in [("cron_restart", &self.cron_restart), ("schedule", &self.schedule)] {
+            if let Some(cron) = cron
+                && cron.next_after(Local::now()).is_none()
+            {
+                let msg = format!("'{cron}' never matches any date");
+                problems.push(Problem::error(field, msg));
+            }
+        }
+
+        if self.is_scheduled() {
+            if self.cron_restart.is_some() {
+                let msg = "cron_restart does nothing for scheduled eggs".to_string();
+                problems.push(Problem::warning("cron_restart", msg));
+            }
+
+            if self.restart.is_some() {
+                let msg = "scheduled eggs aren't restarted, they run again on schedule".to_string();
+                problems.push(Problem::warning("restart", msg));
+            }
+        }
+
+        if self.instances == Some(0) {
+            let msg = "instances is 0, 1 instance will be run".to_string();
+            problems.push(Problem::warning("instances", msg));
+        }
+
+        for (index, dependency) in self.depends_on.iter().flatten().enumerate() {
+            let field = format!("depends_on.{index}");
+
+            if *dependency == self.name {
+                problems.push(Problem::error(&field, "an egg can't depend on itself".to_string()));
+            } else if self.depends_on.iter().flatten().take(index).any(|d| d == dependency) {
+                let msg = format!("{dependency} is listed more than once");
+                problems.push(Problem::warning(&field, msg));
+            }
+        }
+
+        // only the syntax of the `${VAR}` references can be checked here, not if they are set
+        for (field, value) in self.interpolated_fields() {
+            if let Err(msg) = interpolate(&value, |_| Some(String::new())) {
+                problems.push(Problem::error(&field, msg));
+            }
+        }
+
+        if let Some(capture) = &self.log_capture
+            && let Err(msg) = capture.timestamp_format()
+        {
+            problems.push(Problem::error("log_capture.timestamp_format", msg));
+        }
+
+        problems
+    }
+
+    /// validates the egg and checks that it could be spawned: its cwd (or `default_cwd` if it
+    /// doesn't set one) must be a directory, its env files must exist, the variables it
+    /// references must be set, and its command must resolve to an executable.
+    pub fn check(&self, default_cwd: &Path) -> EggCheck {
+        let mut problems = self.validate();
+
+        let resolved = match self.resolve(default_cwd) {
+            Ok(resolved) => Some(resolved),
+            Err(problem) => {
+                // syntax errors were already found by `validate`
+                if !problems.contains(&problem) {
+                    problems.push(problem);
+                }
+
+                None
+            }
+        };
+
+        let (cwd, command_name, env) = match &resolved {
+            Some(resolved) => {
+                (resolved.cwd.clone(), resolved.command.as_str(), resolved.env.clone())
+            }
+            None => (
+                self.cwd.clone().unwrap_or_else(|| default_cwd.to_path_buf()),
+                self.command.as_str(),
+                self.env.clone().unwrap_or_default(),
+            ),
+        };
+
+        // the user and group have to exist (on the server)
+        if let Err(problem) = self.run_as()
+            && !problems.iter().any(|found| found.field == problem.field)
+        {
+            problems.push(problem);
+        }
+
+        if !cwd.is_dir() {
+            let msg = format!("cwd {} is not a directory", cwd.display());
+            problems.push(Problem::error("cwd", msg));
+        }
+
+        // an env var of the egg replaces the PATH of kurv when spawning it
+        let path_var = match env.get("PATH") {
+            Some(path) => Some(OsString::from(path)),
+            None => env::var_os("PATH"),
+        };
+
+        let command = resolve_command(command_name, &cwd, path_var);
+
+        // an empty command was already reported
+        if command.is_none() && !command_name.trim().is_empty() {
+            let msg = format!("command {} not found", command_name);
+            problems.push(Problem::error("command", msg));
+        }
+
+        EggCheck {
+            egg: self.name.clone(),
+            cwd,
+            command,
+            problems,
+            changes: vec![],
+        }
+    }
+}
+
+/// resolves a command the way it would be when spawned from `cwd`: paths are relative to
+/// `cwd`, and bare names are looked up in `path_var` (the value of a `PATH` env var).
+pub fn resolve_command(command: &str, cwd: &Path, path_var: Option<OsString>) -> Option<PathBuf> {
+    if command.trim().is_empty() {
+        return None;
+    }
+
+    let program = Path::new(command);
+
+    if program.components().count() > 1 || program.is_absolute() {
+        return executable(&cwd.join(program));
+    }
+
+    env::split_paths(&path_var?).find_map(|dir| executable(&dir.join(program)))
+}
+
+/// returns the path if it's an executable file
+#[cfg(unix)]
+fn executable(path: &Path) -> Option<PathBuf> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let metadata = path.metadata().ok()?;
+    let is_executable = metadata.is_file() && metadata.permissions().mode() & 0o111 != 0;
+
+    is_executable.then(|| path.to_path_buf())
+}
+
+/// returns the path if it's an executable file, trying the usual extensions if it has none
+#[cfg(windows)]
+fn executable(path: &Path) -> Option<PathBuf> {
+    if path.extension().is_some() {
+        return path.is_file().then(|| path.to_path_buf());
+    }
+
+    ["exe", "bat", "cmd"]
+        .iter()
+        .map(|extension| path.with_extension(extension))
+        .find(|path| path.is_file())
+}
@@ -0,0 +1,122 @@
+use {
+    super::{Egg, EggStatus},
+    serde::{Deserialize, Serialize},
+    serde_json::{Map, Value},
+};
+
+/// fields of an egg that are set by kurv, not by its config
+const RUNTIME_FIELDS: [&str; 5] = ["id", "state", "paths", "plugin", "plugin_path"];
+
+/// a field that differs between two configs of an egg
+#[derive(PartialEq, Eq, Clone, Serialize, Deserialize, Debug)]
+pub struct ConfigChange {
+    /// path of the field, e.g. `command` or `env.PORT`
+    pub field: String,
+
+    /// value before the change, `None` if the field was added
+    pub old: Option<String>,
+
+    /// value after the change, `None` if the field was removed
+    pub new: Option<String>,
+}
+
+impl Egg {
+    /// replaces the configuration of the egg (command, args, cwd, env and the rest of its
+    /// settings) with the one of `config`, keeping its name, id and runtime state.
+    pub fn replace_config(&mut self, config: Egg) {
+        let mut egg = config;
+
+        egg.name = self.name.clone();
+        egg.id = self.id;
+        egg.state = self.state.take();
+        egg.paths = self.paths.take();
+        egg.plugin = self.plugin;
+        egg.plugin_path = self.plugin_path.take();
+
+        *self = egg;
+    }
+
+    /// replaces the configuration of the egg with the one of `config` (see `replace_config`)
+    /// and restarts it so the new config takes effect, with a fresh set of retries.
+    ///
+    /// eggs that are stopped, or being stopped, by the user stay that way: they'll run with
+    /// the new config once they're started again.
+    pub fn recollect(&mut self, config: Egg) {
+        self.replace_config(config);
+        self.reset_try_count();
+
+        let is_stopped_by_user = self.is_stopped()
+            || self.state.as_ref().is_some_and(|state| {
+                state.status == EggStatus::Stopping && state.stop_target == Some(EggStatus::Stopped)
+            });
+
+        if is_stopped_by_user {
+            return;
+        }
+
+        if self.is_stopping() {
+            self.set_stop_target(EggStatus::Restarting);
+        } else {
+            self.set_status(EggStatus::Restarting);
+        }
+    }
+
+    /// returns the fields that change from the config of this egg to the one of `other`.
+    ///
+    /// nested settings (e.g. `env` or `health`) are compared field by field.
+    pub fn config_diff(&self, other: &Egg) -> Vec<ConfigChange> {
+        let mut changes = vec![];
+        diff_objects("", &self.config_fields(), &other.config_fields(), &mut changes);
+
+        changes
+    }
+
+    /// returns the fields of the config of the egg, without the runtime ones
+    fn config_fields(&self) -> Map<String, Value> {
+        let mut fields = match serde_json::to_value(self) {
+            Ok(Value::Object(fields)) => fields,
+            _ => Map::new(),
+        };
+
+        for field in RUNTIME_FIELDS {
+            fields.remove(field);
+        }
+
+        fields
+    }
+}
+
+fn diff_objects(
+    prefix: &str,
+    old: &Map<String, Value>,
+    new: &Map<String, Value>,
+    changes: &mut Vec<ConfigChange>,
+) {
+    let mut keys: Vec<&String> = old.keys().chain(new.keys()).collect();
+    keys.sort();
+    keys.dedup();
+
+    for key in keys {
+        let field = format!("{prefix}{key}");
+
+        match (old.get(key), new.get(key)) {
+            (Some(Value::Object(old)), Some(Value::Object(new))) => {
+                diff_objects(&format!("{field}."), old, new, changes);
+            }
+            (old, new) if old != new => changes.push(ConfigChange {
+                field,
+                old: old.map(display_value),
+                new: new.map(display_value),
+            }),
+            _ => {}
+        }
+    }
+}
+
+/// strings are shown as they are, anything else as json
+fn display_value(value: &Value) -> String {
+    match value {
+        Value::String(string) => string.clone(),
+        value => value.to_string(),
+    }
+}
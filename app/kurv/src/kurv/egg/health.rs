@@ -0,0 +1,350 @@
+use {
+    super::{Egg, EggStatus},
+    crate::common::{API_TOKEN_KEY, duration::HumanDuration},
+    chrono::prelude::*,
+    serde::{Deserialize, Serialize},
+    std::{
+        fmt::Display,
+        io::{BufRead, BufReader, Read, Write},
+        net::{SocketAddr, TcpStream, ToSocketAddrs},
+        path::Path,
+        process::{Command, Stdio},
+        thread::sleep,
+        time::{Duration, Instant},
+    },
+};
+
+/// health of a running egg, as reported by its health check
+#[derive(PartialEq, Eq, Serialize, Deserialize, Clone, Copy, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthStatus {
+    /// the egg has no health check, or it hasn't reported anything yet
+    #[default]
+    Unknown,
+    Healthy,
+    Unhealthy,
+}
+
+impl Display for HealthStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            HealthStatus::Unknown => "unknown",
+            HealthStatus::Healthy => "healthy",
+            HealthStatus::Unhealthy => "unhealthy",
+        };
+
+        write!(f, "{name}")
+    }
+}
+
+/// the probe used to check the health of an egg
+#[derive(PartialEq, Eq, Clone, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthCheck {
+    /// `GET` request against a local port; any 2xx or 3xx response is healthy
+    Http {
+        port: u16,
+        #[serde(default = "default_host")]
+        host: String,
+        #[serde(default = "default_path")]
+        path: String,
+    },
+    /// tcp connection to a local port
+    Tcp {
+        port: u16,
+        #[serde(default = "default_host")]
+        host: String,
+    },
+    /// command run in the egg's cwd and env; exiting with 0 is healthy
+    Exec {
+        command: String,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        args: Vec<String>,
+    },
+}
+
+/// health check configuration of an egg
+#[derive(PartialEq, Eq, Clone, Serialize, Deserialize, Debug)]
+pub struct EggHealth {
+    #[serde(flatten)]
+    pub check: HealthCheck,
+
+    /// time between two consecutive checks
+    #[serde(default = "default_interval")]
+    pub interval: HumanDuration,
+
+    /// time after which a check that didn't answer is considered failed
+    #[serde(default = "default_timeout")]
+    pub timeout: HumanDuration,
+
+    /// number of consecutive failed checks after which the egg is restarted
+    #[serde(default = "default_failure_threshold")]
+    pub failure_threshold: u32,
+
+    /// grace period after the egg starts during which failed checks don't count
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start_period: Option<HumanDuration>,
+}
+
+/// health of an egg, updated after each check
+#[derive(PartialEq, Eq, Clone, Serialize, Deserialize, Default, Debug)]
+pub struct EggHealthState {
+    pub status: HealthStatus,
+
+    /// number of consecutive failed checks
+    #[serde(default)]
+    pub failures: u32,
+
+    /// when the last check was started
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_check: Option<DateTime<Local>>,
+
+    /// reason of the last failed check
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+fn default_host() -> String {
+    "127.0.0.1".to_string()
+}
+
+fn default_path() -> String {
+    "/".to_string()
+}
+
+fn default_interval() -> HumanDuration {
+    HumanDuration::from_secs(10)
+}
+
+fn default_timeout() -> HumanDuration {
+    HumanDuration::from_secs(2)
+}
+
+fn default_failure_threshold() -> u32 {
+    3
+}
+
+impl HealthCheck {
+    /// runs the check for the given `egg`, giving up after `timeout`; `working_dir` is the
+    /// default cwd of the egg, as when it's spawned.
+    ///
+    /// it blocks until the check is done, so it should be run on its own thread.
+    pub fn run(&self, egg: &Egg, working_dir: &Path, timeout: Duration) -> Result<(), String> {
+        match self {
+            HealthCheck::Http { port, host, path } => check_http(host, *port, path, timeout),
+            HealthCheck::Tcp { port, host } => connect(host, *port, timeout).map(|_| ()),
+            HealthCheck::Exec { command, args } => {
+                check_exec(egg, working_dir, command, args, timeout)
+            }
+        }
+    }
+}
+
+fn connect(host: &str, port: u16, timeout: Duration) -> Result<TcpStream, String> {
+    let addrs: Vec<SocketAddr> = (host, port)
+        .to_socket_addrs()
+        .map_err(|err| format!("can't resolve {host}:{port}: {err}"))?
+        .collect();
+
+    let mut last_err = format!("can't resolve {host}:{port}");
+
+    for addr in addrs {
+        match TcpStream::connect_timeout(&addr, timeout) {
+            Ok(stream) => return Ok(stream),
+            Err(err) => last_err = format!("can't connect to {addr}: {err}"),
+        }
+    }
+
+    Err(last_err)
+}
+
+/// max length of the status line of an http health check response
+const MAX_STATUS_LINE: u64 = 1024;
+
+fn check_http(host: &str, port: u16, path: &str, timeout: Duration) -> Result<(), String> {
+    let mut stream = connect(host, port, timeout)?;
+    let _ = stream.set_read_timeout(Some(timeout));
+    let _ = stream.set_write_timeout(Some(timeout));
+
+    let request =
+        format!("GET {path} HTTP/1.0\r\nHost: {host}:{port}\r\nConnection: close\r\n\r\n");
+    stream.write_all(request.as_bytes()).map_err(|err| format!("http request failed: {err}"))?;
+
+    // we only need the status line, which might arrive in pieces
+    let mut status_line = String::new();
+    BufReader::new(&stream)
+        .take(MAX_STATUS_LINE)
+        .read_line(&mut status_line)
+        .map_err(|err| format!("http request failed: {err}"))?;
+
+    let status = status_line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|code| code.parse::<u16>().ok())
+        .ok_or_else(|| "invalid http response".to_string())?;
+
+    match status {
+        200..=399 => Ok(()),
+        status => Err(format!("http check returned status {status}")),
+    }
+}
+
+fn check_exec(
+    egg: &Egg,
+    working_dir: &Path,
+    command: &str,
+    args: &[String],
+    timeout: Duration,
+) -> Result<(), String> {
+    // the same cwd and env the egg is spawned with (env files, `${VAR}` references...)
+    let resolved = egg
+        .resolve(working_dir)
+        .map_err(|problem| format!("invalid {}: {}", problem.field, problem.message))?;
+
+    // it gets no more of the env of kurv than the egg does
+    let mut cmd = Command::new(command);
+    egg.apply_inherit_env(&mut cmd);
+    cmd.env_remove(API_TOKEN_KEY);
+
+    if let Some(run_as) = egg.run_as().map_err(|problem| problem.message)? {
+        run_as.apply(&mut cmd);
+    }
+
+    cmd.args(args)
+        .current_dir(resolved.cwd)
+        .envs(resolved.env)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+
+    let mut child = cmd.spawn().map_err(|err| format!("can't run {command}: {err}"))?;
+    let started = Instant::now();
+
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) if status.success() => return Ok(()),
+            Ok(Some(status)) => {
+                return Err(match status.code() {
+                    Some(code) => format!("health command exited with code {code}"),
+                    None => "health command was killed".to_string(),
+                });
+            }
+            Ok(None) if started.elapsed() >= timeout => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(format!("health command timed out after {:?}", timeout));
+            }
+            Ok(None) => sleep(Duration::from_millis(50)),
+            Err(err) => return Err(format!("can't wait for {command}: {err}")),
+        }
+    }
+}
+
+impl Egg {
+    /// returns the health of the egg; `Unknown` if it has no health check
+    pub fn health_status(&self) -> HealthStatus {
+        self.state
+            .as_ref()
+            .and_then(|state| state.health.as_ref())
+            .map(|health| health.status)
+            .unwrap_or_default()
+    }
+
+    /// checks if a new health check should be started: the egg has to be running, have a
+    /// health check configured and the check interval has to have elapsed since the last one.
+    pub fn is_health_check_due(&self) -> bool {
+        let (Some(health), Some(state)) = (&self.health, &self.state) else {
+            return false;
+        };
+
+        if state.status != EggStatus::Running {
+            return false;
+        }
+
+        // the first check happens one interval after the egg started
+        let last_check = state.health.as_ref().and_then(|health| health.last_check);
+
+        match last_check.or(state.start_time) {
+            Some(since) => Local::now() - since >= health.interval.to_chrono(),
+            None => true,
+        }
+    }
+
+    /// marks a health check as started now
+    pub fn set_health_check_started(&mut self) {
+        self.validate_state();
+
+        if let Some(ref mut state) = self.state {
+            state.health.get_or_insert_with(Default::default).last_check = Some(Local::now());
+        }
+    }
+
+    /// records the `result` of a health check.
+    ///
+    /// returns `true` if the egg reached its failure threshold and has to be restarted.
+    pub fn record_health_check(&mut self, result: Result<(), String>) -> bool {
+        let Some(config) = self.health.clone() else {
+            return false;
+        };
+
+        // failures during the start period don't count, the egg might still be booting
+        let in_start_period = match (config.start_period, self.state.as_ref()) {
+            (Some(period), Some(state)) => {
+                state.start_time.is_some_and(|start| Local::now() - start < period.to_chrono())
+            }
+            _ => false,
+        };
+
+        self.validate_state();
+        let state = self.state.as_mut().unwrap();
+        let health = state.health.get_or_insert_with(Default::default);
+
+        match result {
+            Ok(()) => {
+                health.status = HealthStatus::Healthy;
+                health.failures = 0;
+                false
+            }
+            Err(_) if in_start_period => false,
+            Err(message) => {
+                health.status = HealthStatus::Unhealthy;
+                health.failures += 1;
+                health.message = Some(message);
+                health.failures >= config.failure_threshold.max(1)
+            }
+        }
+    }
+
+    /// marks the `egg` as errored because it's unhealthy, keeping the `reason` in its error
+    /// and history. Like any other failure, it counts as a try and schedules the next one
+    /// after its backoff: once its processes are stopped, it's retried according to its
+    /// restart policy.
+    pub fn set_as_unhealthy(&mut self, reason: String) {
+        self.record_event(reason.clone());
+        self.set_error(reason.clone());
+        self.set_status(EggStatus::Errored);
+        self.increment_try_count();
+        self.schedule_next_attempt();
+
+        if let Some(ref mut egg_state) = self.state {
+            egg_state.restart_reason = Some(reason);
+        }
+    }
+
+    /// checks if the egg is considered to have started correctly as far as its health
+    /// check is concerned: it has none, or it has reported healthy.
+    pub fn is_healthy_or_unchecked(&self) -> bool {
+        self.health.is_none() || self.health_status() == HealthStatus::Healthy
+    }
+
+    /// resets the health of the egg to `Unknown`, keeping the reason of the last failure
+    pub fn reset_health(&mut self) {
+        if let Some(ref mut state) = self.state
+            && let Some(ref mut health) = state.health
+        {
+            health.status = HealthStatus::Unknown;
+            health.failures = 0;
+            health.last_check = None;
+        }
+    }
+}
@@ -0,0 +1,54 @@
+use {
+    super::{Egg, EggStatus},
+    chrono::prelude::*,
+    serde::{Deserialize, Serialize},
+};
+
+/// number of events kept in the history of an egg; the oldest ones are dropped
+const MAX_HISTORY: usize = 20;
+
+/// something that happened to an egg, worth keeping around (e.g. an automatic restart)
+#[derive(PartialEq, Eq, Clone, Serialize, Deserialize, Debug)]
+pub struct EggEvent {
+    pub time: DateTime<Local>,
+    pub message: String,
+}
+
+impl Egg {
+    /// adds an event to the history of the egg
+    pub fn record_event(&mut self, message: String) {
+        self.validate_state();
+
+        if let Some(ref mut egg_state) = self.state {
+            egg_state.history.push(EggEvent {
+                time: Local::now(),
+                message,
+            });
+
+            let excess = egg_state.history.len().saturating_sub(MAX_HISTORY);
+            egg_state.history.drain(..excess);
+        }
+    }
+
+    /// returns the history of the egg, oldest events first
+    pub fn history(&self) -> &[EggEvent] {
+        self.state.as_ref().map(|state| state.history.as_slice()).unwrap_or_default()
+    }
+
+    /// restarts the egg gracefully because of the given `reason`, which is kept in its error
+    /// once it's running again and recorded in its history
+    pub fn restart_because(&mut self, reason: String) {
+        self.record_event(reason.clone());
+        self.set_error(reason.clone());
+        self.set_status(EggStatus::Restarting);
+
+        if let Some(ref mut egg_state) = self.state {
+            egg_state.restart_reason = Some(reason);
+        }
+    }
+
+    /// takes the reason of the last restart, if it was restarted by kurv itself
+    pub fn take_restart_reason(&mut self) -> Option<String> {
+        self.state.as_mut().and_then(|state| state.restart_reason.take())
+    }
+}
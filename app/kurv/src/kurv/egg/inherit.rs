@@ -0,0 +1,112 @@
+use {
+    super::{Egg, secret::matches_pattern},
+    serde::{
+        Deserialize, Deserializer, Serialize, Serializer,
+        de::{self, SeqAccess, Visitor},
+        ser::SerializeSeq,
+    },
+    std::{env, ffi::OsString, process::Command},
+};
+
+/// which env vars of the kurv server an egg inherits
+#[derive(PartialEq, Eq, Clone, Debug, Default)]
+pub enum InheritEnv {
+    /// the whole environment of the server (default)
+    #[default]
+    All,
+
+    /// nothing: the egg only gets its own `env` and `env_file` vars
+    None,
+
+    /// only the vars with these names (or patterns, like `LC_*`)
+    Only(Vec<String>),
+}
+
+impl InheritEnv {
+    /// checks if the env var with the given name is inherited
+    pub fn inherits(&self, key: &str) -> bool {
+        match self {
+            InheritEnv::All => true,
+            InheritEnv::None => false,
+            InheritEnv::Only(names) => names.iter().any(|name| matches_pattern(name, key)),
+        }
+    }
+}
+
+impl Egg {
+    /// returns which env vars of the kurv server the egg inherits (all of them by default)
+    pub fn inherit_env(&self) -> InheritEnv {
+        self.inherit_env.clone().unwrap_or_default()
+    }
+
+    /// picks, out of `vars` (the environment of the kurv server), the ones the egg inherits
+    pub fn inherited_env(
+        &self,
+        vars: impl IntoIterator<Item = (OsString, OsString)>,
+    ) -> Vec<(OsString, OsString)> {
+        let inherit = self.inherit_env();
+
+        vars.into_iter()
+            .filter(|(key, _)| key.to_str().is_some_and(|key| inherit.inherits(key)))
+            .collect()
+    }
+
+    /// sets up the environment of a command run for the egg so it only inherits the env vars
+    /// of kurv it should; its own vars are set on top afterwards
+    pub fn apply_inherit_env(&self, command: &mut Command) {
+        if self.inherit_env() != InheritEnv::All {
+            command.env_clear().envs(self.inherited_env(env::vars_os()));
+        }
+    }
+}
+
+impl Serialize for InheritEnv {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            InheritEnv::All => serializer.serialize_str("all"),
+            InheritEnv::None => serializer.serialize_str("none"),
+            InheritEnv::Only(names) => {
+                let mut seq = serializer.serialize_seq(Some(names.len()))?;
+                for name in names {
+                    seq.serialize_element(name)?;
+                }
+                seq.end()
+            }
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for InheritEnv {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct InheritEnvVisitor;
+
+        impl<'de> Visitor<'de> for InheritEnvVisitor {
+            type Value = InheritEnv;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                write!(f, "'all', 'none' or a list of env var names")
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+                match v {
+                    "all" => Ok(InheritEnv::All),
+                    "none" => Ok(InheritEnv::None),
+                    other => Err(E::custom(format!(
+                        "unknown inherit_env '{other}', expected 'all', 'none' or a list of names"
+                    ))),
+                }
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                let mut names = vec![];
+                while let Some(name) = seq.next_element::<String>()? {
+                    names.push(name);
+                }
+
+                Ok(InheritEnv::Only(names))
+            }
+        }
+
+        deserializer.deserialize_any(InheritEnvVisitor)
+    }
+}
@@ -0,0 +1,205 @@
+use {
+    super::{Egg, EggPaths, EggStatus, ProcessMetrics, default_pid},
+    chrono::{Duration, prelude::*},
+    serde::{Deserialize, Serialize},
+};
+
+/// state of a single instance (process) of an egg
+#[derive(PartialEq, Clone, Serialize, Deserialize, Default, Debug)]
+pub struct EggInstance {
+    /// index of the instance, from `0` to `instances - 1`
+    pub index: usize,
+
+    /// `Running`, `Stopping`, `Stopped` (finished by itself) or `Errored`
+    pub status: EggStatus,
+
+    #[serde(default = "default_pid")]
+    pub pid: u32,
+
+    pub start_time: Option<DateTime<Local>>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+
+    /// when the instance was asked to stop (only while `Stopping`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stopping_since: Option<DateTime<Local>>,
+
+    /// paths to the stdout and stderr log files of the instance
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub paths: Option<EggPaths>,
+
+    /// resource usage of the process group of the instance (only while `Running`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metrics: Option<ProcessMetrics>,
+}
+
+impl Egg {
+    /// returns the number of instances (processes) the egg should run, 1 by default
+    pub fn instance_count(&self) -> usize {
+        self.instances.map(|count| count.max(1) as usize).unwrap_or(1)
+    }
+
+    /// returns the name used for the log files of the instance with the given `index`;
+    /// the first instance uses the egg's name so single instance eggs keep their log files.
+    pub fn instance_log_name(&self, index: usize) -> String {
+        match index {
+            0 => self.name.clone(),
+            index => format!("{}.{}", self.name, index),
+        }
+    }
+
+    /// returns the state of the instance with the given `index`, if it has been spawned
+    pub fn instance(&self, index: usize) -> Option<&EggInstance> {
+        self.state.as_ref().and_then(|state| state.instances.iter().find(|i| i.index == index))
+    }
+
+    /// returns the state of the instance with the given `index`, creating it if needed
+    fn instance_mut(&mut self, index: usize) -> &mut EggInstance {
+        self.validate_state();
+
+        let instances = &mut self.state.as_mut().unwrap().instances;
+        let position = match instances.iter().position(|i| i.index == index) {
+            Some(position) => position,
+            None => {
+                instances.push(EggInstance {
+                    index,
+                    ..Default::default()
+                });
+                instances.sort_by_key(|i| i.index);
+                instances.iter().position(|i| i.index == index).unwrap()
+            }
+        };
+
+        &mut instances[position]
+    }
+
+    /// returns the indexes of the instances that have to be spawned:
+    /// - instances that were never spawned (e.g. after scaling up).
+    /// - if `include_errored`, instances that exited with an error and should be retried.
+    pub fn missing_instances(&self, include_errored: bool) -> Vec<usize> {
+        (0..self.instance_count())
+            .filter(|&index| match self.instance(index) {
+                None => true,
+                Some(instance) => {
+                    instance.status == EggStatus::Pending
+                        || (include_errored && instance.status == EggStatus::Errored)
+                }
+            })
+            .collect()
+    }
+
+    /// returns the indexes of the instances that have to be spawned on this tick, based on
+    /// the egg's status, its restart policy and its backoff.
+    pub fn instances_to_spawn(&self) -> Vec<usize> {
+        if self.should_spawn() {
+            return self.missing_instances(true);
+        }
+
+        if self.is_running() {
+            // new instances (scale up) are spawned right away, while errored ones
+            // follow the restart policy of the egg
+            let retry = self.can_retry() && self.is_backoff_elapsed();
+            return self.missing_instances(retry);
+        }
+
+        vec![]
+    }
+
+    /// returns the pid of the first instance that is still alive, or 0 if there's none
+    pub fn first_instance_pid(&self) -> u32 {
+        self.state
+            .as_ref()
+            .and_then(|state| state.instances.iter().find(|i| i.pid > 0))
+            .map(|instance| instance.pid)
+            .unwrap_or(0)
+    }
+
+    /// checks if every instance of the egg exited by itself successfully
+    pub fn all_instances_finished(&self) -> bool {
+        (0..self.instance_count()).all(|index| {
+            self.instance(index).is_some_and(|instance| instance.status == EggStatus::Stopped)
+        })
+    }
+
+    /// checks if every instance of the egg has been running for more than `duration`
+    pub fn all_instances_running_for(&self, duration: Duration) -> bool {
+        let now = Local::now();
+
+        (0..self.instance_count()).all(|index| {
+            self.instance(index).is_some_and(|instance| {
+                instance.status == EggStatus::Running
+                    && instance.start_time.is_some_and(|start| now - start > duration)
+            })
+        })
+    }
+
+    /// marks the instance with the given `index` as running with the given `pid`
+    pub fn set_instance_running(&mut self, index: usize, pid: u32, paths: Option<EggPaths>) {
+        let instance = self.instance_mut(index);
+        instance.status = EggStatus::Running;
+        instance.pid = pid;
+        instance.start_time = Some(Local::now());
+        instance.error = None;
+        instance.stopping_since = None;
+        instance.paths = paths;
+    }
+
+    /// marks the instance with the given `index` as exited with an error
+    pub fn set_instance_errored(&mut self, index: usize, error: String) {
+        let instance = self.instance_mut(index);
+        instance.status = EggStatus::Errored;
+        instance.pid = 0;
+        instance.start_time = None;
+        instance.error = Some(error);
+        instance.metrics = None;
+    }
+
+    /// marks the instance with the given `index` as finished (it exited by itself and
+    /// won't be restarted)
+    pub fn set_instance_finished(&mut self, index: usize, reason: String) {
+        let instance = self.instance_mut(index);
+        instance.status = EggStatus::Stopped;
+        instance.pid = 0;
+        instance.start_time = None;
+        instance.error = Some(reason);
+        instance.metrics = None;
+    }
+
+    /// sets the last sampled metrics of the instance with the given `index`, if it exists
+    pub fn set_instance_metrics(&mut self, index: usize, metrics: Option<ProcessMetrics>) {
+        if self.instance(index).is_some() {
+            self.instance_mut(index).metrics = metrics;
+        }
+    }
+
+    /// marks the instance with the given `index` as stopping
+    pub fn set_instance_stopping(&mut self, index: usize) {
+        let instance = self.instance_mut(index);
+        instance.status = EggStatus::Stopping;
+        instance.stopping_since = Some(Local::now());
+    }
+
+    /// checks if the instance with the given `index` has been stopping for longer than
+    /// the egg's kill timeout
+    pub fn has_instance_stop_timed_out(&self, index: usize) -> bool {
+        match self.instance(index).and_then(|instance| instance.stopping_since) {
+            Some(since) => Local::now() - since >= self.kill_timeout().to_chrono(),
+            None => false,
+        }
+    }
+
+    /// forgets about the instance with the given `index` (e.g. after scaling down)
+    pub fn remove_instance(&mut self, index: usize) {
+        if let Some(ref mut egg_state) = self.state {
+            egg_state.instances.retain(|instance| instance.index != index);
+        }
+    }
+
+    /// forgets about all the instances of the egg
+    pub fn clear_instances(&mut self) {
+        if let Some(ref mut egg_state) = self.state {
+            egg_state.instances.clear();
+        }
+    }
+}
@@ -0,0 +1,238 @@
+use {
+    super::Egg,
+    crate::common::{size::ByteSize, tcp::Problem},
+    serde::{
+        Deserialize, Deserializer, Serialize, Serializer,
+        de::{self, Visitor},
+    },
+    std::{fmt::Display, process::Command},
+};
+
+/// resource limits of the process of an egg, applied right before it runs its command
+#[derive(PartialEq, Eq, Clone, Serialize, Deserialize, Default, Debug)]
+pub struct EggLimits {
+    /// max size of the (virtual) memory of the process (`RLIMIT_AS`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memory: Option<ByteSize>,
+
+    /// max number of files the process can have open (`RLIMIT_NOFILE`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub open_files: Option<u64>,
+
+    /// max number of processes (and threads) of the user the egg runs as (`RLIMIT_NPROC`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub processes: Option<u64>,
+
+    /// max size of the core dumps of the process, `0` disables them (`RLIMIT_CORE`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub core_size: Option<ByteSize>,
+
+    /// scheduling priority, from -20 (highest) to 19 (lowest)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nice: Option<i32>,
+
+    /// file mode creation mask, in octal (e.g. `"027"`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub umask: Option<Umask>,
+}
+
+/// a file mode creation mask, written in octal
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct Umask(pub u32);
+
+impl Egg {
+    /// returns the resource limits of the egg (none by default)
+    pub fn limits(&self) -> EggLimits {
+        self.limits.clone().unwrap_or_default()
+    }
+}
+
+impl EggLimits {
+    /// checks if no limit is set
+    pub fn is_empty(&self) -> bool {
+        *self == EggLimits::default()
+    }
+
+    /// checks the values of the limits, without looking at the ones of the system
+    pub fn validate(&self) -> Vec<Problem> {
+        let mut problems = vec![];
+
+        if let Some(nice) = self.nice
+            && !(-20..=19).contains(&nice)
+        {
+            let msg = format!("nice has to be between -20 and 19, not {nice}");
+            problems.push(Problem::error("limits.nice", msg));
+        }
+
+        problems
+    }
+
+    /// returns the limits that are set, as `(field, value)` pairs to be displayed
+    pub fn describe(&self) -> Vec<(&'static str, String)> {
+        let mut limits = vec![];
+
+        if let Some(memory) = self.memory {
+            limits.push(("memory", memory.to_string()));
+        }
+
+        if let Some(open_files) = self.open_files {
+            limits.push(("open files", open_files.to_string()));
+        }
+
+        if let Some(processes) = self.processes {
+            limits.push(("processes", processes.to_string()));
+        }
+
+        if let Some(core_size) = self.core_size {
+            limits.push(("core size", core_size.to_string()));
+        }
+
+        if let Some(nice) = self.nice {
+            limits.push(("nice", nice.to_string()));
+        }
+
+        if let Some(umask) = self.umask {
+            limits.push(("umask", umask.to_string()));
+        }
+
+        limits
+    }
+
+    /// sets up a command so its process applies the limits before running the command.
+    ///
+    /// limits that can't be applied (e.g. raising a hard limit, or a negative nice, when kurv
+    /// doesn't run as root) are reported here, instead of making the spawn fail later on.
+    pub fn apply(&self, command: &mut Command) -> Result<(), String> {
+        if self.is_empty() {
+            return Ok(());
+        }
+
+        apply_limits(self, command)
+    }
+}
+
+#[cfg(unix)]
+fn apply_limits(limits: &EggLimits, command: &mut Command) -> Result<(), String> {
+    use {
+        nix::{
+            errno::Errno,
+            sys::{
+                resource::{Resource, getrlimit, setrlimit},
+                stat::{Mode, umask},
+            },
+            unistd::geteuid,
+        },
+        std::os::unix::process::CommandExt,
+    };
+
+    let is_root = geteuid().is_root();
+
+    let rlimits = [
+        ("memory", Resource::RLIMIT_AS, limits.memory.map(|size| size.0)),
+        ("open_files", Resource::RLIMIT_NOFILE, limits.open_files),
+        ("processes", Resource::RLIMIT_NPROC, limits.processes),
+        ("core_size", Resource::RLIMIT_CORE, limits.core_size.map(|size| size.0)),
+    ];
+
+    let mut to_set = vec![];
+    for (field, resource, value) in rlimits {
+        let Some(value) = value else {
+            continue;
+        };
+
+        let (_, hard) = getrlimit(resource)
+            .map_err(|err| format!("can't read the current limits.{field}: {err}"))?;
+
+        if !is_root && hard != nix::sys::resource::RLIM_INFINITY && value > hard {
+            return Err(format!(
+                "can't set limits.{field} to {value}, it's above the hard limit ({hard}) and kurv \
+                 doesn't run as root"
+            ));
+        }
+
+        to_set.push((resource, value as nix::sys::resource::rlim_t));
+    }
+
+    if let Some(nice) = limits.nice
+        && nice < 0
+        && !is_root
+    {
+        return Err(format!("can't set limits.nice to {nice}, kurv doesn't run as root"));
+    }
+
+    let nice = limits.nice;
+    let mask = limits.umask.map(|umask| Mode::from_bits_truncate(umask.0 as _));
+
+    // SAFETY: the closure runs in the forked child, where allocating isn't safe. The limits
+    // (`to_set`), `nice` and `mask` are computed above, before the fork; the closure only
+    // iterates over them and calls `setrlimit`, `setpriority` and `umask`, which are
+    // async-signal-safe
+    unsafe {
+        command.pre_exec(move || {
+            for (resource, value) in &to_set {
+                setrlimit(*resource, *value, *value)?;
+            }
+
+            if let Some(nice) = nice {
+                Errno::result(libc::setpriority(libc::PRIO_PROCESS as _, 0, nice))?;
+            }
+
+            if let Some(mask) = mask {
+                umask(mask);
+            }
+
+            Ok(())
+        });
+    }
+
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn apply_limits(_limits: &EggLimits, _command: &mut Command) -> Result<(), String> {
+    Err("limits are only supported on unix".to_string())
+}
+
+impl Display for Umask {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:04o}", self.0)
+    }
+}
+
+impl Serialize for Umask {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Umask {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct UmaskVisitor;
+
+        impl Visitor<'_> for UmaskVisitor {
+            type Value = Umask;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                write!(f, "an octal umask like '027'")
+            }
+
+            // a bare `027` is read as the number 27, whose digits are the octal ones
+            fn visit_u64<E: de::Error>(self, v: u64) -> Result<Self::Value, E> {
+                self.visit_str(&v.to_string())
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+                let digits = v.trim().trim_start_matches("0o");
+
+                match u32::from_str_radix(digits, 8) {
+                    Ok(mask) if mask <= 0o777 => Ok(Umask(mask)),
+                    _ => {
+                        Err(E::custom(format!("invalid umask '{v}', expected an octal like '027'")))
+                    }
+                }
+            }
+        }
+
+        deserializer.deserialize_any(UmaskVisitor)
+    }
+}
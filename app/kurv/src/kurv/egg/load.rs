@@ -1,8 +1,10 @@
 use {
-    super::Egg,
+    super::{Egg, check::EnvKeys},
+    crate::common::tcp::Problem,
     anyhow::{Context, Result, anyhow},
     log::debug,
-    std::{fs::File, path::PathBuf},
+    serde_saphyr::{DuplicateKeyPolicy, Options},
+    std::{fs, path::PathBuf},
 };
 
 impl Egg {
@@ -13,11 +15,21 @@ impl Egg {
             return Err(anyhow!(format!("file {} not found", path.display())));
         }
 
-        let rdr = File::open(&path)
+        let yaml = fs::read_to_string(&path)
             .with_context(|| format!("failed to open egg file: {}", path.display()))?;
 
-        let mut egg: Egg = serde_saphyr::from_reader(rdr)
-            .context(format!("failed to parse egg file: {}", path.display()))?;
+        let (mut egg, duplicates) =
+            Egg::parse(&yaml).context(format!("failed to parse egg file: {}", path.display()))?;
+
+        // the egg is sent as json, where only the last value of each env var would be left
+        if let Some(problem) = duplicates.first() {
+            return Err(anyhow!(
+                "invalid egg file {}: {} ({})",
+                path.display(),
+                problem.message,
+                problem.field
+            ));
+        }
 
         // remove id if it has one, so that it doesn't collide with an existing egg
         // the server will assign an ID automatically when spawning.
@@ -25,4 +37,20 @@ impl Egg {
 
         Ok(egg)
     }
+
+    /// parses an egg config from its yaml, along with the problems of the env vars it sets more
+    /// than once (see `EnvKeys`)
+    pub fn parse(yaml: &str) -> Result<(Egg, Vec<Problem>), serde_saphyr::Error> {
+        // duplicate keys are let through so the ones of `env` can be reported one by one; the
+        // fields of the egg are structs, which still reject them
+        let options = || Options {
+            duplicate_keys: DuplicateKeyPolicy::LastWins,
+            ..Default::default()
+        };
+
+        let egg: Egg = serde_saphyr::from_str_with_options(yaml, options())?;
+        let keys: EnvKeys = serde_saphyr::from_str_with_options(yaml, options())?;
+
+        Ok((egg, keys.problems()))
+    }
 }
@@ -0,0 +1,100 @@
+use {
+    super::{Egg, EggStatus},
+    crate::common::{duration::HumanDuration, tcp::Problem},
+    serde::{Deserialize, Serialize},
+    std::iter::Sum,
+};
+
+/// how long the memory of an egg has to stay above `max_memory_restart` by default
+const DEFAULT_MAX_MEMORY_PERIOD: HumanDuration = HumanDuration::from_secs(30);
+
+/// error of the eggs restarted because of `max_memory_restart`
+pub const MEMORY_RESTART_MSG: &str = "restarted: memory limit exceeded";
+
+/// resource usage of the processes of an egg (the process group of each of its instances),
+/// as sampled by kurv every `KURV_METRICS_INTERVAL`
+#[derive(PartialEq, Clone, Copy, Serialize, Deserialize, Default, Debug)]
+pub struct ProcessMetrics {
+    /// resident memory (rss), in bytes
+    pub memory: u64,
+
+    /// cpu usage since the previous sample, in % of one cpu (so it can go over 100)
+    pub cpu: f64,
+
+    /// number of threads
+    pub threads: u64,
+
+    /// number of open file descriptors
+    pub fds: u64,
+
+    /// number of processes
+    pub processes: u64,
+}
+
+impl Sum for ProcessMetrics {
+    fn sum<I: Iterator<Item = ProcessMetrics>>(iter: I) -> ProcessMetrics {
+        iter.fold(ProcessMetrics::default(), |total, metrics| ProcessMetrics {
+            memory: total.memory + metrics.memory,
+            cpu: total.cpu + metrics.cpu,
+            threads: total.threads + metrics.threads,
+            fds: total.fds + metrics.fds,
+            processes: total.processes + metrics.processes,
+        })
+    }
+}
+
+/// returns the cpu usage of `ticks` clock ticks of cpu time over `elapsed` seconds, in % of
+/// one cpu, rounded to one decimal
+pub fn cpu_percent(ticks: u64, ticks_per_second: u64, elapsed: f64) -> f64 {
+    if ticks_per_second == 0 || !elapsed.is_normal() || elapsed < 0.0 {
+        return 0.0;
+    }
+
+    let percent = ticks as f64 / ticks_per_second as f64 / elapsed * 100.0;
+    (percent * 10.0).round() / 10.0
+}
+
+impl Egg {
+    /// returns how long the memory of the egg has to stay above `max_memory_restart` to
+    /// restart it
+    pub fn max_memory_period(&self) -> HumanDuration {
+        self.max_memory_period.unwrap_or(DEFAULT_MAX_MEMORY_PERIOD)
+    }
+
+    /// returns a warning if the egg sets `max_memory_restart` while the resource usage of the
+    /// eggs isn't sampled (with a `metrics_interval` of `None`), as it would never fire
+    pub fn check_memory_sampling(
+        &self,
+        metrics_interval: Option<HumanDuration>,
+    ) -> Option<Problem> {
+        if self.max_memory_restart.is_none() || metrics_interval.is_some() {
+            return None;
+        }
+
+        let msg = "max_memory_restart does nothing while KURV_METRICS_INTERVAL is 0, the memory \
+                   of the eggs isn't sampled"
+            .to_string();
+        Some(Problem::warning("max_memory_restart", msg))
+    }
+
+    /// sets the metrics of the egg to the sum of the ones of its running instances, or clears
+    /// them if none of them is running
+    pub fn update_metrics(&mut self) {
+        let Some(ref mut state) = self.state else {
+            return;
+        };
+
+        let running: Vec<ProcessMetrics> = state
+            .instances
+            .iter()
+            .filter(|instance| instance.status == EggStatus::Running)
+            .filter_map(|instance| instance.metrics)
+            .collect();
+
+        state.metrics = if running.is_empty() {
+            None
+        } else {
+            Some(running.into_iter().sum())
+        };
+    }
+}
@@ -1,19 +1,61 @@
+mod basket;
+mod capture;
+pub mod cgroup;
+pub mod check;
+mod config;
+mod health;
+mod history;
+mod inherit;
+mod instances;
+pub mod limits;
 pub mod load;
+mod metrics;
+mod resolve;
+pub mod restart;
+mod schedule;
+pub mod secret;
+mod stop;
+mod user;
 
 use {
-    chrono::{Duration, prelude::*},
+    crate::common::{
+        cron::CronExpr, duration::HumanDuration, rotation::LogRotation, size::ByteSize,
+    },
+    chrono::prelude::*,
     serde::{Deserialize, Serialize},
     std::{collections::HashMap, path::PathBuf},
 };
 
+pub use {
+    basket::Basket,
+    capture::{LogCapture, LogFormat},
+    cgroup::EggCgroup,
+    check::EggCheck,
+    config::ConfigChange,
+    health::{EggHealth, EggHealthState, HealthCheck, HealthStatus},
+    history::EggEvent,
+    inherit::InheritEnv,
+    instances::EggInstance,
+    limits::EggLimits,
+    metrics::{MEMORY_RESTART_MSG, ProcessMetrics, cpu_percent},
+    resolve::{ResolvedCommand, interpolate},
+    restart::{EggRestart, RestartPolicy},
+    schedule::{CRON_RESTART_MSG, EggRun},
+    stop::StopSignal,
+};
+
 /// defines the status of an egg
-#[derive(PartialEq, Eq, Serialize, Deserialize, Clone, Copy, Debug)]
+#[derive(PartialEq, Eq, Serialize, Deserialize, Clone, Copy, Debug, Default)]
 pub enum EggStatus {
+    #[default]
     Pending,
     Running,
+    Stopping,
     Stopped,
     PendingRemoval,
     Restarting,
+    /// waiting for the next run of its `schedule`
+    Scheduled,
     Errored,
 }
 
@@ -22,7 +64,7 @@ fn default_pid() -> u32 {
 }
 
 /// defines the current state of an egg
-#[derive(PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[derive(PartialEq, Clone, Serialize, Deserialize, Default)]
 pub struct EggState {
     pub status: EggStatus,
     pub start_time: Option<DateTime<Local>>,
@@ -35,9 +77,64 @@ pub struct EggState {
 
     #[serde(default = "default_pid")]
     pub pid: u32,
+
+    /// number of times the egg (or any of its instances) has been restarted, after exiting
+    /// or being asked to; unlike `try_count`, it's never reset
+    #[serde(default)]
+    pub restarts: u32,
+
+    /// exit code of the last instance that exited, if it exited by itself with one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+
+    /// when the next spawn attempt is allowed, while backing off after failures
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_attempt_at: Option<DateTime<Local>>,
+
+    /// status to move to once the egg is done stopping (only while `Stopping`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stop_target: Option<EggStatus>,
+
+    /// when the egg was asked to stop (only while `Stopping`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stopping_since: Option<DateTime<Local>>,
+
+    /// state of each of the instances (processes) of the egg
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub instances: Vec<EggInstance>,
+
+    /// health of the egg, if it has a health check
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub health: Option<EggHealthState>,
+
+    /// resource usage of all the running instances of the egg, see `KURV_METRICS_INTERVAL`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metrics: Option<ProcessMetrics>,
+
+    /// why kurv restarted the egg on its own (e.g. it used too much memory), until it's
+    /// running again
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub restart_reason: Option<String>,
+
+    /// the last things that happened to the egg, oldest first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub history: Vec<EggEvent>,
+
+    /// when a scheduled egg runs next (only while `Scheduled`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_run_at: Option<DateTime<Local>>,
+
+    /// the last run of a scheduled egg
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_run: Option<EggRun>,
+
+    /// when the egg is restarted next because of its `cron_restart` (only while `Running`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_restart_at: Option<DateTime<Local>>,
 }
 
 /// partial EggState used as a temporal struct to update the final EggState
+#[derive(Default)]
 pub struct EggStateUpsert {
     pub status: Option<EggStatus>,
     pub start_time: Option<DateTime<Local>>,
@@ -46,14 +143,18 @@ pub struct EggStateUpsert {
     pub pid: Option<u32>,
 }
 
-#[derive(PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[derive(PartialEq, Eq, Clone, Serialize, Deserialize, Debug)]
 pub struct EggPaths {
     pub stdout: PathBuf,
     pub stderr: PathBuf,
+
+    /// path to the log file with both streams, if enabled (see `LogCapture::combined`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub combined: Option<PathBuf>,
 }
 
 /// 🥚 » an egg represents a process that can be started and stopped by kurv
-#[derive(PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[derive(PartialEq, Clone, Serialize, Deserialize, Default)]
 pub struct Egg {
     pub command: String,
     pub name: String,
@@ -77,6 +178,55 @@ pub struct Egg {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub env: Option<HashMap<String, String>>,
 
+    /// `.env` files to load the environment from, relative to the cwd; `env` goes on top
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub env_file: Option<Vec<PathBuf>>,
+
+    /// which env vars of the kurv server the egg inherits: `all` (default), `none` or a list
+    /// of names (or patterns, like `LC_*`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inherit_env: Option<InheritEnv>,
+
+    /// names (or patterns, like `DB_*`) of env vars whose values are masked by the api, on top
+    /// of the default ones (e.g. `*PASSWORD*`, `*TOKEN*`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub secret_env: Option<Vec<String>>,
+
+    /// user (name or uid) the egg runs as; kurv has to run as root to use it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+
+    /// group (name or gid) the egg runs as; defaults to the primary group of `user`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+
+    /// resource limits of the egg's process: memory, open files, processes, nice, umask...
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limits: Option<EggLimits>,
+
+    /// limits of the cgroup the egg's instances run in: memory.max, cpu.max and pids.max
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cgroup: Option<EggCgroup>,
+
+    /// restarts the egg when the memory (rss) of any of its instances stays above this for
+    /// `max_memory_period`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_memory_restart: Option<ByteSize>,
+
+    /// how long the memory has to stay above `max_memory_restart` to restart the egg (30s by
+    /// default)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_memory_period: Option<HumanDuration>,
+
+    /// restarts the egg gracefully at the times of this cron expression (e.g. `0 3 * * *`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cron_restart: Option<CronExpr>,
+
+    /// runs the egg at the times of this cron expression instead of keeping it running;
+    /// each run lasts until its process exits, whatever its exit code
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub schedule: Option<CronExpr>,
+
     /// paths to the stdout and stderr log files
     #[serde(skip_serializing_if = "Option::is_none")]
     pub paths: Option<EggPaths>,
@@ -88,6 +238,42 @@ pub struct Egg {
     /// path to the plugin executable (only for plugin eggs)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub plugin_path: Option<PathBuf>,
+
+    /// defines if and how the egg is restarted after its process exits
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub restart: Option<EggRestart>,
+
+    /// signal sent to the egg's process group to ask it to stop (defaults to `SIGTERM`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop_signal: Option<StopSignal>,
+
+    /// how long to wait for the egg to stop before killing it (defaults to 5 seconds)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kill_timeout: Option<HumanDuration>,
+
+    /// number of instances (processes) of the command to run (defaults to 1)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instances: Option<u32>,
+
+    /// periodic check used to tell if the egg is healthy, restarting it otherwise
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub health: Option<EggHealth>,
+
+    /// names of the eggs that have to be ready before this one is started
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub depends_on: Option<Vec<String>>,
+
+    /// log rotation settings, overriding the global ones field by field
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub log_rotation: Option<LogRotation>,
+
+    /// how the output is written to the log files: timestamps, stream tags, combined file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub log_capture: Option<LogCapture>,
+
+    /// format of the lines written to the log files: `raw` (default) or `json`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub log_format: Option<LogFormat>,
 }
 
 impl Egg {
@@ -95,14 +281,7 @@ impl Egg {
     /// creates a new one if it doesn't.
     fn validate_state(&mut self) {
         if self.state.is_none() {
-            self.state = Some(EggState {
-                status: EggStatus::Pending,
-                start_time: None,
-                try_count: 0,
-                error: None,
-                pid: 0,
-                uncsynced: false,
-            });
+            self.state = Some(EggState::default());
         }
     }
 
@@ -135,7 +314,7 @@ impl Egg {
                 try_count: state.try_count.unwrap_or(0),
                 error: state.error,
                 pid: state.pid.unwrap_or(0),
-                uncsynced: false,
+                ..Default::default()
             });
         }
     }
@@ -158,6 +337,24 @@ impl Egg {
         }
     }
 
+    /// counts one more restart of the `egg`.
+    pub fn increment_restarts(&mut self) {
+        self.validate_state();
+
+        if let Some(ref mut egg_state) = self.state {
+            egg_state.restarts += 1;
+        }
+    }
+
+    /// sets the exit code of the last instance of the `egg` that exited.
+    pub fn set_exit_code(&mut self, code: Option<i32>) {
+        self.validate_state();
+
+        if let Some(ref mut egg_state) = self.state {
+            egg_state.exit_code = code;
+        }
+    }
+
     /// resets the `try_count` of the `egg` to 0.
     pub fn reset_try_count(&mut self) {
         self.validate_state();
@@ -216,15 +413,40 @@ impl Egg {
         self.set_pid(pid);
         self.reset_start_time();
         self.set_status(EggStatus::Running);
-        self.set_error("".to_string());
+
+        // if kurv restarted it, that's the last thing worth knowing about it
+        let reason = self.take_restart_reason().unwrap_or_default();
+        self.set_error(reason);
+        self.clear_next_attempt();
+        self.clear_next_runs();
+        self.reset_health();
     }
 
     /// marks the `egg` as errored by:
+    /// - setting its `error` and `status` to `EggStatus::Errored`.
+    /// - incrementing its `try_count`.
+    /// - scheduling the next spawn attempt according to its backoff config.
     pub fn set_as_errored(&mut self, error: String) {
         self.set_error(error);
         self.set_status(EggStatus::Errored);
         self.set_pid(0);
         self.increment_try_count();
+        self.schedule_next_attempt();
+    }
+
+    /// clears the scheduled next spawn attempt of the `egg`, if any.
+    pub fn clear_next_attempt(&mut self) {
+        if let Some(ref mut egg_state) = self.state {
+            egg_state.next_attempt_at = None;
+        }
+    }
+
+    /// clears the next scheduled run and cron restart of the `egg`, if any.
+    pub fn clear_next_runs(&mut self) {
+        if let Some(ref mut egg_state) = self.state {
+            egg_state.next_run_at = None;
+            egg_state.next_restart_at = None;
+        }
     }
 
     /// marks the `egg` as stopped by:
@@ -236,6 +458,9 @@ impl Egg {
         self.set_pid(0);
         self.reset_try_count();
         self.set_start_time(None);
+        self.clear_next_runs();
+        self.clear_instances();
+        self.reset_health();
     }
 
     /// resets the `egg` to its initial state
@@ -244,33 +469,43 @@ impl Egg {
         self.set_error("".to_string());
         self.set_pid(0);
         self.set_start_time(None);
+        self.reset_try_count();
+        self.clear_next_attempt();
+        self.clear_instances();
+        self.reset_health();
+    }
+
+    /// marks the `egg` as finished after its process exited and won't be restarted:
+    /// - setting the `status` of the `egg` to `EggStatus::Stopped`.
+    /// - keeping the exit reason in its `error` field.
+    pub fn set_as_finished(&mut self, reason: String) {
+        self.set_as_stopped();
+        self.set_error(reason);
     }
 
     /// checks if the `egg` should be spawned
-    /// (if its state is `Pending` or `Errored`).
+    /// (if its state is `Pending`, or `Errored` and its restart policy allows a retry once
+    /// its backoff delay has elapsed, or `Scheduled` and its next run is due).
     ///
     /// if it doesn't have a state, it should be spawned, as it's probably
-    /// a new egg that has just been added.
+    /// a new egg that has just been added; unless it runs on a schedule, then it has to
+    /// wait for its first run.
     pub fn should_spawn(&self) -> bool {
         if let Some(ref egg_state) = self.state {
-            egg_state.status == EggStatus::Pending || egg_state.status == EggStatus::Errored
+            match egg_state.status {
+                EggStatus::Pending => !self.is_scheduled(),
+                EggStatus::Errored => self.can_retry() && self.is_backoff_elapsed(),
+                EggStatus::Scheduled => self.is_run_due(),
+                _ => false,
+            }
         } else {
-            true
+            !self.is_scheduled()
         }
     }
 
-    pub fn has_been_running_for(&self, duration: Duration) -> bool {
-        if let Some(ref egg_state) = self.state {
-            if let Some(start_time) = egg_state.start_time {
-                let now = Local::now();
-                let diff = now.signed_duration_since(start_time);
-                diff > duration
-            } else {
-                false
-            }
-        } else {
-            false
-        }
+    /// returns the names of the eggs this one depends on
+    pub fn dependencies(&self) -> &[String] {
+        self.depends_on.as_deref().unwrap_or_default()
     }
 
     /// checks if the `egg` is running
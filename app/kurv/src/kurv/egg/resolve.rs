@@ -0,0 +1,159 @@
+use {
+    super::Egg,
+    crate::common::{dotenv, tcp::Problem},
+    std::{
+        collections::HashMap,
+        env, fs,
+        path::{Path, PathBuf},
+    },
+};
+
+/// what is spawned for an egg: its command, args, cwd and env with the `${VAR}` references
+/// replaced, and its env files loaded
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct ResolvedCommand {
+    pub command: String,
+    pub args: Vec<String>,
+    pub cwd: PathBuf,
+    pub env: HashMap<String, String>,
+}
+
+impl Egg {
+    /// resolves the command of the egg with the environment of kurv (see `Egg::resolve_with`)
+    pub fn resolve(&self, default_cwd: &Path) -> Result<ResolvedCommand, Problem> {
+        self.resolve_with(default_cwd, |name| env::var(name).ok())
+    }
+
+    /// resolves the command of the egg, looking up the variables it references with `lookup`.
+    ///
+    /// the env files are read relative to the (resolved) cwd, in order, and the `env` of the egg
+    /// goes on top of them. Returns the first problem found, for the field it was found in.
+    pub fn resolve_with(
+        &self,
+        default_cwd: &Path,
+        lookup: impl Fn(&str) -> Option<String>,
+    ) -> Result<ResolvedCommand, Problem> {
+        let resolve = |field: &str, value: &str| {
+            interpolate(value, &lookup).map_err(|msg| Problem::error(field, msg))
+        };
+
+        let cwd = match &self.cwd {
+            Some(cwd) => PathBuf::from(resolve("cwd", &cwd.to_string_lossy())?),
+            None => default_cwd.to_path_buf(),
+        };
+
+        let command = resolve("command", &self.command)?;
+
+        let mut args = vec![];
+        for (index, arg) in self.args.iter().flatten().enumerate() {
+            args.push(resolve(&format!("args.{index}"), arg)?);
+        }
+
+        let mut env = HashMap::new();
+        for (index, file) in self.env_file.iter().flatten().enumerate() {
+            let field = format!("env_file.{index}");
+            let path = cwd.join(file);
+
+            let content = fs::read_to_string(&path).map_err(|err| {
+                Problem::error(&field, format!("can't read {}: {}", path.display(), err))
+            })?;
+
+            let vars = dotenv::parse(&content)
+                .map_err(|err| Problem::error(&field, format!("{}: {}", path.display(), err)))?;
+
+            env.extend(vars);
+        }
+
+        for (key, value) in self.env.iter().flatten() {
+            env.insert(key.clone(), resolve(&format!("env.{key}"), value)?);
+        }
+
+        Ok(ResolvedCommand {
+            command,
+            args,
+            cwd,
+            env,
+        })
+    }
+
+    /// returns the fields where `${VAR}` references are resolved, with their values
+    pub(super) fn interpolated_fields(&self) -> Vec<(String, String)> {
+        let mut fields = vec![("command".to_string(), self.command.clone())];
+
+        if let Some(cwd) = &self.cwd {
+            fields.push(("cwd".to_string(), cwd.to_string_lossy().to_string()));
+        }
+
+        for (index, arg) in self.args.iter().flatten().enumerate() {
+            fields.push((format!("args.{index}"), arg.clone()));
+        }
+
+        let mut env: Vec<(&String, &String)> = self.env.iter().flatten().collect();
+        env.sort();
+        fields.extend(env.into_iter().map(|(key, value)| (format!("env.{key}"), value.clone())));
+
+        fields
+    }
+}
+
+/// replaces the `${VAR}` references in `value` with the value `lookup` returns for `VAR`.
+///
+/// `${VAR:-default}` uses the default if `VAR` isn't set or is empty, and `${VAR-default}` only
+/// if it isn't set; `$${` is a literal `${`. A `VAR` that isn't set and has no default is an
+/// error.
+pub fn interpolate(value: &str, lookup: impl Fn(&str) -> Option<String>) -> Result<String, String> {
+    let mut result = String::with_capacity(value.len());
+    let mut rest = value;
+
+    while let Some(start) = rest.find("${") {
+        let (before, reference) = rest.split_at(start);
+        let reference = &reference[2..];
+
+        // `$${` is escaped
+        if let Some(before) = before.strip_suffix('$') {
+            result.push_str(before);
+            result.push_str("${");
+            rest = reference;
+            continue;
+        }
+
+        result.push_str(before);
+
+        let Some(end) = reference.find('}') else {
+            return Err(format!("unclosed ${{ in {value}"));
+        };
+
+        let (expression, after) = (&reference[..end], &reference[end + 1..]);
+        let name_end = expression
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .unwrap_or(expression.len());
+        let (name, modifier) = expression.split_at(name_end);
+
+        if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+            return Err(format!("invalid variable name in ${{{expression}}}"));
+        }
+
+        let (default, or_empty) = if modifier.is_empty() {
+            (None, false)
+        } else if let Some(default) = modifier.strip_prefix(":-") {
+            (Some(default), true)
+        } else if let Some(default) = modifier.strip_prefix('-') {
+            (Some(default), false)
+        } else {
+            return Err(format!("invalid variable reference ${{{expression}}}"));
+        };
+
+        let resolved = match (lookup(name), default) {
+            (Some(value), Some(default)) if or_empty && value.is_empty() => default.to_string(),
+            (Some(value), _) => value,
+            (None, Some(default)) => default.to_string(),
+            (None, None) => return Err(format!("environment variable {name} is not set")),
+        };
+
+        result.push_str(&resolved);
+        rest = after;
+    }
+
+    result.push_str(rest);
+    Ok(result)
+}
@@ -0,0 +1,207 @@
+use {
+    super::Egg,
+    crate::common::{duration::HumanDuration, tcp::Problem},
+    chrono::{Duration, Local},
+    serde::{Deserialize, Serialize},
+    std::hash::{BuildHasher, RandomState},
+};
+
+/// defines when an egg should be restarted after its process exits
+#[derive(PartialEq, Eq, Serialize, Deserialize, Clone, Copy, Debug, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum RestartPolicy {
+    /// restart the egg whatever its exit code
+    #[default]
+    Always,
+    /// restart the egg only if it exited with a code that is not considered a success
+    OnFailure,
+    /// never restart the egg, once it exits it stays that way
+    Never,
+}
+
+/// restart configuration of an egg
+#[derive(PartialEq, Clone, Serialize, Deserialize, Default, Debug)]
+pub struct EggRestart {
+    /// when the egg should be restarted, defaults to `always`
+    #[serde(default)]
+    pub policy: RestartPolicy,
+
+    /// max number of consecutive restarts before giving up; unlimited if not set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_retries: Option<u32>,
+
+    /// exit codes considered a success, defaults to `[0]`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub success_codes: Option<Vec<i32>>,
+
+    /// delay schedule between consecutive restart attempts; the default one (1s, doubling up
+    /// to 1m) if not set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub backoff: Option<EggBackoff>,
+}
+
+/// exponential backoff schedule between restart attempts
+#[derive(PartialEq, Clone, Serialize, Deserialize, Debug)]
+pub struct EggBackoff {
+    /// delay before the first restart attempt
+    #[serde(default = "default_initial_delay")]
+    pub initial_delay: HumanDuration,
+
+    /// factor by which the delay grows after each consecutive failure, at least 1
+    #[serde(default = "default_multiplier")]
+    pub multiplier: f64,
+
+    /// upper bound for the delay between attempts
+    #[serde(default = "default_max_delay")]
+    pub max_delay: HumanDuration,
+
+    /// percentage (0-100) by which each delay is randomly shortened or lengthened, so that
+    /// eggs failing at the same time don't get restarted in lockstep
+    #[serde(default)]
+    pub jitter: u32,
+}
+
+fn default_initial_delay() -> HumanDuration {
+    HumanDuration::from_secs(1)
+}
+
+fn default_multiplier() -> f64 {
+    2.0
+}
+
+fn default_max_delay() -> HumanDuration {
+    HumanDuration::from_secs(60)
+}
+
+impl Default for EggBackoff {
+    fn default() -> Self {
+        EggBackoff {
+            initial_delay: default_initial_delay(),
+            multiplier: default_multiplier(),
+            max_delay: default_max_delay(),
+            jitter: 0,
+        }
+    }
+}
+
+impl EggBackoff {
+    /// returns the delay to wait before the next attempt after `failures` consecutive
+    /// failures, without jitter.
+    pub fn base_delay(&self, failures: u32) -> Duration {
+        let initial = self.initial_delay.to_chrono();
+        let max = self.max_delay.to_chrono();
+
+        // way past any sensible max delay already, the delay just has to be capped
+        let exponent = failures.saturating_sub(1).min(1024) as i32;
+        let millis = initial.num_milliseconds() as f64 * self.multiplier.max(1.0).powi(exponent);
+
+        if !millis.is_finite() || millis >= max.num_milliseconds() as f64 {
+            return max;
+        }
+
+        Duration::milliseconds(millis as i64)
+    }
+
+    /// returns the delay to wait before the next attempt after `failures` consecutive
+    /// failures, with jitter applied.
+    pub fn delay(&self, failures: u32) -> Duration {
+        let base = self.base_delay(failures);
+        let jitter = self.jitter.min(100) as i64;
+
+        if jitter == 0 {
+            return base;
+        }
+
+        // random factor in the [-jitter, +jitter] percent range
+        let random = RandomState::new().hash_one(Local::now()) % (2 * jitter as u64 + 1);
+        let percent = random as i64 - jitter;
+
+        base + Duration::milliseconds(base.num_milliseconds() * percent / 100)
+    }
+}
+
+impl EggRestart {
+    /// returns the backoff schedule between restart attempts, or the default one if it has
+    /// none, so a crashing egg isn't restarted over and over without a pause.
+    pub fn backoff(&self) -> EggBackoff {
+        self.backoff.clone().unwrap_or_default()
+    }
+
+    /// checks the values of the restart config
+    pub fn validate(&self) -> Vec<Problem> {
+        let mut problems = vec![];
+
+        if let Some(backoff) = &self.backoff
+            && (!backoff.multiplier.is_finite() || backoff.multiplier < 1.0)
+        {
+            let msg = format!("multiplier has to be at least 1, not {}", backoff.multiplier);
+            problems.push(Problem::error("restart.backoff.multiplier", msg));
+        }
+
+        problems
+    }
+
+    /// checks if the given exit `code` is considered a successful exit.
+    ///
+    /// a process killed by a signal has no exit code and is never a success.
+    pub fn is_success(&self, code: Option<i32>) -> bool {
+        match (code, &self.success_codes) {
+            (Some(code), Some(codes)) => codes.contains(&code),
+            (Some(code), None) => code == 0,
+            (None, _) => false,
+        }
+    }
+}
+
+impl Egg {
+    /// returns the restart configuration of the egg, or the default one
+    /// (always restart, no retries limit) if it has none.
+    pub fn restart_config(&self) -> EggRestart {
+        self.restart.clone().unwrap_or_default()
+    }
+
+    /// checks if the egg process should be restarted after exiting with the given `code`,
+    /// without taking the retries limit into account.
+    pub fn should_restart_on_exit(&self, code: Option<i32>) -> bool {
+        let restart = self.restart_config();
+
+        match restart.policy {
+            RestartPolicy::Always => true,
+            RestartPolicy::OnFailure => !restart.is_success(code),
+            RestartPolicy::Never => false,
+        }
+    }
+
+    /// checks if an errored egg is allowed to be re-spawned, based on its restart policy
+    /// and the number of consecutive failed tries so far.
+    pub fn can_retry(&self) -> bool {
+        let restart = self.restart_config();
+
+        if restart.policy == RestartPolicy::Never {
+            return false;
+        }
+
+        match (restart.max_retries, &self.state) {
+            (Some(max_retries), Some(state)) => state.try_count <= max_retries,
+            _ => true,
+        }
+    }
+
+    /// checks if the backoff delay before the next spawn attempt (if any) has elapsed.
+    pub fn is_backoff_elapsed(&self) -> bool {
+        match self.state.as_ref().and_then(|state| state.next_attempt_at) {
+            Some(next_attempt_at) => Local::now() >= next_attempt_at,
+            None => true,
+        }
+    }
+
+    /// schedules the next spawn attempt of the egg according to its backoff config (or the
+    /// default one) and its number of consecutive failures.
+    pub fn schedule_next_attempt(&mut self) {
+        let backoff = self.restart_config().backoff();
+
+        if let Some(ref mut state) = self.state {
+            state.next_attempt_at = Some(Local::now() + backoff.delay(state.try_count));
+        }
+    }
+}
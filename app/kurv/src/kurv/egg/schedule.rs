@@ -0,0 +1,89 @@
+use {
+    super::{Egg, EggStatus},
+    crate::common::duration::HumanDuration,
+    chrono::prelude::*,
+    serde::{Deserialize, Serialize},
+};
+
+/// error of the eggs restarted because of `cron_restart`
+pub const CRON_RESTART_MSG: &str = "restarted: cron_restart";
+
+/// the last run of a scheduled egg
+#[derive(PartialEq, Eq, Clone, Serialize, Deserialize, Debug)]
+pub struct EggRun {
+    pub started_at: DateTime<Local>,
+    pub duration: HumanDuration,
+
+    /// exit code of its (last) process, if it exited with one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+}
+
+impl Egg {
+    /// checks if the egg runs on a `schedule` instead of being kept running
+    pub fn is_scheduled(&self) -> bool {
+        self.schedule.is_some()
+    }
+
+    /// marks the `egg` as waiting for its next scheduled run, which is computed from now.
+    pub fn set_as_scheduled(&mut self) {
+        self.set_as_stopped();
+        self.set_status(EggStatus::Scheduled);
+
+        let next_run_at = self.schedule.as_ref().and_then(|cron| cron.next_after(Local::now()));
+
+        if let Some(ref mut egg_state) = self.state {
+            egg_state.next_run_at = next_run_at;
+        }
+    }
+
+    /// checks if it's time for the next run of a scheduled egg
+    pub fn is_run_due(&self) -> bool {
+        self.state
+            .as_ref()
+            .and_then(|state| state.next_run_at)
+            .is_some_and(|next_run_at| Local::now() >= next_run_at)
+    }
+
+    /// marks the run of a scheduled `egg` as done, whatever its outcome, keeping its start
+    /// time, duration and exit code, and waits for the next one. The exit `reason` is kept
+    /// in its error.
+    pub fn finish_run(&mut self, reason: String) {
+        if let Some(ref mut egg_state) = self.state
+            && let Some(started_at) = egg_state.start_time
+        {
+            let duration = (Local::now() - started_at).to_std().unwrap_or_default();
+
+            egg_state.last_run = Some(EggRun {
+                started_at,
+                duration: HumanDuration(duration),
+                exit_code: egg_state.exit_code,
+            });
+        }
+
+        self.set_as_scheduled();
+        self.set_error(reason);
+    }
+
+    /// checks if it's time to restart the egg because of its `cron_restart`; the first time
+    /// it's asked about a running egg, it just schedules the next restart.
+    pub fn cron_restart_due(&mut self) -> bool {
+        let Some(ref cron) = self.cron_restart else {
+            return false;
+        };
+
+        let now = Local::now();
+
+        let Some(ref mut egg_state) = self.state else {
+            return false;
+        };
+
+        match egg_state.next_restart_at {
+            Some(next_restart_at) => now >= next_restart_at,
+            None => {
+                egg_state.next_restart_at = cron.next_after(now);
+                false
+            }
+        }
+    }
+}
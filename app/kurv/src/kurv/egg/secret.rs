@@ -0,0 +1,88 @@
+use super::{ConfigChange, Egg};
+
+/// what secret values are replaced with
+pub const SECRET_MASK: &str = "****";
+
+/// env vars whose name matches any of these patterns are always secret
+const SECRET_PATTERNS: [&str; 8] = [
+    "*PASSWORD*",
+    "*PASSWD*",
+    "*SECRET*",
+    "*TOKEN*",
+    "*API_KEY*",
+    "*ACCESS_KEY*",
+    "*PRIVATE_KEY*",
+    "*CREDENTIALS*",
+];
+
+impl Egg {
+    /// checks if the env var with the given name is secret: if it matches one of the names or
+    /// patterns (where `*` matches anything) in `secret_env`, or one of the default patterns
+    /// (like `*PASSWORD*` or `*TOKEN*`). Names are compared ignoring case.
+    pub fn is_secret_env(&self, key: &str) -> bool {
+        let custom = self.secret_env.iter().flatten().map(String::as_str);
+
+        SECRET_PATTERNS.into_iter().chain(custom).any(|pattern| matches_pattern(pattern, key))
+    }
+
+    /// returns a copy of the egg with the values of its secret env vars masked
+    pub fn masked(&self) -> Egg {
+        let mut egg = self.clone();
+
+        if let Some(env) = egg.env.as_mut() {
+            for (key, value) in env.iter_mut() {
+                if self.is_secret_env(key) {
+                    *value = SECRET_MASK.to_string();
+                }
+            }
+        }
+
+        egg
+    }
+
+    /// like `Egg::config_diff`, but with the values of secret env vars masked
+    pub fn masked_config_diff(&self, other: &Egg) -> Vec<ConfigChange> {
+        let mut changes = self.config_diff(other);
+
+        for change in changes.iter_mut() {
+            let Some(key) = change.field.strip_prefix("env.") else {
+                continue;
+            };
+
+            if self.is_secret_env(key) || other.is_secret_env(key) {
+                change.old = change.old.as_ref().map(|_| SECRET_MASK.to_string());
+                change.new = change.new.as_ref().map(|_| SECRET_MASK.to_string());
+            }
+        }
+
+        changes
+    }
+}
+
+/// matches `text` against a pattern where `*` matches any number of characters, ignoring case
+pub(super) fn matches_pattern(pattern: &str, text: &str) -> bool {
+    let pattern = pattern.to_ascii_uppercase();
+    let text = text.to_ascii_uppercase();
+
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+
+    let Some(mut rest) = text.strip_prefix(first) else {
+        return false;
+    };
+
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        // no `*` at all
+        return rest.is_empty();
+    };
+
+    for part in middle {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+
+    rest.len() >= last.len() && rest.ends_with(last)
+}
@@ -0,0 +1,158 @@
+use {
+    super::{Egg, EggStatus},
+    crate::common::duration::HumanDuration,
+    chrono::Local,
+    serde::{Deserialize, Serialize},
+    std::fmt::Display,
+};
+
+/// default time to wait for an egg to stop gracefully before killing it
+const DEFAULT_KILL_TIMEOUT_SECS: u64 = 5;
+
+/// signal sent to an egg's process group to ask it to stop
+#[derive(PartialEq, Eq, Serialize, Deserialize, Clone, Copy, Debug, Default)]
+pub enum StopSignal {
+    #[default]
+    #[serde(rename = "SIGTERM", alias = "TERM")]
+    Term,
+    #[serde(rename = "SIGINT", alias = "INT")]
+    Int,
+    #[serde(rename = "SIGQUIT", alias = "QUIT")]
+    Quit,
+    #[serde(rename = "SIGHUP", alias = "HUP")]
+    Hup,
+    #[serde(rename = "SIGKILL", alias = "KILL")]
+    Kill,
+    #[serde(rename = "SIGUSR1", alias = "USR1")]
+    Usr1,
+    #[serde(rename = "SIGUSR2", alias = "USR2")]
+    Usr2,
+}
+
+impl Display for StopSignal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            StopSignal::Term => "SIGTERM",
+            StopSignal::Int => "SIGINT",
+            StopSignal::Quit => "SIGQUIT",
+            StopSignal::Hup => "SIGHUP",
+            StopSignal::Kill => "SIGKILL",
+            StopSignal::Usr1 => "SIGUSR1",
+            StopSignal::Usr2 => "SIGUSR2",
+        };
+
+        write!(f, "{name}")
+    }
+}
+
+#[cfg(unix)]
+impl From<StopSignal> for command_group::Signal {
+    fn from(signal: StopSignal) -> Self {
+        use command_group::Signal;
+
+        match signal {
+            StopSignal::Term => Signal::SIGTERM,
+            StopSignal::Int => Signal::SIGINT,
+            StopSignal::Quit => Signal::SIGQUIT,
+            StopSignal::Hup => Signal::SIGHUP,
+            StopSignal::Kill => Signal::SIGKILL,
+            StopSignal::Usr1 => Signal::SIGUSR1,
+            StopSignal::Usr2 => Signal::SIGUSR2,
+        }
+    }
+}
+
+impl Egg {
+    /// returns the signal used to ask the egg to stop (`SIGTERM` by default)
+    pub fn stop_signal(&self) -> StopSignal {
+        self.stop_signal.unwrap_or_default()
+    }
+
+    /// returns how long to wait for the egg to stop before killing it (5 seconds by default)
+    pub fn kill_timeout(&self) -> HumanDuration {
+        self.kill_timeout.unwrap_or(HumanDuration::from_secs(DEFAULT_KILL_TIMEOUT_SECS))
+    }
+
+    /// checks if the `egg` is stopping
+    /// (if its state is `Stopping`).
+    pub fn is_stopping(&self) -> bool {
+        self.is_in_status(EggStatus::Stopping)
+    }
+
+    /// marks the `egg` as stopping: its process has been asked to stop and kurv is waiting
+    /// for it to exit. The current status is kept as the one to move to once it's done.
+    pub fn set_as_stopping(&mut self) {
+        self.validate_state();
+
+        if let Some(ref mut egg_state) = self.state {
+            egg_state.stop_target = Some(egg_state.status);
+            egg_state.stopping_since = Some(Local::now());
+            egg_state.status = EggStatus::Stopping;
+
+            for instance in egg_state.instances.iter_mut() {
+                if instance.status == EggStatus::Running {
+                    instance.status = EggStatus::Stopping;
+                    instance.stopping_since = egg_state.stopping_since;
+                }
+            }
+        }
+    }
+
+    /// changes the status the `egg` will move to once it's done stopping.
+    pub fn set_stop_target(&mut self, status: EggStatus) {
+        if let Some(ref mut egg_state) = self.state {
+            egg_state.stop_target = Some(status);
+        }
+    }
+
+    /// checks if the `egg` has been stopping for longer than its kill timeout
+    pub fn has_stop_timed_out(&self) -> bool {
+        match self.state.as_ref().and_then(|state| state.stopping_since) {
+            Some(since) => Local::now() - since >= self.kill_timeout().to_chrono(),
+            None => false,
+        }
+    }
+
+    /// completes the stop of the `egg`, moving it to the status it was stopped for:
+    /// - `Restarting` eggs are reset to `Pending`, so they are started again.
+    /// - `PendingRemoval` eggs keep that status, so they can be removed.
+    /// - `Errored` eggs keep that status and their try count, so they are retried after
+    ///   their backoff, as long as their restart policy allows it.
+    /// - any other egg is marked as `Stopped`.
+    pub fn finish_stopping(&mut self) {
+        let target = match self.state {
+            Some(ref mut egg_state) => {
+                let target = match egg_state.status {
+                    EggStatus::Stopping => egg_state.stop_target.unwrap_or(EggStatus::Stopped),
+                    status => status,
+                };
+
+                egg_state.stop_target = None;
+                egg_state.stopping_since = None;
+                egg_state.status = target;
+                target
+            }
+            None => EggStatus::Stopped,
+        };
+
+        match target {
+            EggStatus::Restarting => {
+                self.increment_restarts();
+                self.reset_state();
+            }
+            EggStatus::Errored => {
+                let error = self.state.as_ref().and_then(|state| state.error.clone());
+
+                // its instances are restarted (and counted as such) when it's retried
+                for index in 0..self.instance_count() {
+                    self.set_instance_errored(index, error.clone().unwrap_or_default());
+                }
+
+                self.set_pid(0);
+                self.set_start_time(None);
+                self.reset_health();
+            }
+            _ => self.set_as_stopped(),
+        }
+    }
+}
@@ -0,0 +1,161 @@
+use {super::Egg, crate::common::tcp::Problem, std::process::Command};
+
+/// the user and groups an egg runs as
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct RunAs {
+    pub uid: u32,
+    pub gid: u32,
+
+    /// supplementary groups of the user
+    pub groups: Vec<u32>,
+
+    /// name and home of the user, if it's known
+    pub name: Option<String>,
+    pub home: Option<String>,
+}
+
+impl Egg {
+    /// resolves the `user` and `group` of the egg (names or numeric ids) to the ids its
+    /// process runs as; `None` if it runs as kurv itself. Fails if the user or group doesn't
+    /// exist, or if kurv can't switch to them.
+    pub fn run_as(&self) -> Result<Option<RunAs>, Problem> {
+        if self.user.is_none() && self.group.is_none() {
+            return Ok(None);
+        }
+
+        resolve_run_as(self.user.as_deref(), self.group.as_deref()).map(Some)
+    }
+}
+
+impl RunAs {
+    /// sets up a command so its process runs as this user and groups, with the `HOME`,
+    /// `USER` and `LOGNAME` of the user (set the env of the egg after this, to override them)
+    pub fn apply(&self, command: &mut Command) {
+        if let Some(name) = &self.name {
+            command.env("USER", name).env("LOGNAME", name);
+        }
+
+        if let Some(home) = &self.home {
+            command.env("HOME", home);
+        }
+
+        drop_privileges(command, self);
+    }
+}
+
+#[cfg(unix)]
+fn resolve_run_as(user: Option<&str>, group: Option<&str>) -> Result<RunAs, Problem> {
+    use nix::unistd::{Gid, Group, Uid, User, getegid, geteuid};
+
+    let user = match user {
+        Some(user) => {
+            let found = match user.parse::<u32>() {
+                Ok(uid) => User::from_uid(Uid::from_raw(uid)),
+                Err(_) => User::from_name(user),
+            };
+
+            match found.map_err(|err| {
+                Problem::error("user", format!("can't look up user {user}: {err}"))
+            })? {
+                Some(found) => Some(found),
+                None => return Err(Problem::error("user", format!("user {user} does not exist"))),
+            }
+        }
+        None => None,
+    };
+
+    let gid = match group {
+        Some(group) => {
+            let found = match group.parse::<u32>() {
+                Ok(gid) => Group::from_gid(Gid::from_raw(gid)),
+                Err(_) => Group::from_name(group),
+            };
+
+            match found.map_err(|err| {
+                Problem::error("group", format!("can't look up group {group}: {err}"))
+            })? {
+                Some(found) => found.gid,
+                None => {
+                    return Err(Problem::error("group", format!("group {group} does not exist")));
+                }
+            }
+        }
+        None => user.as_ref().map(|user| user.gid).unwrap_or_else(getegid),
+    };
+
+    let uid = user.as_ref().map(|user| user.uid).unwrap_or_else(geteuid);
+
+    if !geteuid().is_root() && (uid != geteuid() || gid != getegid()) {
+        let msg = "kurv has to run as root to run eggs as another user or group".to_string();
+        let field = if user.is_some() { "user" } else { "group" };
+        return Err(Problem::error(field, msg));
+    }
+
+    Ok(RunAs {
+        uid: uid.as_raw(),
+        gid: gid.as_raw(),
+        groups: supplementary_groups(user.as_ref(), gid),
+        name: user.as_ref().map(|user| user.name.clone()),
+        home: user.as_ref().map(|user| user.dir.to_string_lossy().to_string()),
+    })
+}
+
+#[cfg(not(unix))]
+fn resolve_run_as(user: Option<&str>, _group: Option<&str>) -> Result<RunAs, Problem> {
+    let msg = "user and group are only supported on unix".to_string();
+    let field = if user.is_some() { "user" } else { "group" };
+    Err(Problem::error(field, msg))
+}
+
+/// returns the groups the user belongs to, or just `gid` if there is no user
+#[cfg(target_os = "linux")]
+fn supplementary_groups(user: Option<&nix::unistd::User>, gid: nix::unistd::Gid) -> Vec<u32> {
+    use {nix::unistd::getgrouplist, std::ffi::CString};
+
+    let groups = user
+        .and_then(|user| CString::new(user.name.as_str()).ok())
+        .and_then(|name| getgrouplist(&name, gid).ok())
+        .unwrap_or_else(|| vec![gid]);
+
+    groups.into_iter().map(|group| group.as_raw()).collect()
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+fn supplementary_groups(_user: Option<&nix::unistd::User>, gid: nix::unistd::Gid) -> Vec<u32> {
+    vec![gid.as_raw()]
+}
+
+/// makes the process of the command switch to the user and groups before running the command
+#[cfg(unix)]
+fn drop_privileges(command: &mut Command, run_as: &RunAs) {
+    use {
+        nix::unistd::{Gid, Uid, geteuid, setgid, setuid},
+        std::os::unix::process::CommandExt,
+    };
+
+    // without root, the egg can only run as kurv itself (see `resolve_run_as`)
+    if !geteuid().is_root() {
+        return;
+    }
+
+    let groups: Vec<Gid> = run_as.groups.iter().map(|gid| Gid::from_raw(*gid)).collect();
+    let (uid, gid) = (Uid::from_raw(run_as.uid), Gid::from_raw(run_as.gid));
+
+    // SAFETY: the closure runs in the forked child, where allocating isn't safe. `groups`,
+    // `uid` and `gid` are built above, before the fork, and only borrowed by `setgroups`,
+    // `setgid` and `setuid`, which are plain async-signal-safe syscalls
+    unsafe {
+        command.pre_exec(move || {
+            #[cfg(target_os = "linux")]
+            nix::unistd::setgroups(&groups)?;
+
+            setgid(gid)?;
+            setuid(uid)?;
+
+            Ok(())
+        });
+    }
+}
+
+#[cfg(not(unix))]
+fn drop_privileges(_command: &mut Command, _run_as: &RunAs) {}
@@ -0,0 +1,81 @@
+use {
+    super::{Kurv, spawn::log_restart_outcome},
+    log::{debug, warn},
+    std::thread,
+};
+
+impl Kurv {
+    /// runs the health checks of the running eggs that have one configured.
+    ///
+    /// checks run on their own threads so a slow or hanging egg doesn't block the main
+    /// loop; their results are collected on the next ticks. Once an egg reaches its
+    /// failure threshold, it's restarted.
+    pub(crate) fn check_health(&mut self) -> bool {
+        let state = self.state.clone();
+        let mut state = state.lock().unwrap();
+        let mut unsynced: bool = false;
+
+        for (_, egg) in state.eggs.iter_mut() {
+            // collect the result of the check in progress, if it's done
+            if let Some(check) = self.health_checks.get(&egg.name) {
+                if !check.is_finished() {
+                    continue;
+                }
+
+                let check = self.health_checks.remove(&egg.name).unwrap();
+                let result = check.join().unwrap_or_else(|_| Err("health check panicked".into()));
+
+                // the egg might have been stopped or restarted while it was being checked
+                if !egg.is_running() {
+                    continue;
+                }
+
+                let previous = egg.health_status();
+                let restart = egg.record_health_check(result);
+
+                if egg.health_status() != previous {
+                    debug!("egg <green>{}</green> is {}", egg.name, egg.health_status());
+                    unsynced = true;
+                }
+
+                if restart {
+                    let message = egg
+                        .state
+                        .as_ref()
+                        .and_then(|state| state.health.as_ref())
+                        .and_then(|health| health.message.clone())
+                        .unwrap_or_default();
+
+                    warn!("egg <green>{}</green> is unhealthy, stopping it: {}", egg.name, message);
+
+                    // it's stopped and then retried as any other failed egg, so its
+                    // restart policy, max retries and backoff apply
+                    let failure = format!("unhealthy: {message}");
+                    egg.set_as_unhealthy(failure.clone());
+                    log_restart_outcome(egg, failure);
+                    unsynced = true;
+                }
+
+                continue;
+            }
+
+            if !egg.is_health_check_due() {
+                continue;
+            }
+
+            let Some(health) = egg.health.clone() else {
+                continue;
+            };
+
+            egg.set_health_check_started();
+
+            let target = egg.clone();
+            let working_dir = self.info.lock().unwrap().paths.working_dir.clone();
+            let handle =
+                thread::spawn(move || health.check.run(&target, &working_dir, health.timeout.0));
+            self.health_checks.insert(egg.name.clone(), handle);
+        }
+
+        unsynced
+    }
+}
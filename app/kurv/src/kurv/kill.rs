@@ -1,25 +1,45 @@
 use {
-    super::Kurv,
+    super::{
+        Kurv,
+        cgroup::InstanceCgroup,
+        egg::{EggStatus, StopSignal},
+    },
+    command_group::GroupChild,
     log::{debug, error, warn},
+    std::collections::HashMap,
 };
 
 impl Kurv {
     /// checks each egg looking for those that are still running but that were
-    /// marked as stopped from the api. In case it finds such a case, then it
-    /// kills the background process of the egg that's supposed to be stopped.
-    pub(crate) fn check_stopped_eggs(&mut self) -> bool {
+    /// marked as stopped from the api. In case it finds such a case, it asks the
+    /// egg's process group to stop by sending its stop signal and marks it as
+    /// `Stopping`. If it doesn't stop within its kill timeout, it gets killed.
+    pub fn check_stopped_eggs(&mut self) -> bool {
         let state = self.state.clone();
         let mut state = state.lock().unwrap();
         let mut unsynced: bool = false;
+        let cgroup_root = self.info.lock().unwrap().paths.cgroup.clone();
+
+        // eggs are stopped in reverse dependency order: an egg waits for the eggs that
+        // depend on it and are being stopped too
+        let stopping = state.stopping_eggs(|egg| self.workers.has_group(&egg.name));
+        let dependents: HashMap<String, Vec<String>> =
+            state.eggs.keys().map(|name| (name.clone(), state.dependents_of(name))).collect();
 
         for (_, egg) in state.eggs.iter_mut() {
-            // if the egg is not stopped or pending removal, continue
+            // if the egg is not stopped, stopping or pending removal, continue
 
             let is_pending_removal = egg.is_pending_removal();
             let is_stopped = egg.is_stopped();
             let is_restarting = egg.is_restarting();
+            let is_stopping = egg.is_stopping();
+
+            // errored eggs whose processes are still around failed while running (e.g. they
+            // became unhealthy), they have to be stopped before being retried
+            let is_failing =
+                egg.is_in_status(EggStatus::Errored) && self.workers.has_group(&egg.name);
 
-            if !is_stopped && !is_pending_removal && !is_restarting {
+            if !is_stopped && !is_pending_removal && !is_restarting && !is_stopping && !is_failing {
                 continue;
             }
 
@@ -32,50 +52,84 @@ impl Kurv {
                 }
             };
 
-            if let Some(child) = self.workers.get_child_mut(id) {
-                // check if the egg is actually running when it shouldn't
-                match child.inner().try_wait() {
-                    Ok(None) => {
-                        // it's still running, let's kill the mf
-                        match child.kill() {
-                            Err(ref e) if e.kind() == std::io::ErrorKind::InvalidData => {
-                                warn!("egg {} has already finished by itself.", egg.name);
-                            }
-                            Err(err) => {
-                                error!("error while stopping egg {}: {}", egg.name, err);
-                            }
-                            _ => {}
+            if let Some(pool) = self.workers.get_group_mut(&egg.name) {
+                if !is_stopping {
+                    let waiting_for = dependents
+                        .get(&egg.name)
+                        .is_some_and(|names| names.iter().any(|name| stopping.contains(name)));
+
+                    if waiting_for {
+                        continue;
+                    }
+
+                    // it's still running, let's politely ask every instance to stop
+                    let signal = egg.stop_signal();
+
+                    for (_, (_, child)) in pool.iter_mut() {
+                        if let Ok(None) = child.inner().try_wait()
+                            && let Err(err) = send_stop_signal(child, signal)
+                        {
+                            error!("error while sending {} to egg {}: {}", signal, egg.name, err);
                         }
+                    }
 
-                        // we should also remove the child from the workers map and
-                        // set the egg as stopped (clear its pid, etc, not just the state)
-                        self.workers.remove_child(None, egg.name.clone());
+                    egg.set_as_stopping();
+                    unsynced = true;
+                    debug!("egg <green>{}</green> is stopping ({})", egg.name, signal);
+                    continue;
+                }
 
-                        if is_restarting {
-                            egg.reset_state();
-                        } else {
-                            egg.set_as_stopped();
+                // it's stopping, we'll give it until its kill timeout
+                let timed_out = egg.has_stop_timed_out();
+
+                if timed_out {
+                    warn!(
+                        "egg <green>{}</green> didn't stop after {}, killing it",
+                        egg.name,
+                        egg.kill_timeout()
+                    );
+                }
+
+                // forget about the instances that are gone (by themselves or because we
+                // asked them to) and, if time's up, kill the ones that are still around
+                pool.retain(|worker_id, (_, child)| {
+                    let is_gone = match child.inner().try_wait() {
+                        Ok(None) if timed_out => {
+                            kill_child(child, &egg.name);
+                            true
                         }
+                        Ok(None) => false,
+                        Ok(Some(_)) => true,
+                        Err(e) => {
+                            error!("error while waiting for child process {}: {}", id, e);
+                            false
+                        }
+                    };
 
-                        unsynced = true;
-                        debug!("egg <green>{}</green> has been stopped", egg.name);
-                    }
-                    Ok(_) => {
-                        // it's stopped, but we still have it in the workers for some
-                        // odd reason (shouldn't happen)... well, let's remove it.
-                        self.workers.remove_child(None, egg.name.clone());
-                        // just in case...
-                        egg.set_as_stopped();
-                        unsynced = true;
-
-                        debug!(
-                            "egg <green>{}</green> is stopped but was still on the workers list, it has now been removed",
-                            egg.name
-                        );
+                    // so are the processes that left the process group of the instance
+                    if is_gone && let Some(root) = &cgroup_root {
+                        let index = worker_id.parse::<usize>().unwrap_or(0);
+                        let cgroup = InstanceCgroup::new(root, &egg.name, index);
+                        let (_, leftover) =
+                            cgroup.release(&egg.name, index, egg.stop_signal(), egg.kill_timeout());
+
+                        self.leftover_cgroups.extend(leftover);
                     }
-                    Err(e) => {
-                        error!("error while waiting for child process {}: {}", id, e);
-                        continue;
+
+                    !is_gone
+                });
+
+                if pool.is_empty() {
+                    // we should also remove the group from the workers map and set the
+                    // egg as stopped (clear its pid, etc, not just the state)
+                    self.workers.remove_group_if_empty(&egg.name);
+                    egg.finish_stopping();
+                    unsynced = true;
+
+                    if timed_out {
+                        debug!("egg <green>{}</green> has been killed", egg.name);
+                    } else {
+                        debug!("egg <green>{}</green> has been stopped", egg.name);
                     }
                 }
             } else {
@@ -83,10 +137,12 @@ impl Kurv {
                 // let's clean status to show that there is nothing running
                 // - if the egg is restarting, we should set it to Pending instead to
                 //   allow it to start even from a stopped state
+                // - if the egg was stopping (e.g. kurv was restarted in the meantime), we
+                //   move it to the status it was being stopped for
                 // - set_as_stopped will change status to Stopped only if current status is
                 //   not PendingRemoval. This will allow the removal to take place.
-                if is_restarting {
-                    egg.reset_state();
+                if is_restarting || is_stopping {
+                    egg.finish_stopping();
                     unsynced = true;
                 } else {
                     egg.set_as_stopped();
@@ -98,8 +154,8 @@ impl Kurv {
     }
 
     /// checks each egg looking for those that has its removal pending
-    /// and removes them from the state.
-    pub(crate) fn check_removal_pending_eggs(&mut self) -> bool {
+    /// and removes them from the state, once their processes are gone.
+    pub fn check_removal_pending_eggs(&mut self) -> bool {
         let state = self.state.clone();
         let mut state = state.lock().unwrap();
         let mut unsynced: bool = false;
@@ -112,7 +168,16 @@ impl Kurv {
                 continue;
             }
 
-            let _ = state.remove(egg.id.unwrap());
+            // it's still being stopped (e.g. waiting for its dependents to stop first),
+            // removing it now would leave its processes behind
+            if self.workers.has_group(&egg.name) {
+                continue;
+            }
+
+            if let Err(err) = state.remove(egg.id.unwrap()) {
+                debug!("egg <green>{}</green> can't be removed yet: {}", egg.name, err);
+                continue;
+            }
 
             debug!("egg <green>{}</green> has been removed", egg.name);
             unsynced = true
@@ -120,4 +185,40 @@ impl Kurv {
 
         unsynced
     }
+
+    /// removes the cgroups left behind by the instances that are gone once their processes
+    /// are gone too, killing the ones that take too long; the rest are tried again on the
+    /// next tick, without holding the state lock.
+    pub(crate) fn check_leftover_cgroups(&mut self) {
+        self.leftover_cgroups.retain(|cgroup| !cgroup.clean_up());
+    }
+}
+
+/// kills the process group of the given child and reaps it, so it doesn't linger as a zombie
+pub(crate) fn kill_child(child: &mut GroupChild, egg_name: &str) {
+    match child.kill() {
+        Err(ref e) if e.kind() == std::io::ErrorKind::InvalidData => {
+            warn!("egg {} has already finished by itself.", egg_name);
+        }
+        Err(err) => {
+            error!("error while stopping egg {}: {}", egg_name, err);
+        }
+        _ => {
+            let _ = child.wait();
+        }
+    }
+}
+
+/// asks the process group of the given child to stop by sending it the given signal
+#[cfg(unix)]
+pub(crate) fn send_stop_signal(child: &mut GroupChild, signal: StopSignal) -> std::io::Result<()> {
+    use command_group::UnixChildExt;
+
+    child.signal(signal.into())
+}
+
+/// there are no signals outside unix, the process group is just killed
+#[cfg(not(unix))]
+pub(crate) fn send_stop_signal(child: &mut GroupChild, _signal: StopSignal) -> std::io::Result<()> {
+    child.kill()
 }
@@ -0,0 +1,217 @@
+// the resource usage of each instance of each egg is sampled from /proc (only on linux) for
+// its whole process group, so the processes it spawns are accounted for too. Metrics are
+// kept in the state of the eggs, but sampling them doesn't trigger a sync of the state file.
+// Eggs with a `max_memory_restart` are restarted once one of their instances has been
+// above it for `max_memory_period`.
+
+use {
+    super::{
+        Kurv,
+        egg::{EggStatus, MEMORY_RESTART_MSG, ProcessMetrics, cpu_percent},
+    },
+    crate::common::size::humanize_size,
+    log::warn,
+    std::{
+        collections::HashMap,
+        time::{Duration, Instant},
+    },
+};
+
+/// the fields of `/proc/<pid>/stat` kurv uses
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub struct ProcStat {
+    /// process group the process belongs to
+    pub pgrp: u32,
+
+    /// cpu time of the process (user + system), in clock ticks
+    pub ticks: u64,
+
+    pub threads: u64,
+
+    /// resident memory, in pages
+    pub rss_pages: u64,
+}
+
+/// keeps track of the samples taken so far, to know when the next one is due and how much
+/// cpu time each process group used since the previous one
+#[derive(Default)]
+pub(crate) struct MetricsSampler {
+    last_sample: Option<Instant>,
+
+    /// cpu time of each process group (by pid of its leader) at the last sample
+    cpu_ticks: HashMap<u32, u64>,
+
+    /// since when each process group (by pid of its leader) has been above the
+    /// `max_memory_restart` of its egg
+    over_memory_since: HashMap<u32, Instant>,
+}
+
+impl Kurv {
+    /// samples the resource usage of the running eggs, if it's time to (see
+    /// `KURV_METRICS_INTERVAL`), and restarts the ones that have been using too much memory
+    /// for too long. Returns whether any egg has been restarted.
+    pub(crate) fn sample_metrics(&mut self) -> bool {
+        let Some(interval) = self.info.lock().unwrap().metrics_interval else {
+            return false;
+        };
+
+        if let Some(last_sample) = self.metrics.last_sample
+            && last_sample.elapsed() < interval.0
+        {
+            return false;
+        }
+
+        let now = Instant::now();
+        let elapsed = self.metrics.last_sample.map(|last_sample| now - last_sample);
+        self.metrics.last_sample = Some(now);
+
+        let groups = read_process_groups();
+        let mut cpu_ticks = HashMap::new();
+        let mut over_memory_since = HashMap::new();
+        let mut unsynced = false;
+
+        let state = self.state.clone();
+        let mut state = state.lock().unwrap();
+
+        for egg in state.eggs.values_mut() {
+            let instances: Vec<(usize, u32, EggStatus)> = egg
+                .state
+                .iter()
+                .flat_map(|state| state.instances.iter())
+                .map(|instance| (instance.index, instance.pid, instance.status))
+                .collect();
+
+            for (index, pid, status) in instances {
+                let group = groups.get(&pid).filter(|_| pid > 0 && status == EggStatus::Running);
+
+                let metrics = group.map(|group| {
+                    let ticks = group.iter().map(|(_, stat)| stat.ticks).sum::<u64>();
+                    let previous = self.metrics.cpu_ticks.get(&pid).copied();
+                    cpu_ticks.insert(pid, ticks);
+
+                    // the first sample of a group has nothing to compare to
+                    let used = previous.map(|previous| ticks.saturating_sub(previous)).unwrap_or(0);
+                    group_metrics(group, used, elapsed)
+                });
+
+                if let (Some(metrics), Some(limit)) = (metrics, egg.max_memory_restart)
+                    && metrics.memory > limit.0
+                {
+                    let since = self.metrics.over_memory_since.get(&pid).copied().unwrap_or(now);
+                    over_memory_since.insert(pid, since);
+
+                    if now - since >= egg.max_memory_period().0 && egg.is_running() {
+                        warn!(
+                            "egg <green>{}</green> has been using {} of memory (over {}) for {}, \
+                             restarting it",
+                            egg.name,
+                            humanize_size(metrics.memory),
+                            limit,
+                            egg.max_memory_period()
+                        );
+
+                        egg.restart_because(MEMORY_RESTART_MSG.to_string());
+                        unsynced = true;
+                    }
+                }
+
+                egg.set_instance_metrics(index, metrics);
+            }
+
+            egg.update_metrics();
+        }
+
+        self.metrics.cpu_ticks = cpu_ticks;
+        self.metrics.over_memory_since = over_memory_since;
+
+        unsynced
+    }
+}
+
+/// returns the metrics of a process group, made of `(pid, stat)` of each process, that used
+/// `used_ticks` of cpu time in the `elapsed` time since the previous sample
+fn group_metrics(
+    group: &[(u32, ProcStat)],
+    used_ticks: u64,
+    elapsed: Option<Duration>,
+) -> ProcessMetrics {
+    let (ticks_per_second, page_size) = system_units();
+
+    ProcessMetrics {
+        memory: group.iter().map(|(_, stat)| stat.rss_pages * page_size).sum(),
+        cpu: elapsed
+            .map(|elapsed| cpu_percent(used_ticks, ticks_per_second, elapsed.as_secs_f64()))
+            .unwrap_or(0.0),
+        threads: group.iter().map(|(_, stat)| stat.threads).sum(),
+        fds: group.iter().map(|(pid, _)| count_fds(*pid)).sum(),
+        processes: group.len() as u64,
+    }
+}
+
+/// parses the contents of `/proc/<pid>/stat`
+pub fn parse_stat(content: &str) -> Option<ProcStat> {
+    // the name of the command is between parentheses and can contain anything, even spaces
+    // and parentheses, so the fields are the ones after the last `)`
+    let (_, fields) = content.rsplit_once(')')?;
+    let fields: Vec<&str> = fields.split_whitespace().collect();
+
+    // `fields[0]` is the 3rd field of the file (the state of the process)
+    let field = |number: usize| fields.get(number - 3)?.parse::<u64>().ok();
+
+    Some(ProcStat {
+        pgrp: field(5)? as u32,
+        ticks: field(14)? + field(15)?,
+        threads: field(20)?,
+        rss_pages: field(24)?,
+    })
+}
+
+/// returns the processes running in the system, by process group
+#[cfg(target_os = "linux")]
+fn read_process_groups() -> HashMap<u32, Vec<(u32, ProcStat)>> {
+    let mut groups: HashMap<u32, Vec<(u32, ProcStat)>> = HashMap::new();
+
+    let Ok(entries) = std::fs::read_dir("/proc") else {
+        return groups;
+    };
+
+    for entry in entries.flatten() {
+        let Some(pid) = entry.file_name().to_str().and_then(|name| name.parse::<u32>().ok()) else {
+            continue;
+        };
+
+        // the process might be gone by now
+        if let Ok(content) = std::fs::read_to_string(entry.path().join("stat"))
+            && let Some(stat) = parse_stat(&content)
+        {
+            groups.entry(stat.pgrp).or_default().push((pid, stat));
+        }
+    }
+
+    groups
+}
+
+#[cfg(not(target_os = "linux"))]
+fn read_process_groups() -> HashMap<u32, Vec<(u32, ProcStat)>> {
+    HashMap::new()
+}
+
+/// returns the number of files the process has open
+fn count_fds(pid: u32) -> u64 {
+    std::fs::read_dir(format!("/proc/{pid}/fd")).map(|entries| entries.count() as u64).unwrap_or(0)
+}
+
+/// returns the clock ticks per second and the size of a memory page, in bytes
+#[cfg(unix)]
+fn system_units() -> (u64, u64) {
+    // SAFETY: sysconf just reads system settings
+    let (ticks, page_size) =
+        unsafe { (libc::sysconf(libc::_SC_CLK_TCK), libc::sysconf(libc::_SC_PAGESIZE)) };
+
+    (ticks.max(0) as u64, page_size.max(0) as u64)
+}
+
+#[cfg(not(unix))]
+fn system_units() -> (u64, u64) {
+    (100, 4096)
+}
@@ -1,29 +1,43 @@
-mod egg;
+mod cgroup;
+pub mod egg;
+mod health;
 mod kill;
+pub mod metrics;
 mod plugins;
+mod schedule;
 mod spawn;
 mod state;
-mod stdio;
+pub mod stdio;
 mod sync;
-mod workers;
+pub mod workers;
 
 use {
     crate::common::Info,
     anyhow::Result,
+    cgroup::LeftoverCgroup,
     command_group::CommandGroup,
+    metrics::MetricsSampler,
     std::{
-        process::Command,
+        collections::HashMap,
+        process::{Command, Stdio},
         sync::{Arc, Mutex},
-        thread::sleep,
+        thread::{JoinHandle, sleep},
         time::Duration,
     },
-    stdio::{clean_log_handles, create_log_file_handles},
     workers::Workers,
 };
 
 pub use {
-    egg::{Egg, EggState, EggStateUpsert, EggStatus},
-    state::KurvState,
+    egg::{
+        Basket, CRON_RESTART_MSG, ConfigChange, Egg, EggCheck, EggRestart, EggState,
+        EggStateUpsert, EggStatus, HealthCheck, HealthStatus, LogFormat, ProcessMetrics,
+        ResolvedCommand, RestartPolicy,
+    },
+    state::{KurvState, MISSING_DEPENDENCY_MSG},
+    stdio::{
+        JsonLogFields, JsonLogLine, LogFollower, LogOptions, create_log_files, get_log_paths,
+        pipe_to_log, tail_lines,
+    },
 };
 
 pub type KurvStateMtx = Arc<Mutex<KurvState>>;
@@ -34,6 +48,15 @@ pub struct Kurv {
     pub info: InfoMtx,
     pub state: KurvStateMtx,
     pub workers: Workers,
+
+    /// health checks in progress, by egg name
+    pub health_checks: HashMap<String, JoinHandle<Result<(), String>>>,
+
+    /// samples the resource usage of the eggs
+    metrics: MetricsSampler,
+
+    /// cgroups of the instances that are gone, waiting for their processes to be gone too
+    leftover_cgroups: Vec<LeftoverCgroup>,
 }
 
 impl Kurv {
@@ -43,19 +66,29 @@ impl Kurv {
             info,
             state,
             workers: Workers::new(),
+            health_checks: HashMap::new(),
+            metrics: MetricsSampler::default(),
+            leftover_cgroups: vec![],
         }
     }
 
     /// main loop of the server, it runs twice a second and checks the state
     /// of the app:
-    ///   - if there are any new eggs to spawn (eggs with state `Errored` or `Pending`),
-    ///     try to spawn them
+    ///   - moves the new scheduled eggs to `Scheduled` and restarts the ones whose
+    ///     `cron_restart` is due
+    ///   - if there are any new eggs to spawn (eggs with state `Errored` or `Pending`, or
+    ///     `Scheduled` and due), try to spawn them
     ///   - checks if all the running eggs are still actually running, and if not,
     ///     change their state to `Pending` or `Errored` depending on the reason and
     ///     remove them from the `workers` list so that they can be re-started on the
     ///     next tick
+    ///   - runs the health checks of the running eggs, restarting the unhealthy ones
     ///   - check if all eggs that were marked as stopped are actually stopped and
     ///     kill them otherwise
+    ///   - removes the cgroups left behind by the instances that are gone, once their
+    ///     processes are gone too
+    ///   - samples the resource usage of the running eggs every `KURV_METRICS_INTERVAL`,
+    ///     restarting the ones over their `max_memory_restart`
     pub fn run(&mut self) {
         loop {
             // each check returns an "unsynced" flag that tell us wether the state
@@ -63,16 +96,23 @@ impl Kurv {
             // this avoids unnecesary write operations
             let mut unsynced = false;
 
+            // before spawning, so scheduled eggs wait for their time
+            unsynced = self.check_schedules() || unsynced;
             unsynced = self.spawn_all() || unsynced;
             unsynced = self.check_running_eggs() || unsynced;
+            unsynced = self.check_health() || unsynced;
             unsynced = self.check_stopped_eggs() || unsynced;
 
             // removal needs to happen after stops, to avoid orphans
             unsynced = self.check_removal_pending_eggs() || unsynced;
+            self.check_leftover_cgroups();
 
             // check eggs for unsynced state changes, manually triggered (not state changes)
             unsynced = self.check_unsynced_eggs() || unsynced;
 
+            // metrics change all the time, they are only synced along with other changes
+            unsynced = self.sample_metrics() || unsynced;
+
             if unsynced {
                 // let state = self.state.clone();
                 let state = self.state.lock().unwrap();
@@ -91,6 +131,11 @@ impl Kurv {
     /// also discovers and collects plugin eggs from the plugins directory.
     pub fn collect() -> Result<(InfoMtx, KurvStateMtx)> {
         let info = Info::new();
+
+        if let Some(root) = &info.paths.cgroup {
+            cgroup::enable_controllers(root);
+        }
+
         let mut state = KurvState::load(&info.paths.kurv_file).unwrap();
 
         // discover and collect new plugins eggs
@@ -107,6 +152,8 @@ impl Kurv {
                 && state.status == EggStatus::Running
             {
                 state.status = EggStatus::Pending;
+                state.instances.clear();
+                state.metrics = None;
             }
         }
 
@@ -0,0 +1,42 @@
+use {
+    super::{CRON_RESTART_MSG, EggStatus, Kurv},
+    log::{debug, info},
+};
+
+impl Kurv {
+    /// moves the scheduled eggs that have just been added or started to `Scheduled`, so
+    /// they wait for their next run, and restarts the running eggs whose `cron_restart` is due.
+    pub(crate) fn check_schedules(&mut self) -> bool {
+        let state = self.state.clone();
+        let mut state = state.lock().unwrap();
+        let mut unsynced: bool = false;
+
+        for (_, egg) in state.eggs.iter_mut() {
+            let is_pending =
+                egg.state.as_ref().is_none_or(|state| state.status == EggStatus::Pending);
+
+            if egg.is_scheduled() && is_pending {
+                egg.set_as_scheduled();
+
+                match egg.state.as_ref().and_then(|state| state.next_run_at) {
+                    Some(next_run_at) => {
+                        debug!("egg <green>{}</green> will run at {}", egg.name, next_run_at)
+                    }
+                    None => debug!("egg <green>{}</green> won't ever run", egg.name),
+                }
+
+                unsynced = true;
+                continue;
+            }
+
+            if egg.is_running() && egg.cron_restart_due() {
+                info!("egg <green>{}</green> is being restarted by its cron_restart", egg.name);
+
+                egg.restart_because(CRON_RESTART_MSG.to_string());
+                unsynced = true;
+            }
+        }
+
+        unsynced
+    }
+}
@@ -1,12 +1,19 @@
 use {
-    super::{egg::EggPaths, *},
-    chrono::Duration,
+    super::{
+        cgroup::InstanceCgroup,
+        egg::EggPaths,
+        kill::{kill_child, send_stop_signal},
+        *,
+    },
+    crate::common::{API_TOKEN_KEY, duration::humanize_duration},
+    chrono::{Duration, Local},
     command_group::GroupChild,
-    log::{debug, error, warn},
+    log::{debug, error, info, warn},
 };
 
 impl Kurv {
-    /// try to spawn all eggs that are in `Pending` or `Errored` state
+    /// try to spawn all eggs that are in `Pending` or `Errored` state, as well as the
+    /// missing instances of running eggs (e.g. after one of them crashed or after scaling up)
     pub(crate) fn spawn_all(&mut self) -> bool {
         let state = self.state.clone();
         let mut state = state.lock().unwrap();
@@ -14,32 +21,58 @@ impl Kurv {
 
         let mut eggs = state.eggs.clone();
         for (key, egg) in eggs.iter_mut() {
-            // if the egg is errored or pending, try to spawn it
-            if egg.should_spawn() {
-                let (updated_egg, child) = self.spawn_egg(egg);
+            // an instance waits for the processes its previous run left behind to be gone
+            let instances: Vec<usize> = egg
+                .instances_to_spawn()
+                .into_iter()
+                .filter(|&index| {
+                    !self.leftover_cgroups.iter().any(|cgroup| cgroup.is_instance(&egg.name, index))
+                })
+                .collect();
 
-                // update original egg in state.eggs with the new values
-                state.eggs.insert(key.clone(), updated_egg);
+            if instances.is_empty() {
+                continue;
+            }
 
-                // if child is Some, add it to the workers
-                if let Some(child) = child {
-                    // so, we have a running egg, let's add it to the worker
-                    self.workers.add_child(None, key.clone(), egg.id.unwrap(), child);
-                }
+            // an egg is only started once its dependencies are ready; new instances of an
+            // egg that is already running don't have to wait
+            if !egg.is_running() {
+                let pending = state.pending_dependencies(egg);
 
-                unsynced = true;
+                if !pending.is_empty() {
+                    let waiting = format!("waiting for dependencies: {}", pending.join(", "));
+
+                    if egg.state.as_ref().and_then(|state| state.error.as_ref()) != Some(&waiting) {
+                        debug!("egg <green>{}</green> is {}", egg.name, waiting);
+
+                        if let Some(egg) = state.eggs.get_mut(key) {
+                            egg.set_error(waiting);
+                            unsynced = true;
+                        }
+                    }
+
+                    continue;
+                }
             }
+
+            let updated_egg = self.spawn_egg(egg, &instances);
+
+            // update original egg in state.eggs with the new values
+            state.eggs.insert(key.clone(), updated_egg);
+            unsynced = true;
         }
 
         unsynced
     }
 
     /// checks each eggs looking for those that have finished running unexpectedly
-    /// and sets their state accordingly. Also keeps re-try count updated
+    /// and sets their state accordingly. Also keeps re-try count updated and stops
+    /// the instances left over after an egg has been scaled down.
     pub(crate) fn check_running_eggs(&mut self) -> bool {
         let state = self.state.clone();
         let mut state = state.lock().unwrap();
         let mut unsynced: bool = false;
+        let cgroup_root = self.info.lock().unwrap().paths.cgroup.clone();
 
         for (_, egg) in state.eggs.iter_mut() {
             // if the egg is not running, then it was probably already checked
@@ -55,97 +88,191 @@ impl Kurv {
                 }
             };
 
-            if let Some(child) = self.workers.get_child_mut(id) {
-                // check that the child is still running
-                match child.inner().try_wait() {
-                    Ok(None) => {
-                        // if it has been running for more than 5 seconds, we can assume
-                        // it started correctly and reset the try count just in case
-                        if egg.has_been_running_for(Duration::seconds(5)) {
-                            egg.reset_try_count();
+            let instance_count = egg.instance_count();
+            let mut exited: Vec<(String, usize, Option<i32>)> = vec![];
+
+            if let Some(pool) = self.workers.get_group_mut(&egg.name) {
+                for (worker_id, (_, child)) in pool.iter_mut() {
+                    let index = worker_id.parse::<usize>().unwrap_or(0);
+
+                    // check that the child is still running
+                    match child.inner().try_wait() {
+                        Ok(None) if index >= instance_count => {
+                            // the egg has been scaled down, this instance has to go
+                            let is_stopping = egg
+                                .instance(index)
+                                .is_some_and(|instance| instance.status == EggStatus::Stopping);
+
+                            if !is_stopping {
+                                if let Err(err) = send_stop_signal(child, egg.stop_signal()) {
+                                    error!("error while stopping egg {}: {}", egg.name, err);
+                                }
+
+                                egg.set_instance_stopping(index);
+                                unsynced = true;
+                            } else if egg.has_instance_stop_timed_out(index) {
+                                kill_child(child, &egg.name);
+                                exited.push((worker_id.clone(), index, None));
+                            }
+                        }
+                        Ok(None) => {}
+                        Ok(Some(status)) => {
+                            exited.push((worker_id.clone(), index, status.code()));
+                        }
+                        Err(e) => {
+                            error!("error while waiting for child process {}: {}", id, e);
                         }
                     }
-                    Ok(Some(status)) => {
-                        // yikes, the egg has exited, let's update its state
-                        let exit_err_msg: String = match status.code() {
-                            Some(code) => format!("Exited with code {}", code),
-                            None => "Exited with unknown code".to_string(),
-                        };
-
-                        // try to get the try count from the egg
-                        let try_count = match &egg.state {
-                            Some(state) => state.try_count,
-                            None => 0,
-                        };
-
-                        warn!(
-                            "egg <green>{}</green> exited: {} [#{}]",
-                            egg.name, exit_err_msg, try_count
-                        );
-
-                        egg.set_as_errored(exit_err_msg);
-                        unsynced = true
-                    }
-                    Err(e) => {
-                        error!("error while waiting for child process {}: {}", id, e);
-                        continue;
+                }
+
+                for (worker_id, _, _) in exited.iter() {
+                    pool.remove(worker_id);
+                }
+            }
+
+            let is_alive = self.workers.has_group(&egg.name);
+            self.workers.remove_group_if_empty(&egg.name);
+
+            // yikes, some instances have exited, let's update their state
+            let mut failure: Option<String> = None;
+            let mut last_exit: Option<String> = None;
+
+            for (_, index, code) in exited {
+                unsynced = true;
+
+                // whatever the instance left behind goes away with it
+                let oom_killed = match &cgroup_root {
+                    Some(root) => {
+                        let cgroup = InstanceCgroup::new(root, &egg.name, index);
+                        let (oom_killed, leftover) =
+                            cgroup.release(&egg.name, index, egg.stop_signal(), egg.kill_timeout());
+
+                        self.leftover_cgroups.extend(leftover);
+                        oom_killed
                     }
+                    None => false,
+                };
+
+                if index >= instance_count {
+                    egg.remove_instance(index);
+                    debug!("egg <green>{}</green> has been scaled down", instance_name(egg, index));
+                    continue;
+                }
+
+                let (exit_msg, failed) = on_instance_exit(egg, index, code, oom_killed);
+
+                if failed {
+                    failure = Some(exit_msg.clone());
+                }
+
+                last_exit = Some(exit_msg);
+            }
+
+            if !is_alive {
+                // no instance is running anymore, the egg as a whole is either done or errored
+                let reason = last_exit.unwrap_or_default();
+
+                if egg.is_scheduled() {
+                    info!("egg <green>{}</green> is done running: {}", egg.name, reason);
+                    egg.finish_run(reason);
+                } else if egg.all_instances_finished() {
+                    egg.set_as_finished(reason);
+                } else if let Some(ref failure) = failure {
+                    egg.set_as_errored(failure.clone());
+                } else {
+                    egg.set_status(EggStatus::Errored);
+                    egg.set_pid(0);
+                }
+
+                unsynced = true;
+            } else {
+                egg.set_pid(egg.first_instance_pid());
+
+                if let Some(ref failure) = failure {
+                    // the failed instances will be re-spawned on their own, the rest of the
+                    // egg keeps running
+                    egg.set_error(failure.clone());
+                    egg.increment_try_count();
+                    egg.schedule_next_attempt();
+                }
+
+                // if all its instances have been running for more than 5 seconds (and it's
+                // healthy, if it has a health check), we can assume it started correctly and
+                // reset the try count just in case
+                if egg.all_instances_running_for(Duration::seconds(5))
+                    && egg.is_healthy_or_unchecked()
+                {
+                    egg.reset_try_count();
                 }
             }
+
+            if let Some(failure) = failure {
+                log_restart_outcome(egg, failure);
+            }
         }
 
         unsynced
     }
 
-    /// spawns the given `egg` and adds it to the `workers` list
-    fn spawn_egg(&mut self, egg: &Egg) -> (Egg, Option<GroupChild>) {
-        let info = &self.info.lock().unwrap();
+    /// spawns the given `instances` of the `egg` and adds them to the `workers` list
+    fn spawn_egg(&mut self, egg: &Egg, instances: &[usize]) -> Egg {
         let mut egg = egg.clone();
-        let egg_name = egg.name.clone();
-        let log_dir = info.paths.logs_dir.clone();
+        let was_running = egg.is_running();
+        let mut spawn_error: Option<String> = None;
+
+        for &index in instances {
+            match self.spawn_instance(&egg, index) {
+                Ok((child, paths)) => {
+                    // instances that errored before (e.g. crashed) are being restarted
+                    if egg.instance(index).is_some_and(|i| i.status == EggStatus::Errored) {
+                        egg.increment_restarts();
+                    }
+
+                    // the paths of the first instance are the paths of the egg
+                    if index == 0 {
+                        egg.paths = Some(paths.clone());
+                    }
+
+                    egg.set_instance_running(index, child.id(), Some(paths));
 
-        let ((stdout_path, stdout_log), (stderr_path, stderr_log)) =
-            match create_log_file_handles(&egg_name, &log_dir) {
-                Ok((stdout_log, stderr_log)) => (stdout_log, stderr_log),
-                Err(err) => {
-                    panic!("failed to create log file handles: {}", err)
+                    // so, we have a running instance, let's add it to the egg's workers
+                    self.workers.add_child(
+                        Some(&egg.name),
+                        index.to_string(),
+                        egg.id.unwrap(),
+                        child,
+                    );
+
+                    debug!("spawned egg <green>{}</green>", instance_name(&egg, index));
                 }
-            };
+                Err(error) => {
+                    error!("{}", error);
+                    egg.set_instance_errored(index, error.clone());
+                    spawn_error = Some(error);
+                }
+            }
+        }
 
-        egg.paths = Some(EggPaths {
-            stdout: stdout_path,
-            stderr: stderr_path,
-        });
-
-        let (command, cwd, args, envs) = {
-            (
-                egg.command.clone(),
-                match egg.cwd.clone() {
-                    Some(cwd) => cwd,
-                    None => info.paths.working_dir.clone(),
-                },
-                egg.args.clone(),
-                egg.env.clone(),
-            )
-        };
+        let pid = egg.first_instance_pid();
 
-        // Chain the args method call directly to the Command creation and configuration
-        let process = Command::new(command)
-            .current_dir(cwd)
-            .stdout(stdout_log)
-            .stderr(stderr_log)
-            .args(args.unwrap_or_else(Vec::new))
-            .envs(envs.unwrap_or_else(std::collections::HashMap::new))
-            .group_spawn();
+        if pid > 0 {
+            if was_running {
+                egg.set_pid(pid);
+            } else {
+                egg.set_as_running(pid);
+            }
+        }
 
         // check if it has been spawned correctly
-        let child = match process {
-            Ok(child) => child,
-            Err(err) => {
-                let error = format!("failed to spawn child {egg_name} with err: {err:?}");
-                error!("{}", error);
-                clean_log_handles(&egg_name, &log_dir);
+        if let Some(error) = spawn_error {
+            // a scheduled egg tries again on its next run
+            if pid == 0 && egg.is_scheduled() {
+                egg.set_as_scheduled();
+                egg.set_error(error);
+                return egg;
+            }
 
+            if pid == 0 {
                 // Update all necessary fields on the task.
                 egg.upsert_state(EggStateUpsert {
                     status: Some(egg::EggStatus::Errored),
@@ -154,19 +281,239 @@ impl Kurv {
                     start_time: None,
                     try_count: None,
                 });
+            } else {
+                egg.set_error(error);
+            }
+
+            // Increment the try count and back off before the next attempt
+            egg.increment_try_count();
+            egg.schedule_next_attempt();
+        }
+
+        egg
+    }
+
+    /// spawns the instance with the given `index` of the `egg`
+    fn spawn_instance(&self, egg: &Egg, index: usize) -> Result<(GroupChild, EggPaths), String> {
+        let info = &self.info.lock().unwrap();
+        let log_name = egg.instance_log_name(index);
+        let log_dir = info.paths.logs_dir.clone();
+
+        // `${VAR}` references are resolved (and env files read) on each spawn
+        let ResolvedCommand {
+            command,
+            args,
+            cwd,
+            env: mut envs,
+        } = egg
+            .resolve(&info.paths.working_dir)
+            .map_err(|problem| format!("invalid {}: {}", problem.field, problem.message))?;
+
+        // fails if the user or group doesn't exist
+        let run_as = egg.run_as().map_err(|problem| problem.message)?;
+
+        // a fresh cgroup for the instance, if kurv has been given one to manage
+        let cgroup = match &info.paths.cgroup {
+            Some(root) => {
+                let cgroup = InstanceCgroup::new(root, &egg.name, index);
+                cgroup.create(&egg.cgroup())?;
+                Some(cgroup)
+            }
+            None if egg.cgroup.is_some() => {
+                return Err(format!(
+                    "egg {} sets cgroup limits, but kurv hasn't been given a cgroup (KURV_CGROUP)",
+                    egg.name
+                ));
+            }
+            None => None,
+        };
+
+        // undoes what has been set up so far when the instance can't be spawned; the log
+        // files are kept, they hold the output of the previous runs (their open handles are
+        // just dropped)
+        let clean_up = |err: String| {
+            if let Some(cgroup) = &cgroup {
+                let _ = cgroup.remove();
+            }
+
+            err
+        };
+
+        // the egg's rotation settings take precedence over the global ones
+        let rotation = match egg.log_rotation {
+            Some(ref rotation) => rotation.or(&info.log_rotation),
+            None => info.log_rotation.clone(),
+        };
+
+        // lines are written as is unless asked otherwise
+        let capture = egg.log_capture.clone().unwrap_or_default();
+        let options = LogOptions {
+            timestamp_format: if capture.timestamps {
+                Some(capture.timestamp_format()?)
+            } else {
+                None
+            },
+            combined: capture.combined,
+            json: match egg.log_format.unwrap_or_default() {
+                LogFormat::Json => Some(JsonLogFields {
+                    egg: egg.name.clone(),
+                    id: egg.id.unwrap_or(0),
+                    instance: index,
+                    pid: 0,
+                }),
+                LogFormat::Raw => None,
+            },
+            // so the user the egg runs as can read its own logs
+            owner: run_as.as_ref().map(|run_as| (run_as.uid, run_as.gid)),
+        };
+
+        let (paths, mut stdout_log, mut stderr_log) =
+            create_log_files(&log_name, &log_dir, &rotation, &options)
+                .map_err(|err| clean_up(format!("failed to create log file handles: {}", err)))?;
+
+        // let each instance know which one it is
+        envs.insert("KURV_INSTANCE".to_string(), index.to_string());
+
+        // Chain the args method call directly to the Command creation and configuration;
+        // the output goes through pipes owned by kurv, so log files can be rotated
+        let mut process = Command::new(command);
+        egg.apply_inherit_env(&mut process);
+
+        // the process joins its cgroup first, while it can still write to it
+        if let Some(cgroup) = &cgroup {
+            cgroup.attach(&mut process).map_err(clean_up)?;
+        }
 
-                // Increment the try count
-                egg.increment_try_count();
+        // limits go next, as setting some of them needs the privileges the egg may drop
+        egg.limits().apply(&mut process).map_err(clean_up)?;
 
-                // Return the updated egg
-                return (egg, None);
+        if let Some(run_as) = &run_as {
+            run_as.apply(&mut process);
+        }
+
+        let process = process
+            .current_dir(cwd)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .args(args)
+            // eggs don't get the api token of kurv, unless they set it themselves
+            .env_remove(API_TOKEN_KEY)
+            .envs(envs)
+            .group_spawn();
+
+        match process {
+            Ok(mut child) => {
+                stdout_log.set_pid(child.id());
+                stderr_log.set_pid(child.id());
+
+                if let Some(stdout) = child.inner().stdout.take() {
+                    pipe_to_log(stdout, stdout_log);
+                }
+
+                if let Some(stderr) = child.inner().stderr.take() {
+                    pipe_to_log(stderr, stderr_log);
+                }
+
+                Ok((child, paths))
             }
+            Err(err) => Err(clean_up(format!(
+                "failed to spawn child {} with err: {err:?}",
+                instance_name(egg, index)
+            ))),
+        }
+    }
+}
+
+/// returns the name of the instance with the given `index` of the egg, as shown in logs
+fn instance_name(egg: &Egg, index: usize) -> String {
+    match egg.instance_count() {
+        1 => egg.name.clone(),
+        _ => format!("{}[{}]", egg.name, index),
+    }
+}
+
+/// updates the state of the instance with the given `index` of the `egg` after its process
+/// exited with the given `code` (`oom_killed` if the OOM killer killed any of its processes).
+///
+/// returns the exit message and whether the exit counts as a failure (as opposed to a
+/// successful exit that the restart policy doesn't want to restart, like one-shot jobs)
+fn on_instance_exit(
+    egg: &mut Egg,
+    index: usize,
+    code: Option<i32>,
+    oom_killed: bool,
+) -> (String, bool) {
+    let mut exit_msg: String = match code {
+        Some(code) => format!("Exited with code {}", code),
+        None => "Exited with unknown code".to_string(),
+    };
+
+    if oom_killed {
+        let limit = egg.cgroup().memory_max.map(|max| format!(" (memory_max: {max})"));
+
+        exit_msg = match code {
+            Some(_) => format!("{exit_msg} after the OOM killer killed one of its processes"),
+            None => "Killed by the OOM killer".to_string(),
         };
+        exit_msg.push_str(&limit.unwrap_or_default());
+    }
 
-        egg.set_as_running(child.id());
+    egg.set_exit_code(code);
 
-        debug!("spawned egg <green>{}</green>", egg.name);
+    // a scheduled egg is expected to exit, whatever its exit code it will run again on its
+    // next scheduled time
+    if egg.is_scheduled() {
+        egg.set_instance_finished(index, exit_msg.clone());
+        return (exit_msg, false);
+    }
+
+    let restart: EggRestart = egg.restart_config();
+
+    if !oom_killed && restart.is_success(code) && !egg.should_restart_on_exit(code) {
+        info!("egg <green>{}</green> finished: {}", instance_name(egg, index), exit_msg);
+        egg.set_instance_finished(index, exit_msg.clone());
+        return (exit_msg, false);
+    }
+
+    // try to get the try count from the egg
+    let try_count = match &egg.state {
+        Some(state) => state.try_count,
+        None => 0,
+    };
+
+    warn!(
+        "egg <green>{}</green> exited: {} [#{}]",
+        instance_name(egg, index),
+        exit_msg,
+        try_count
+    );
+
+    egg.set_instance_errored(index, exit_msg.clone());
+    (exit_msg, true)
+}
+
+/// lets the user know what happens next after a failure of the `egg`: either it will be
+/// restarted (maybe after a backoff delay), or its restart policy gave up on it.
+pub(crate) fn log_restart_outcome(egg: &mut Egg, failure: String) {
+    // if the restart policy doesn't allow another try, the errored state
+    // is final; let's make the reason clear
+    if !egg.can_retry() {
+        let restart = egg.restart_config();
+        let reason = match restart.max_retries {
+            Some(max) if restart.policy != RestartPolicy::Never => {
+                format!("{failure} (gave up after {max} retries)")
+            }
+            _ => format!("{failure} (restart policy: never)"),
+        };
 
-        (egg, Some(child))
+        warn!("egg <green>{}</green> won't be restarted: {}", egg.name, reason);
+        egg.set_error(reason);
+    } else if let Some(next_attempt_at) = egg.state.as_ref().and_then(|state| state.next_attempt_at)
+    {
+        debug!(
+            "egg <green>{}</green> will be restarted in {}",
+            egg.name,
+            humanize_duration(next_attempt_at - Local::now())
+        );
     }
 }
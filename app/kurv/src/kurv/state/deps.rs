@@ -0,0 +1,122 @@
+use {
+    super::KurvState,
+    crate::{
+        common::tcp::Problem,
+        kurv::egg::{Egg, EggStatus, HealthStatus},
+    },
+    std::collections::HashSet,
+};
+
+/// ends the warning of a dependency that isn't collected (see `missing_dependencies`)
+pub const MISSING_DEPENDENCY_MSG: &str = "isn't collected, the egg will wait for it";
+
+impl KurvState {
+    /// 🥚 » checks if the egg with the given `name` is ready for its dependents to start:
+    /// it has to be running and, if it has a health check, be healthy.
+    pub fn is_ready(&self, name: &str) -> bool {
+        match self.get_by_name(name) {
+            Some(egg) if egg.is_running() => {
+                egg.health.is_none() || egg.health_status() == HealthStatus::Healthy
+            }
+            _ => false,
+        }
+    }
+
+    /// 🥚 » returns the dependencies of the `egg` that are not ready yet
+    pub fn pending_dependencies(&self, egg: &Egg) -> Vec<String> {
+        egg.dependencies().iter().filter(|name| !self.is_ready(name)).cloned().collect()
+    }
+
+    /// 🥚 » returns a warning for each dependency of the `egg` that isn't collected: the egg
+    /// waits for it until it is.
+    pub fn missing_dependencies(&self, egg: &Egg) -> Vec<Problem> {
+        let mut problems = vec![];
+
+        for (index, dependency) in egg.dependencies().iter().enumerate() {
+            let repeated = egg.dependencies()[..index].contains(dependency);
+
+            if *dependency != egg.name && !repeated && self.get_by_name(dependency).is_none() {
+                let msg = format!("{dependency} {MISSING_DEPENDENCY_MSG}");
+                problems.push(Problem::warning(&format!("depends_on.{index}"), msg));
+            }
+        }
+
+        problems
+    }
+
+    /// 🥚 » returns the names of the eggs that depend on the egg with the given `name`
+    pub fn dependents_of(&self, name: &str) -> Vec<String> {
+        self.eggs
+            .values()
+            .filter(|egg| egg.dependencies().iter().any(|dep| dep == name))
+            .map(|egg| egg.name.clone())
+            .collect()
+    }
+
+    /// 🥚 » looks for a dependency cycle that the given `egg` would introduce if it was
+    /// collected (or replaced, if there's already an egg with its name).
+    ///
+    /// returns the cycle as a list of egg names, starting and ending with the same egg
+    /// (e.g. `["api", "worker", "api"]`).
+    pub fn find_dependency_cycle(&self, egg: &Egg) -> Option<Vec<String>> {
+        let mut path = vec![egg.name.clone()];
+        let mut visited = HashSet::new();
+
+        self.walk_dependencies(egg, egg, &mut path, &mut visited)
+    }
+
+    fn walk_dependencies(
+        &self,
+        root: &Egg,
+        current: &Egg,
+        path: &mut Vec<String>,
+        visited: &mut HashSet<String>,
+    ) -> Option<Vec<String>> {
+        for dep in current.dependencies() {
+            if dep == &root.name {
+                let mut cycle = path.clone();
+                cycle.push(dep.clone());
+                return Some(cycle);
+            }
+
+            if !visited.insert(dep.clone()) {
+                continue;
+            }
+
+            // missing dependencies can't be part of a cycle (yet)
+            let Some(next) = self.get_by_name(dep) else {
+                continue;
+            };
+
+            path.push(dep.clone());
+
+            if let Some(cycle) = self.walk_dependencies(root, next, path, visited) {
+                return Some(cycle);
+            }
+
+            path.pop();
+        }
+
+        None
+    }
+
+    /// 🥚 » returns the names of the eggs that are on their way to be stopped but still
+    /// have processes around; their dependencies have to wait for them to be gone.
+    pub fn stopping_eggs(&self, is_alive: impl Fn(&Egg) -> bool) -> HashSet<String> {
+        self.eggs
+            .values()
+            .filter(|egg| {
+                matches!(
+                    egg.state.as_ref().map(|state| state.status),
+                    Some(
+                        EggStatus::Stopped
+                            | EggStatus::Stopping
+                            | EggStatus::PendingRemoval
+                            | EggStatus::Restarting
+                    )
+                ) && is_alive(egg)
+            })
+            .map(|egg| egg.name.clone())
+            .collect()
+    }
+}
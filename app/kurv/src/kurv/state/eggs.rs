@@ -34,7 +34,10 @@ impl KurvState {
 
     /// 🥚 » retrieves the egg with the given `pid` from the state
     pub fn get_by_pid(&self, pid: u32) -> Option<&Egg> {
-        self.eggs.values().find(|&e| e.state.is_some() && e.state.as_ref().unwrap().pid == pid)
+        self.eggs.values().find(|&e| match e.state {
+            Some(ref state) => state.pid == pid || state.instances.iter().any(|i| i.pid == pid),
+            None => false,
+        })
     }
 
     // 🥚 » returns `true` if there's an agg with name `key`
@@ -1,3 +1,4 @@
+mod deps;
 pub mod eggs;
 
 use {
@@ -9,9 +10,11 @@ use {
     std::{collections::BTreeMap, fs::File, path::PathBuf},
 };
 
+pub use deps::MISSING_DEPENDENCY_MSG;
+
 /// KurvState encapsulates the state of the server side application
 /// It's serialized to disk as a YAML file and loaded on startup
-#[derive(PartialEq, Eq, Clone, Deserialize, Serialize)]
+#[derive(PartialEq, Clone, Deserialize, Serialize)]
 pub struct KurvState {
     pub eggs: BTreeMap<String, Egg>,
 }
@@ -1,29 +1,532 @@
 use {
+    super::egg::EggPaths,
+    crate::common::rotation::LogRotation,
     anyhow::{Result, anyhow},
+    chrono::{DateTime, Local, SecondsFormat},
+    flate2::{Compression, write::GzEncoder},
     log::error,
+    serde::{Deserialize, Serialize},
     std::{
-        fs::{File, OpenOptions, create_dir_all},
+        fs::{self, File, OpenOptions, create_dir_all},
+        io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write},
         path::{Path, PathBuf},
+        sync::{Arc, Mutex},
+        thread::{self, JoinHandle},
     },
 };
 
 /// The type of an stdio file.
+#[derive(Clone, Copy)]
 enum StdioFile {
     Stdout,
     Stderr,
+    Combined,
 }
 
-/// Create and return the two file handles for the `(stdout, stderr)` log file of a task.
-/// These are two handles to the same file.
-pub fn create_log_file_handles(
+impl StdioFile {
+    /// tag written before the lines of this stream when they are prefixed
+    fn tag(&self) -> &'static str {
+        match self {
+            StdioFile::Stdout => "[out]",
+            StdioFile::Stderr => "[err]",
+            StdioFile::Combined => "",
+        }
+    }
+
+    /// name of the stream in json logs
+    fn name(&self) -> &'static str {
+        match self {
+            StdioFile::Stdout => "stdout",
+            StdioFile::Stderr => "stderr",
+            StdioFile::Combined => "combined",
+        }
+    }
+}
+
+/// A log file of a task, rotated according to its `LogRotation` settings.
+///
+/// The file is owned by kurv (the task writes to a pipe that kurv reads from), so it can be
+/// rotated at any time without the task noticing.
+pub struct LogFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+    opened_at: DateTime<Local>,
+    rotation: LogRotation,
+
+    /// uid and gid the file (and the ones it's rotated to) is owned by
+    owner: Option<(u32, u32)>,
+
+    /// thread gzipping the last rotated file, so the task isn't kept waiting on its pipe
+    compressing: Option<JoinHandle<()>>,
+}
+
+impl LogFile {
+    /// creates the log file at `path` or opens it for appending if it already exists
+    pub fn open(path: &Path, rotation: LogRotation) -> Result<LogFile> {
+        let file = create_or_append_file(path)?;
+        let metadata = file.metadata().ok();
+
+        // an existing file keeps counting from when it was created
+        let opened_at = metadata
+            .as_ref()
+            .and_then(|metadata| metadata.created().ok())
+            .map(DateTime::<Local>::from)
+            .unwrap_or_else(Local::now);
+
+        Ok(LogFile {
+            path: path.to_path_buf(),
+            file,
+            size: metadata.map(|metadata| metadata.len()).unwrap_or(0),
+            opened_at,
+            rotation,
+            owner: None,
+            compressing: None,
+        })
+    }
+
+    /// makes the file be owned by the given uid and gid (also after being rotated)
+    pub fn set_owner(&mut self, owner: Option<(u32, u32)>) -> Result<()> {
+        self.owner = owner;
+        chown(&self.path, owner)
+    }
+
+    /// appends `line` to the log file, rotating it first if it's due
+    pub fn write_line(&mut self, line: &[u8]) -> Result<()> {
+        if self.should_rotate() {
+            self.rotate()?;
+        }
+
+        self.file.write_all(line)?;
+        self.size += line.len() as u64;
+
+        Ok(())
+    }
+
+    /// checks if the log file has to be rotated before writing to it again
+    fn should_rotate(&self) -> bool {
+        if self.size == 0 {
+            return false;
+        }
+
+        let too_big = self.rotation.max_size.is_some_and(|max_size| self.size >= max_size.0);
+        let too_old = self
+            .rotation
+            .interval
+            .is_some_and(|interval| Local::now() - self.opened_at >= interval.to_chrono());
+
+        too_big || too_old
+    }
+
+    /// moves the current log file to `<file>.1` (and `<file>.1` to `<file>.2` and so on,
+    /// dropping the ones past `max_files`), then starts a new one.
+    pub fn rotate(&mut self) -> Result<()> {
+        // the rotated files are about to be renamed, the last one has to be compressed first
+        self.wait_for_compression();
+
+        let max_files = self.rotation.max_files();
+
+        for index in (1..=max_files).rev() {
+            for gz in [false, true] {
+                let from = rotated_path(&self.path, index, gz);
+
+                if !from.exists() {
+                    continue;
+                }
+
+                if index == max_files {
+                    fs::remove_file(&from)?;
+                } else {
+                    fs::rename(&from, rotated_path(&self.path, index + 1, gz))?;
+                }
+            }
+        }
+
+        if max_files > 0 {
+            let rotated = rotated_path(&self.path, 1, false);
+            fs::rename(&self.path, &rotated)?;
+
+            if self.rotation.compress() {
+                let compressing = thread::Builder::new().spawn(move || {
+                    if let Err(err) = compress(&rotated) {
+                        error!("failed to compress log file {}: {}", rotated.display(), err);
+                    }
+                });
+
+                self.compressing = Some(compressing?);
+            }
+        } else {
+            fs::remove_file(&self.path)?;
+        }
+
+        self.file = create_or_append_file(&self.path)?;
+        self.size = 0;
+        self.opened_at = Local::now();
+        chown(&self.path, self.owner)?;
+
+        Ok(())
+    }
+
+    /// waits for the last rotated file to be compressed, if it's being compressed
+    pub fn wait_for_compression(&mut self) {
+        if let Some(compressing) = self.compressing.take() {
+            let _ = compressing.join();
+        }
+    }
+}
+
+/// returns the path of the rotated log file with the given `index` (e.g. `api.stdout.1`, or
+/// `api.stdout.1.gz` if `gz`).
+pub fn rotated_path(path: &Path, index: u32, gz: bool) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{index}"));
+
+    if gz {
+        name.push(".gz");
+    }
+
+    PathBuf::from(name)
+}
+
+/// gzips the file at `path` into `<path>.gz` and removes the original
+fn compress(path: &Path) -> Result<()> {
+    let mut gz_name = path.as_os_str().to_owned();
+    gz_name.push(".gz");
+
+    let mut input = File::open(path)?;
+    let mut encoder = GzEncoder::new(File::create(PathBuf::from(gz_name))?, Compression::default());
+    io::copy(&mut input, &mut encoder)?;
+    encoder.finish()?;
+
+    fs::remove_file(path)?;
+    Ok(())
+}
+
+/// how the lines of a task are written to its log files
+#[derive(Clone, Default)]
+pub struct LogOptions {
+    /// strftime format of the timestamps prefixed to each line, if enabled
+    pub timestamp_format: Option<String>,
+
+    /// also writes both streams, interleaved and tagged, to `<task_name>.log`
+    pub combined: bool,
+
+    /// writes each line as a json object with these fields, instead of as text
+    pub json: Option<JsonLogFields>,
+
+    /// uid and gid the log files are owned by, when the task runs as another user
+    pub owner: Option<(u32, u32)>,
+}
+
+/// fields identifying the task in each line of its json logs
+#[derive(Clone, Default)]
+pub struct JsonLogFields {
+    pub egg: String,
+    pub id: usize,
+    pub instance: usize,
+    pub pid: u32,
+}
+
+/// a line of a log written in json format
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct JsonLogLine {
+    /// when the line was written, in rfc 3339 format
+    pub ts: String,
+    pub egg: String,
+    pub id: usize,
+    pub instance: usize,
+    /// `stdout` or `stderr`
+    pub stream: String,
+    pub pid: u32,
+    pub msg: String,
+}
+
+/// writes the lines read from one of the pipes of a task to its log files.
+pub struct LogWriter {
+    stdio: StdioFile,
+    log: LogFile,
+
+    /// log file shared by both pipes of the task, if enabled
+    combined: Option<Arc<Mutex<LogFile>>>,
+
+    options: LogOptions,
+}
+
+impl LogWriter {
+    /// sets the pid of the task, once it has been spawned, for its json logs
+    pub fn set_pid(&mut self, pid: u32) {
+        if let Some(ref mut json) = self.options.json {
+            json.pid = pid;
+        }
+    }
+
+    /// writes a line read from the pipe, prefixing it with its timestamp and stream tag if
+    /// enabled (the lines in the combined file are always tagged), or as json.
+    fn write_line(&mut self, line: &[u8]) -> Result<()> {
+        if let Some(ref fields) = self.options.json {
+            let json = self.to_json(fields, line)?;
+            self.log.write_line(&json)?;
+
+            if let Some(ref combined) = self.combined {
+                let mut combined =
+                    combined.lock().map_err(|_| anyhow!("failed to lock log file"))?;
+                combined.write_line(&json)?;
+            }
+
+            return Ok(());
+        }
+
+        let timestamp = match self.options.timestamp_format {
+            Some(ref format) => format!("{} ", Local::now().format(format)),
+            None => String::new(),
+        };
+
+        let tagged = || {
+            let mut tagged = format!("{}{} ", timestamp, self.stdio.tag()).into_bytes();
+            tagged.extend_from_slice(line);
+
+            // the last line of the output might not have a line break
+            if !tagged.ends_with(b"\n") {
+                tagged.push(b'\n');
+            }
+
+            tagged
+        };
+
+        match self.options.timestamp_format {
+            Some(_) => self.log.write_line(&tagged())?,
+            None => self.log.write_line(line)?,
+        }
+
+        if let Some(ref combined) = self.combined {
+            let mut combined = combined.lock().map_err(|_| anyhow!("failed to lock log file"))?;
+            combined.write_line(&tagged())?;
+        }
+
+        Ok(())
+    }
+
+    /// returns the given line as a json object, followed by a line break
+    fn to_json(&self, fields: &JsonLogFields, line: &[u8]) -> Result<Vec<u8>> {
+        let msg = String::from_utf8_lossy(line);
+
+        let mut json = serde_json::to_vec(&JsonLogLine {
+            ts: Local::now().to_rfc3339_opts(SecondsFormat::Millis, false),
+            egg: fields.egg.clone(),
+            id: fields.id,
+            instance: fields.instance,
+            stream: self.stdio.name().to_string(),
+            pid: fields.pid,
+            msg: msg.trim_end_matches(['\n', '\r']).to_string(),
+        })?;
+
+        json.push(b'\n');
+        Ok(json)
+    }
+
+    /// returns the path of the log file of this pipe
+    fn path(&self) -> &Path {
+        &self.log.path
+    }
+}
+
+/// Create and return the log files of a task: the paths to all of them and the writers of its
+/// `stdout` and `stderr`, which write their lines as set in `options`.
+pub fn create_log_files(
     task_name: &str,
     path: &Path,
-) -> Result<((PathBuf, File), (PathBuf, File))> {
+    rotation: &LogRotation,
+    options: &LogOptions,
+) -> Result<(EggPaths, LogWriter, LogWriter)> {
     let (stdout_path, stderr_path) = get_log_paths(task_name, path);
-    let stdout_handle = create_or_append_file(&stdout_path)?;
-    let stderr_handle = create_or_append_file(&stderr_path)?;
+    let combined_path = path.join(stdio_filename(task_name, StdioFile::Combined));
+
+    let open = |path: &Path| -> Result<LogFile> {
+        let mut log = LogFile::open(path, rotation.clone())?;
+        log.set_owner(options.owner)?;
+        Ok(log)
+    };
+
+    let combined_log = if options.combined {
+        Some(Arc::new(Mutex::new(open(&combined_path)?)))
+    } else {
+        None
+    };
+
+    let writer = |stdio: StdioFile, path: &Path| -> Result<LogWriter> {
+        Ok(LogWriter {
+            stdio,
+            log: open(path)?,
+            combined: combined_log.clone(),
+            options: options.clone(),
+        })
+    };
 
-    Ok(((stdout_path, stdout_handle), (stderr_path, stderr_handle)))
+    let stdout = writer(StdioFile::Stdout, &stdout_path)?;
+    let stderr = writer(StdioFile::Stderr, &stderr_path)?;
+
+    let paths = EggPaths {
+        stdout: stdout_path,
+        stderr: stderr_path,
+        combined: options.combined.then_some(combined_path),
+    };
+
+    Ok((paths, stdout, stderr))
+}
+
+/// max length of a line of a log; longer lines are written in pieces of this length
+pub const MAX_LINE_LENGTH: u64 = 64 * 1024;
+
+/// copies everything the task writes to the given pipe into its log files, line by line, on
+/// its own thread. The thread ends once the pipe is closed (the task and all of its children
+/// have exited).
+pub fn pipe_to_log<R: Read + Send + 'static>(pipe: R, mut log: LogWriter) {
+    let name = log.path().file_name().map(|name| name.to_string_lossy().to_string());
+    let builder = thread::Builder::new().name(name.unwrap_or_default());
+
+    let spawned = builder.spawn(move || copy_to_log(pipe, &mut log));
+
+    if let Err(err) = spawned {
+        error!("failed to start log writer thread: {}", err);
+    }
+}
+
+/// copies everything read from `pipe` into the log files of `log`, line by line, until the
+/// pipe is closed. Lines longer than `MAX_LINE_LENGTH` are written as they come, in pieces,
+/// instead of being held in memory until their line break shows up.
+fn copy_to_log<R: Read>(pipe: R, log: &mut LogWriter) {
+    let mut reader = BufReader::new(pipe);
+    let mut line = Vec::new();
+
+    loop {
+        line.clear();
+
+        match reader.by_ref().take(MAX_LINE_LENGTH).read_until(b'\n', &mut line) {
+            Ok(0) => break,
+            Ok(_) => {
+                if let Err(err) = log.write_line(&line) {
+                    error!("failed to write to log file {}: {}", log.path().display(), err);
+                }
+            }
+            Err(err) => {
+                error!("failed to read output for {}: {}", log.path().display(), err);
+                break;
+            }
+        }
+    }
+}
+
+/// size of the blocks read from the end of a log file when looking for its last lines
+const TAIL_BLOCK_SIZE: u64 = 8 * 1024;
+
+/// returns the last `count` lines of the log file at `path` (less if it doesn't have that
+/// many), without the trailing line breaks. A missing file has no lines.
+pub fn tail_lines(path: &Path, count: usize) -> Result<Vec<String>> {
+    if count == 0 || !path.exists() {
+        return Ok(vec![]);
+    }
+
+    let mut file = File::open(path)?;
+    let mut pos = file.metadata()?.len();
+    let mut buf: Vec<u8> = Vec::new();
+
+    // read blocks backwards until we have enough line breaks (one more than `count`, the
+    // last line of the file usually ends with one too)
+    while pos > 0 && buf.iter().filter(|&&byte| byte == b'\n').count() <= count {
+        let size = TAIL_BLOCK_SIZE.min(pos);
+        pos -= size;
+
+        let mut block = vec![0u8; size as usize];
+        file.seek(SeekFrom::Start(pos))?;
+        file.read_exact(&mut block)?;
+
+        block.extend_from_slice(&buf);
+        buf = block;
+    }
+
+    if buf.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let text = String::from_utf8_lossy(&buf);
+    let lines: Vec<&str> = text.strip_suffix('\n').unwrap_or(&text).split('\n').collect();
+
+    // the first line might be incomplete if we didn't read the whole file
+    let skip = lines.len().saturating_sub(count);
+
+    Ok(lines[skip..].iter().map(|line| line.trim_end_matches('\r').to_string()).collect())
+}
+
+/// follows a log file as it's written to, like `tail -f`.
+pub struct LogFollower {
+    path: PathBuf,
+    offset: u64,
+    partial: Vec<u8>,
+}
+
+impl LogFollower {
+    /// starts following the log file at `path` from its current end
+    pub fn new(path: &Path) -> LogFollower {
+        LogFollower {
+            path: path.to_path_buf(),
+            offset: fs::metadata(path).map(|metadata| metadata.len()).unwrap_or(0),
+            partial: vec![],
+        }
+    }
+
+    /// returns the lines completed since the last call. If the file shrank (it was rotated
+    /// or truncated), it starts over from the beginning of the new file.
+    pub fn read_lines(&mut self) -> Result<Vec<String>> {
+        let len = match fs::metadata(&self.path) {
+            Ok(metadata) => metadata.len(),
+            // the file might be being rotated right now
+            Err(_) => return Ok(vec![]),
+        };
+
+        if len < self.offset {
+            self.offset = 0;
+            self.partial.clear();
+        }
+
+        if len == self.offset {
+            return Ok(vec![]);
+        }
+
+        let mut file = File::open(&self.path)?;
+        file.seek(SeekFrom::Start(self.offset))?;
+
+        let mut read = Vec::new();
+        file.take(len - self.offset).read_to_end(&mut read)?;
+        self.offset += read.len() as u64;
+        self.partial.extend_from_slice(&read);
+
+        // keep the last incomplete line for the next call
+        let Some(last_break) = self.partial.iter().rposition(|&byte| byte == b'\n') else {
+            return Ok(vec![]);
+        };
+
+        let complete: Vec<u8> = self.partial.drain(..=last_break).collect();
+        let text = String::from_utf8_lossy(&complete[..last_break]);
+
+        Ok(text.split('\n').map(|line| line.trim_end_matches('\r').to_string()).collect())
+    }
+}
+
+/// changes the owner of a file, if one is given
+#[cfg(unix)]
+fn chown(path: &Path, owner: Option<(u32, u32)>) -> Result<()> {
+    if let Some((uid, gid)) = owner {
+        std::os::unix::fs::chown(path, Some(uid), Some(gid))
+            .map_err(|err| anyhow!("failed to change the owner of {}: {}", path.display(), err))?;
+    }
+
+    Ok(())
+}
+
+/// files have no (unix) owners elsewhere
+#[cfg(not(unix))]
+fn chown(_path: &Path, _owner: Option<(u32, u32)>) -> Result<()> {
+    Ok(())
 }
 
 /// creates a file or opens it for appending if it already exists
@@ -54,22 +557,6 @@ fn stdio_filename(task_name: &str, file_type: StdioFile) -> String {
         + match file_type {
             StdioFile::Stdout => ".stdout",
             StdioFile::Stderr => ".stderr",
+            StdioFile::Combined => ".log",
         }
 }
-
-/// Remove the the log files of a task.
-pub fn clean_log_handles(task_name: &String, path: &Path) {
-    let (stdout_path, stderr_path) = get_log_paths(task_name, path);
-
-    if stdout_path.exists()
-        && let Err(err) = std::fs::remove_file(stdout_path)
-    {
-        error!("Failed to remove stdout file for task {task_name} with error {err:?}");
-    };
-
-    if stderr_path.exists()
-        && let Err(err) = std::fs::remove_file(stderr_path)
-    {
-        error!("Failed to remove stderr file for task {task_name} with error {err:?}");
-    };
-}
@@ -1,18 +1,22 @@
 // shamelessly stolen from the pueue project (original name Children)
-// it doesn't provide any advantage over using a simple HashMap or BTreeMap, for now
-// but it might come in handy later on, if we want to implement running multiple workers/
-// instances of an egg at the same time (like a cluster)
+// each egg gets its own group, named after the egg, in which every instance of the egg
+// (see `Egg::instances`) is a worker identified by its instance index.
 
 use {command_group::GroupChild, std::collections::BTreeMap};
 
+/// A pool of workers (children processes) of a single group
+pub type WorkerPool = BTreeMap<String, (usize, GroupChild)>;
+
 /// This structure is needed to manage worker pools for groups.
 /// It's a newtype pattern around a nested BTreeMap, which implements some convenience functions.
 ///
 /// The datastructure represents the following data:
-/// BTreeMap<group_name, BTreeMap<group_worker_id, (egg_name, subprocess_handle)>
-pub struct Workers(pub BTreeMap<String, BTreeMap<String, (usize, GroupChild)>>);
+/// BTreeMap<group_name, BTreeMap<group_worker_id, (egg_id, subprocess_handle)>
+pub struct Workers(pub BTreeMap<String, WorkerPool>);
 
-const DEFAULT_GROUP: &str = "default_kurv";
+/// group of the workers added without one; egg names can't contain slashes (see
+/// `Egg::validate`), so it can't be taken for the group of an egg
+pub const DEFAULT_GROUP: &str = "kurv/default";
 
 impl Default for Workers {
     fn default() -> Self {
@@ -29,26 +33,21 @@ impl Workers {
         Workers(pools)
     }
 
-    /// A convenience function to get a mutable child by its respective task_id.
-    /// We have to do a nested linear search over all children of all pools,
-    /// beceause these datastructure aren't indexed via task_ids.
-    pub fn get_child_mut(&mut self, task_id: usize) -> Option<&mut GroupChild> {
-        for pool in self.0.values_mut() {
-            for (child_task_id, child) in pool.values_mut() {
-                if child_task_id == &task_id {
-                    return Some(child);
-                }
-            }
-        }
+    /// Returns the worker pool of the given group, if it exists and has any worker.
+    pub fn get_group_mut(&mut self, group: &str) -> Option<&mut WorkerPool> {
+        self.0.get_mut(group).filter(|pool| !pool.is_empty())
+    }
 
-        None
+    /// Returns true if the given group exists and has any worker.
+    pub fn has_group(&self, group: &str) -> bool {
+        self.0.get(group).is_some_and(|pool| !pool.is_empty())
     }
 
-    /// Inserts a new children into the worker pool of the given group.
+    /// Inserts a new children into the worker pool of the given group (or the default group
+    /// if `group == None`).
     ///
     /// This function should only be called when spawning a new process.
-    /// At this point, we're sure that the worker pool for the given group already exists, hence
-    /// the expect call.
+    /// The worker pool of the group is created if it doesn't exist yet.
     pub fn add_child(
         &mut self,
         group: Option<&str>,
@@ -57,24 +56,16 @@ impl Workers {
         child: GroupChild,
     ) {
         let group = group.unwrap_or(DEFAULT_GROUP);
-
-        let pool = self
-            .0
-            .get_mut(group)
-            .expect("The worker pool should be initialized when inserting a new child.");
+        let pool = self.0.entry(group.to_string()).or_default();
 
         pool.insert(worker_id, (task_id, child));
     }
 
-    /// Removes a child from the given group (or the default group if `group == None`).
-    pub fn remove_child(&mut self, group: Option<&str>, worker_id: String) {
-        let group = group.unwrap_or(DEFAULT_GROUP);
-
-        let pool = self
-            .0
-            .get_mut(group)
-            .expect("The worker pool should be initialized when removing a child.");
-
-        pool.remove(&worker_id);
+    /// Removes the given group if it has no children left (the default group is never
+    /// removed).
+    pub fn remove_group_if_empty(&mut self, group: &str) {
+        if group != DEFAULT_GROUP && self.0.get(group).is_some_and(|pool| pool.is_empty()) {
+            self.0.remove(group);
+        }
     }
 }
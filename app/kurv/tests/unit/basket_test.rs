@@ -0,0 +1,87 @@
+use kurv::kurv::{Basket, Egg};
+
+#[test]
+fn test_basket_applies_defaults() {
+    let basket = Basket::parse(
+        "defaults:\n  cwd: /srv/app\n  env:\n    NODE_ENV: production\n    PORT: 3000\neggs:\n  - name: api\n    command: node\n    env:\n      PORT: 8080\n  - name: worker\n    command: node\n    cwd: /srv/worker\n",
+    )
+    .unwrap();
+
+    assert_eq!(basket.eggs.len(), 2);
+
+    let api = &basket.eggs[0];
+    assert_eq!(api.name, "api");
+    assert_eq!(api.cwd.as_ref().unwrap().to_str(), Some("/srv/app"));
+
+    // env is merged key by key, the egg wins
+    let env = api.env.as_ref().unwrap();
+    assert_eq!(env.get("NODE_ENV").map(String::as_str), Some("production"));
+    assert_eq!(env.get("PORT").map(String::as_str), Some("8080"));
+
+    let worker = &basket.eggs[1];
+    assert_eq!(worker.cwd.as_ref().unwrap().to_str(), Some("/srv/worker"));
+    assert_eq!(worker.env.as_ref().unwrap().get("PORT").map(String::as_str), Some("3000"));
+}
+
+#[test]
+fn test_basket_keeps_string_scalars() {
+    let basket =
+        Basket::parse("eggs:\n  - name: api\n    command: node\n    env:\n      DEBUG: yes\n")
+            .unwrap();
+
+    let env = basket.eggs[0].env.as_ref().unwrap();
+    assert_eq!(env.get("DEBUG").map(String::as_str), Some("yes"));
+}
+
+#[test]
+fn test_basket_rejects_duplicated_names() {
+    let result = Basket::parse(
+        "eggs:\n  - name: api\n    command: node\n  - name: api\n    command: deno\n",
+    );
+
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_basket_without_defaults() {
+    let basket = Basket::parse("eggs:\n  - name: api\n    command: node\n    id: 7\n").unwrap();
+
+    assert_eq!(basket.eggs[0].command, "node");
+    assert!(basket.eggs[0].id.is_none());
+}
+
+#[test]
+fn test_basket_dependency_cycle() {
+    // neither egg exists yet, so the server would only see the cycle when collecting the second
+    let basket = Basket::parse(
+        "eggs:\n  - name: api\n    command: node\n    depends_on: [worker]\n  - name: worker\n    command: node\n    depends_on: [api]\n",
+    )
+    .unwrap();
+
+    let problems = basket.check_dependencies(&[]);
+    assert_eq!(problems.len(), 2);
+    assert_eq!(problems[0].0, "api");
+    assert_eq!(problems[0].1.field, "depends_on");
+    assert!(problems[0].1.is_error());
+    assert!(problems[0].1.message.contains("api -> worker -> api"));
+    assert_eq!(problems[1].0, "worker");
+}
+
+#[test]
+fn test_basket_unknown_dependency() {
+    let basket =
+        Basket::parse("eggs:\n  - name: api\n    command: node\n    depends_on: [db, cache]\n")
+            .unwrap();
+
+    // eggs already in the server count
+    let db = Egg {
+        name: "db".to_string(),
+        command: "postgres".to_string(),
+        ..Default::default()
+    };
+
+    let problems = basket.check_dependencies(&[db]);
+    assert_eq!(problems.len(), 1);
+    assert_eq!(problems[0].0, "api");
+    assert!(problems[0].1.message.contains("cache"));
+}
@@ -0,0 +1,78 @@
+use {
+    indoc::indoc,
+    kurv::{
+        common::size::ByteSize,
+        kurv::{
+            Egg,
+            egg::cgroup::{CpuMax, EggCgroup},
+        },
+    },
+};
+
+#[test]
+fn test_parse_cgroup() {
+    let egg: Egg = serde_saphyr::from_str(indoc! {"
+        name: api
+        command: node
+        cgroup:
+          memory_max: 512M
+          cpu_max: 1.5
+          pids_max: 64
+    "})
+    .unwrap();
+
+    let cgroup = egg.cgroup();
+    assert_eq!(cgroup.memory_max, Some(ByteSize(512 * 1024 * 1024)));
+    assert_eq!(cgroup.cpu_max, Some(CpuMax(1500)));
+    assert_eq!(cgroup.pids_max, Some(64));
+
+    assert_eq!(
+        cgroup.files(),
+        vec![
+            ("memory.max", Some("536870912".to_string())),
+            ("cpu.max", Some("150000 100000".to_string())),
+            ("pids.max", Some("64".to_string())),
+        ]
+    );
+
+    assert_eq!(
+        cgroup.describe(),
+        vec![
+            ("memory", "512M".to_string()),
+            ("cpus", "1.5".to_string()),
+            ("pids", "64".to_string()),
+        ]
+    );
+
+    // the ones that aren't set are reset
+    assert!(EggCgroup::default().files().iter().all(|(_, value)| value.is_none()));
+}
+
+#[test]
+fn test_cpu_max() {
+    let parse = |value: &str| serde_json::from_str::<CpuMax>(value);
+
+    assert_eq!(parse("2").unwrap(), CpuMax(2000));
+    assert_eq!(parse("0.25").unwrap(), CpuMax(250));
+    assert_eq!(parse("\"1.5\"").unwrap(), CpuMax(1500));
+    assert_eq!(parse("\"50%\"").unwrap(), CpuMax(500));
+    assert!(parse("\"-1\"").is_err());
+    assert!(parse("\"lots\"").is_err());
+
+    assert_eq!(CpuMax(500).quota(), "50000 100000");
+    assert_eq!(serde_json::to_string(&CpuMax(1500)).unwrap(), "1.5");
+}
+
+#[test]
+fn test_validate_cgroup() {
+    let cgroup = EggCgroup {
+        memory_max: Some(ByteSize(0)),
+        cpu_max: Some(CpuMax(5)),
+        pids_max: Some(0),
+    };
+
+    let fields: Vec<String> = cgroup.validate().into_iter().map(|problem| problem.field).collect();
+    assert_eq!(fields, vec!["cgroup.memory_max", "cgroup.cpu_max", "cgroup.pids_max"]);
+
+    assert!(EggCgroup::default().validate().is_empty());
+}
@@ -0,0 +1,181 @@
+use {
+    kurv::{
+        common::tcp::Severity,
+        kurv::{
+            Egg,
+            egg::check::{EnvKeys, resolve_command},
+        },
+    },
+    std::{collections::HashMap, ffi::OsString, fs, path::Path},
+    tempfile::TempDir,
+};
+
+#[cfg(unix)]
+fn write_executable(path: &Path) {
+    use std::os::unix::fs::PermissionsExt;
+
+    fs::write(path, "#!/bin/sh\n").unwrap();
+    fs::set_permissions(path, fs::Permissions::from_mode(0o755)).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn test_resolve_command_in_path() {
+    let dir = TempDir::new().unwrap();
+    let bin = dir.path().join("bin");
+    fs::create_dir(&bin).unwrap();
+    write_executable(&bin.join("serve"));
+
+    let path_var = Some(OsString::from(format!("/nonexistent:{}", bin.display())));
+    let resolved = resolve_command("serve", Path::new("/"), path_var);
+
+    assert_eq!(resolved, Some(bin.join("serve")));
+    assert_eq!(resolve_command("serve", Path::new("/"), None), None);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_resolve_command_relative_to_cwd() {
+    let dir = TempDir::new().unwrap();
+    write_executable(&dir.path().join("run.sh"));
+    fs::write(dir.path().join("data.txt"), "not executable").unwrap();
+
+    assert_eq!(resolve_command("./run.sh", dir.path(), None), Some(dir.path().join("./run.sh")));
+    assert_eq!(resolve_command("./data.txt", dir.path(), None), None);
+    assert_eq!(resolve_command("./missing.sh", dir.path(), None), None);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_egg_check() {
+    let dir = TempDir::new().unwrap();
+    let bin = dir.path().join("bin");
+    fs::create_dir(&bin).unwrap();
+    write_executable(&bin.join("serve"));
+
+    // the PATH of the egg is used to find its command
+    let egg = Egg {
+        name: "api".to_string(),
+        command: "serve".to_string(),
+        env: Some(HashMap::from([("PATH".to_string(), bin.display().to_string())])),
+        ..Default::default()
+    };
+
+    let check = egg.check(dir.path());
+    assert_eq!(check.cwd, dir.path());
+    assert_eq!(check.command, Some(bin.join("serve")));
+    assert!(check.problems.is_empty());
+}
+
+#[test]
+fn test_egg_check_problems() {
+    let egg = Egg {
+        name: "api".to_string(),
+        command: "surely-not-a-command-kurv".to_string(),
+        cwd: Some("/surely/not/a/dir".into()),
+        ..Default::default()
+    };
+
+    let check = egg.check(Path::new("/"));
+    assert_eq!(check.command, None);
+    assert_eq!(check.problems.len(), 2);
+}
+
+#[test]
+fn test_egg_validate() {
+    let egg = Egg {
+        name: "api".to_string(),
+        command: "node".to_string(),
+        ..Default::default()
+    };
+
+    assert!(egg.validate().is_empty());
+}
+
+#[test]
+fn test_egg_validate_problems() {
+    let egg = Egg {
+        name: "my api".to_string(),
+        command: " ".to_string(),
+        cwd: Some("relative/dir".into()),
+        env: Some(HashMap::from([("A=B".to_string(), "c".to_string())])),
+        depends_on: Some(vec!["db".to_string(), "db".to_string(), "my api".to_string()]),
+        ..Default::default()
+    };
+
+    let problems: Vec<(String, Severity)> =
+        egg.validate().into_iter().map(|problem| (problem.field, problem.severity)).collect();
+
+    assert_eq!(
+        problems,
+        vec![
+            ("name".to_string(), Severity::Error),
+            ("command".to_string(), Severity::Error),
+            ("cwd".to_string(), Severity::Warning),
+            ("env.A=B".to_string(), Severity::Error),
+            ("depends_on.1".to_string(), Severity::Warning),
+            ("depends_on.2".to_string(), Severity::Error),
+        ]
+    );
+}
+
+#[test]
+fn test_egg_validate_numeric_name() {
+    let egg = Egg {
+        name: "42".to_string(),
+        command: "node".to_string(),
+        ..Default::default()
+    };
+
+    let problems = egg.validate();
+    assert_eq!(problems.len(), 1);
+    assert_eq!(problems[0].field, "name");
+    assert!(problems[0].is_error());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_egg_run_as() {
+    let egg = |user: Option<&str>, group: Option<&str>| Egg {
+        name: "api".to_string(),
+        command: "node".to_string(),
+        user: user.map(str::to_string),
+        group: group.map(str::to_string),
+        ..Default::default()
+    };
+
+    assert_eq!(egg(None, None).run_as().unwrap(), None);
+
+    let problem = egg(Some("no-such-user-kurv"), None).run_as().unwrap_err();
+    assert_eq!(problem.field, "user");
+    assert_eq!(problem.message, "user no-such-user-kurv does not exist");
+
+    let problem = egg(None, Some("no-such-group-kurv")).run_as().unwrap_err();
+    assert_eq!(problem.field, "group");
+
+    let problems = egg(Some(" "), None).validate();
+    assert_eq!(problems.len(), 1);
+    assert_eq!(problems[0].field, "user");
+}
+
+#[test]
+fn test_duplicate_env_keys() {
+    let yaml = "name: api\ncommand: node\nenv:\n  PORT: 80\n  HOST: a\n  PORT: 81\n  PORT: 82\n";
+    let (egg, problems) = Egg::parse(yaml).unwrap();
+
+    assert_eq!(egg.env.unwrap()["PORT"], "82");
+    assert_eq!(problems.len(), 1);
+    assert_eq!(problems[0].field, "env.PORT");
+    assert!(problems[0].is_error());
+
+    // other fields still can't be repeated
+    assert!(Egg::parse("name: api\nname: web\ncommand: node\n").is_err());
+
+    let json = r#"{"name": "api", "command": "node", "env": {"HOST": "a", "HOST": "b"}}"#;
+    let problems = serde_json::from_str::<EnvKeys>(json).unwrap().problems();
+    assert_eq!(problems.len(), 1);
+    assert_eq!(problems[0].field, "env.HOST");
+
+    let json = r#"{"name": "api", "command": "node", "env": null}"#;
+    assert!(serde_json::from_str::<EnvKeys>(json).unwrap().problems().is_empty());
+}
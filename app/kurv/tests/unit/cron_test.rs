@@ -0,0 +1,64 @@
+use {
+    chrono::{DateTime, Local, NaiveDateTime, TimeZone},
+    kurv::common::cron::CronExpr,
+};
+
+fn at(time: &str) -> DateTime<Local> {
+    let naive = NaiveDateTime::parse_from_str(time, "%Y-%m-%d %H:%M:%S").unwrap();
+    Local.from_local_datetime(&naive).unwrap()
+}
+
+/// returns the next match of `expr` after `time`, as a local time string
+fn next(expr: &str, time: &str) -> Option<String> {
+    let cron: CronExpr = expr.parse().unwrap();
+    cron.next_after(at(time)).map(|next| next.format("%Y-%m-%d %H:%M").to_string())
+}
+
+#[test]
+fn test_cron_next_after() {
+    let next_of = |expr| next(expr, "2025-01-15 10:20:30");
+
+    assert_eq!(next_of("* * * * *").as_deref(), Some("2025-01-15 10:21"));
+    assert_eq!(next_of("0 3 * * *").as_deref(), Some("2025-01-16 03:00"));
+    assert_eq!(next_of("*/15 * * * *").as_deref(), Some("2025-01-15 10:30"));
+    assert_eq!(next_of("5,50 10 * * *").as_deref(), Some("2025-01-15 10:50"));
+    assert_eq!(next_of("0 9-17/4 * * *").as_deref(), Some("2025-01-15 13:00"));
+    assert_eq!(next_of("0 0 1 * *").as_deref(), Some("2025-02-01 00:00"));
+    assert_eq!(next_of("@yearly").as_deref(), Some("2026-01-01 00:00"));
+
+    // 2025-01-15 is a wednesday
+    assert_eq!(next_of("0 8 * * mon-fri").as_deref(), Some("2025-01-16 08:00"));
+    assert_eq!(next_of("0 8 * * 7").as_deref(), Some("2025-01-19 08:00"));
+    assert_eq!(next_of("@weekly").as_deref(), Some("2025-01-19 00:00"));
+    assert_eq!(next_of("30 6 * jun sun").as_deref(), Some("2025-06-01 06:30"));
+
+    // when both days are restricted, either of them will do
+    assert_eq!(next_of("0 0 20 * fri").as_deref(), Some("2025-01-17 00:00"));
+
+    // the current minute is never a match, even at its very start
+    assert_eq!(next("20 10 * * *", "2025-01-15 10:20:00").as_deref(), Some("2025-01-16 10:20"));
+
+    assert_eq!(next("0 0 29 2 *", "2025-01-15 10:20:30").as_deref(), Some("2028-02-29 00:00"));
+    assert_eq!(next("0 0 30 2 *", "2025-01-15 10:20:30"), None);
+}
+
+#[test]
+fn test_cron_parse() {
+    assert!("0 3 * * *".parse::<CronExpr>().is_ok());
+    assert!("@Daily".parse::<CronExpr>().is_ok());
+
+    assert!("".parse::<CronExpr>().is_err());
+    assert!("* * * *".parse::<CronExpr>().is_err());
+    assert!("* * * * * *".parse::<CronExpr>().is_err());
+    assert!("60 * * * *".parse::<CronExpr>().is_err());
+    assert!("* 24 * * *".parse::<CronExpr>().is_err());
+    assert!("* * 0 * *".parse::<CronExpr>().is_err());
+    assert!("*/0 * * * *".parse::<CronExpr>().is_err());
+    assert!("10-5 * * * *".parse::<CronExpr>().is_err());
+    assert!("* * * * someday".parse::<CronExpr>().is_err());
+
+    // it's shown and saved as written
+    let cron: CronExpr = " */5 * * * MON ".parse().unwrap();
+    assert_eq!(cron.to_string(), "*/5 * * * MON");
+    assert_eq!(serde_json::to_string(&cron).unwrap(), "\"*/5 * * * MON\"");
+}
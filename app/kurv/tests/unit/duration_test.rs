@@ -0,0 +1,151 @@
+use {
+    kurv::{
+        common::duration::HumanDuration,
+        kurv::{Egg, EggRestart, egg::restart::EggBackoff},
+    },
+    std::time::Duration,
+};
+
+#[test]
+fn test_human_duration_parse() {
+    assert_eq!("500ms".parse::<HumanDuration>().unwrap().0, Duration::from_millis(500));
+    assert_eq!("30s".parse::<HumanDuration>().unwrap().0, Duration::from_secs(30));
+    assert_eq!("2m".parse::<HumanDuration>().unwrap().0, Duration::from_secs(120));
+    assert_eq!("1h".parse::<HumanDuration>().unwrap().0, Duration::from_secs(3600));
+    assert_eq!("1d".parse::<HumanDuration>().unwrap().0, Duration::from_secs(86400));
+    assert_eq!("250".parse::<HumanDuration>().unwrap().0, Duration::from_millis(250));
+
+    assert!("".parse::<HumanDuration>().is_err());
+    assert!("5x".parse::<HumanDuration>().is_err());
+    assert!("s".parse::<HumanDuration>().is_err());
+
+    // too long to be represented, instead of overflowing
+    assert!("99999999999999999d".parse::<HumanDuration>().is_err());
+    assert!("99999999999999999999".parse::<HumanDuration>().is_err());
+}
+
+#[test]
+fn test_human_duration_display() {
+    assert_eq!(HumanDuration::from_millis(1500).to_string(), "1500ms");
+    assert_eq!(HumanDuration::from_secs(45).to_string(), "45s");
+    assert_eq!(HumanDuration::from_secs(120).to_string(), "2m");
+    assert_eq!(HumanDuration::from_secs(7200).to_string(), "2h");
+}
+
+#[test]
+fn test_human_duration_serde() {
+    let from_number: HumanDuration = serde_json::from_str("1500").unwrap();
+    assert_eq!(from_number, HumanDuration::from_millis(1500));
+
+    let from_string: HumanDuration = serde_json::from_str("\"10s\"").unwrap();
+    assert_eq!(from_string, HumanDuration::from_secs(10));
+
+    assert_eq!(serde_json::to_string(&from_string).unwrap(), "\"10s\"");
+}
+
+#[test]
+fn test_backoff_delay_grows_and_caps() {
+    let backoff = EggBackoff {
+        initial_delay: HumanDuration::from_secs(1),
+        multiplier: 3.0,
+        max_delay: HumanDuration::from_secs(20),
+        jitter: 0,
+    };
+
+    assert_eq!(backoff.delay(1).num_seconds(), 1);
+    assert_eq!(backoff.delay(2).num_seconds(), 3);
+    assert_eq!(backoff.delay(3).num_seconds(), 9);
+    assert_eq!(backoff.delay(4).num_seconds(), 20);
+    assert_eq!(backoff.delay(100).num_seconds(), 20);
+
+    let backoff = EggBackoff {
+        multiplier: 1.5,
+        ..Default::default()
+    };
+
+    assert_eq!(backoff.delay(2).num_milliseconds(), 1500);
+    assert_eq!(backoff.delay(3).num_milliseconds(), 2250);
+}
+
+#[test]
+fn test_backoff_multiplier_validation() {
+    let egg = |multiplier| Egg {
+        name: "test".to_string(),
+        command: "echo".to_string(),
+        restart: Some(EggRestart {
+            backoff: Some(EggBackoff {
+                multiplier,
+                ..Default::default()
+            }),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+
+    let problems = egg(0.5).validate();
+    assert_eq!(problems.len(), 1);
+    assert_eq!(problems[0].field, "restart.backoff.multiplier");
+    assert!(problems[0].is_error());
+
+    assert!(egg(f64::NAN).validate().iter().any(|problem| problem.is_error()));
+    assert!(egg(1.0).validate().is_empty());
+}
+
+#[test]
+fn test_backoff_jitter_stays_in_range() {
+    let backoff = EggBackoff {
+        initial_delay: HumanDuration::from_secs(10),
+        jitter: 20,
+        ..Default::default()
+    };
+
+    for _ in 0..50 {
+        let delay = backoff.delay(1).num_milliseconds();
+        assert!((8000..=12000).contains(&delay), "delay out of range: {delay}");
+    }
+}
+
+#[test]
+fn test_errored_egg_waits_for_backoff() {
+    let mut egg = Egg {
+        name: "test".to_string(),
+        command: "echo".to_string(),
+        restart: Some(EggRestart {
+            backoff: Some(EggBackoff {
+                initial_delay: HumanDuration::from_secs(60),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+
+    egg.set_as_errored("Exited with code 1".to_string());
+    assert!(egg.state.as_ref().unwrap().next_attempt_at.is_some());
+    assert!(!egg.is_backoff_elapsed());
+    assert!(!egg.should_spawn());
+
+    // a manual restart clears the backoff
+    egg.reset_state();
+    assert!(egg.state.as_ref().unwrap().next_attempt_at.is_none());
+    assert!(egg.should_spawn());
+}
+
+#[test]
+fn test_default_backoff() {
+    // without a restart config, a crashing egg still waits between attempts
+    let mut egg = Egg {
+        name: "test".to_string(),
+        command: "echo".to_string(),
+        ..Default::default()
+    };
+
+    egg.set_as_errored("Exited with code 1".to_string());
+    assert!(!egg.is_backoff_elapsed());
+    assert!(!egg.should_spawn());
+
+    let backoff = egg.restart_config().backoff();
+    assert_eq!(backoff.delay(1).num_seconds(), 1);
+    assert_eq!(backoff.delay(2).num_seconds(), 2);
+    assert_eq!(backoff.delay(10).num_seconds(), 60);
+}
@@ -1,6 +1,13 @@
 use {
     chrono::Local,
-    kurv::kurv::{Egg, EggStateUpsert, EggStatus},
+    kurv::{
+        common::duration::HumanDuration,
+        kurv::{
+            ConfigChange, Egg, EggRestart, EggStateUpsert, EggStatus, RestartPolicy,
+            egg::{StopSignal, restart::EggBackoff},
+        },
+    },
+    std::collections::HashMap,
 };
 
 #[test]
@@ -9,13 +16,7 @@ fn test_egg_state_transitions() {
         name: "test".to_string(),
         command: "echo".to_string(),
         id: Some(1),
-        state: None,
-        args: None,
-        cwd: None,
-        env: None,
-        paths: None,
-        plugin: None,
-        plugin_path: None,
+        ..Default::default()
     };
 
     // initially should have no state
@@ -40,13 +41,7 @@ fn test_egg_should_spawn() {
         name: "test".to_string(),
         command: "echo".to_string(),
         id: Some(1),
-        state: None,
-        args: None,
-        cwd: None,
-        env: None,
-        paths: None,
-        plugin: None,
-        plugin_path: None,
+        ..Default::default()
     };
 
     // new eggs without state should spawn
@@ -75,13 +70,7 @@ fn test_egg_upsert_state() {
         name: "test".to_string(),
         command: "echo".to_string(),
         id: Some(1),
-        state: None,
-        args: None,
-        cwd: None,
-        env: None,
-        paths: None,
-        plugin: None,
-        plugin_path: None,
+        ..Default::default()
     };
 
     // upsert on egg without state should create state
@@ -120,13 +109,7 @@ fn test_egg_set_as_errored() {
         name: "test".to_string(),
         command: "echo".to_string(),
         id: Some(1),
-        state: None,
-        args: None,
-        cwd: None,
-        env: None,
-        paths: None,
-        plugin: None,
-        plugin_path: None,
+        ..Default::default()
     };
 
     // set as running first
@@ -147,13 +130,7 @@ fn test_egg_status_checks() {
         name: "test".to_string(),
         command: "echo".to_string(),
         id: Some(1),
-        state: None,
-        args: None,
-        cwd: None,
-        env: None,
-        paths: None,
-        plugin: None,
-        plugin_path: None,
+        ..Default::default()
     };
 
     egg.set_status(EggStatus::Running);
@@ -179,13 +156,7 @@ fn test_egg_reset_state() {
         name: "test".to_string(),
         command: "echo".to_string(),
         id: Some(1),
-        state: None,
-        args: None,
-        cwd: None,
-        env: None,
-        paths: None,
-        plugin: None,
-        plugin_path: None,
+        ..Default::default()
     };
 
     // set as running with some state
@@ -208,13 +179,7 @@ fn test_egg_increment_and_reset_try_count() {
         name: "test".to_string(),
         command: "echo".to_string(),
         id: Some(1),
-        state: None,
-        args: None,
-        cwd: None,
-        env: None,
-        paths: None,
-        plugin: None,
-        plugin_path: None,
+        ..Default::default()
     };
 
     egg.set_status(EggStatus::Pending);
@@ -229,3 +194,370 @@ fn test_egg_increment_and_reset_try_count() {
     egg.reset_try_count();
     assert_eq!(egg.state.as_ref().unwrap().try_count, 0);
 }
+
+#[test]
+fn test_egg_restart_policy_on_failure() {
+    let egg = Egg {
+        name: "test".to_string(),
+        command: "echo".to_string(),
+        restart: Some(EggRestart {
+            policy: RestartPolicy::OnFailure,
+            success_codes: Some(vec![0, 3]),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+
+    assert!(!egg.should_restart_on_exit(Some(0)));
+    assert!(!egg.should_restart_on_exit(Some(3)));
+    assert!(egg.should_restart_on_exit(Some(1)));
+
+    // killed by a signal, no exit code
+    assert!(egg.should_restart_on_exit(None));
+}
+
+#[test]
+fn test_egg_restart_policy_never() {
+    let mut egg = Egg {
+        name: "test".to_string(),
+        command: "echo".to_string(),
+        restart: Some(EggRestart {
+            policy: RestartPolicy::Never,
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+
+    assert!(!egg.should_restart_on_exit(Some(0)));
+    assert!(!egg.should_restart_on_exit(Some(1)));
+
+    // an errored egg with a `never` policy is never re-spawned
+    egg.set_as_errored("Exited with code 1".to_string());
+    assert!(!egg.should_spawn());
+}
+
+#[test]
+fn test_egg_restart_max_retries() {
+    let mut egg = Egg {
+        name: "test".to_string(),
+        command: "echo".to_string(),
+        restart: Some(EggRestart {
+            max_retries: Some(2),
+            // retried right away
+            backoff: Some(EggBackoff {
+                initial_delay: HumanDuration::from_secs(0),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+
+    // first and second failures are retried
+    egg.set_as_errored("Exited with code 1".to_string());
+    assert!(egg.should_spawn());
+    egg.set_as_errored("Exited with code 1".to_string());
+    assert!(egg.should_spawn());
+
+    // third failure exhausts the retries
+    egg.set_as_errored("Exited with code 1".to_string());
+    assert!(!egg.can_retry());
+    assert!(!egg.should_spawn());
+
+    // a restart gives the egg a fresh set of retries
+    egg.reset_state();
+    assert!(egg.should_spawn());
+    assert!(egg.can_retry());
+}
+
+#[test]
+fn test_egg_set_as_finished() {
+    let mut egg = Egg {
+        name: "test".to_string(),
+        command: "echo".to_string(),
+        ..Default::default()
+    };
+
+    egg.set_as_running(1234);
+    egg.set_as_finished("Exited with code 0".to_string());
+
+    let state = egg.state.as_ref().unwrap();
+    assert_eq!(state.status, EggStatus::Stopped);
+    assert_eq!(state.pid, 0);
+    assert_eq!(state.error, Some("Exited with code 0".to_string()));
+}
+
+#[test]
+fn test_egg_restart_config_parsing() {
+    let egg: Egg = serde_saphyr::from_str(
+        "name: migrate\ncommand: ./migrate\nrestart:\n  policy: on-failure\n  max_retries: 3\n",
+    )
+    .unwrap();
+
+    let restart = egg.restart.unwrap();
+    assert_eq!(restart.policy, RestartPolicy::OnFailure);
+    assert_eq!(restart.max_retries, Some(3));
+    assert_eq!(restart.success_codes, None);
+}
+
+#[test]
+fn test_egg_stopping_transitions() {
+    let mut egg = Egg {
+        name: "test".to_string(),
+        command: "echo".to_string(),
+        ..Default::default()
+    };
+
+    egg.set_as_running(1234);
+    egg.set_status(EggStatus::Stopped);
+
+    // asking the process to stop keeps the target status around
+    egg.set_as_stopping();
+    assert!(egg.is_stopping());
+    assert_eq!(egg.state.as_ref().unwrap().stop_target, Some(EggStatus::Stopped));
+    assert!(!egg.has_stop_timed_out());
+
+    // a remove while stopping only changes the target
+    egg.set_stop_target(EggStatus::PendingRemoval);
+    egg.finish_stopping();
+
+    let state = egg.state.as_ref().unwrap();
+    assert_eq!(state.status, EggStatus::PendingRemoval);
+    assert_eq!(state.pid, 0);
+    assert!(state.stop_target.is_none());
+    assert!(state.stopping_since.is_none());
+}
+
+#[test]
+fn test_egg_stopping_for_restart() {
+    let mut egg = Egg {
+        name: "test".to_string(),
+        command: "echo".to_string(),
+        kill_timeout: Some("0s".parse().unwrap()),
+        ..Default::default()
+    };
+
+    egg.set_as_running(1234);
+    egg.set_status(EggStatus::Restarting);
+    egg.set_as_stopping();
+    assert!(egg.has_stop_timed_out());
+
+    // once stopped, a restarting egg is pending to be spawned again
+    egg.finish_stopping();
+    assert!(egg.is_in_status(EggStatus::Pending));
+    assert!(egg.should_spawn());
+}
+
+#[test]
+fn test_egg_stop_config_parsing() {
+    let egg: Egg = serde_saphyr::from_str(
+        "name: api\ncommand: node\nstop_signal: SIGINT\nkill_timeout: 30s\n",
+    )
+    .unwrap();
+
+    assert_eq!(egg.stop_signal(), StopSignal::Int);
+    assert_eq!(egg.kill_timeout().0.as_secs(), 30);
+
+    // short signal names are accepted too, and defaults apply when not set
+    let egg: Egg = serde_saphyr::from_str("name: api\ncommand: node\nstop_signal: HUP\n").unwrap();
+    assert_eq!(egg.stop_signal(), StopSignal::Hup);
+    assert_eq!(egg.kill_timeout().0.as_secs(), 5);
+
+    let egg = Egg::default();
+    assert_eq!(egg.stop_signal(), StopSignal::Term);
+}
+
+#[test]
+fn test_egg_instances_defaults() {
+    let egg: Egg = serde_saphyr::from_str("name: api\ncommand: node\ninstances: 3\n").unwrap();
+    assert_eq!(egg.instance_count(), 3);
+
+    // the first instance keeps the egg's log file names
+    assert_eq!(egg.instance_log_name(0), "api");
+    assert_eq!(egg.instance_log_name(2), "api.2");
+
+    // eggs without instances (or with 0) run a single one
+    let egg = Egg {
+        instances: Some(0),
+        ..Default::default()
+    };
+    assert_eq!(egg.instance_count(), 1);
+}
+
+#[test]
+fn test_egg_instances_to_spawn() {
+    let mut egg = Egg {
+        name: "api".to_string(),
+        command: "node".to_string(),
+        instances: Some(3),
+        ..Default::default()
+    };
+
+    // a new egg spawns all of its instances
+    assert_eq!(egg.instances_to_spawn(), vec![0, 1, 2]);
+
+    egg.set_instance_running(0, 100, None);
+    egg.set_instance_running(1, 101, None);
+    egg.set_instance_running(2, 102, None);
+    egg.set_as_running(egg.first_instance_pid());
+    assert!(egg.instances_to_spawn().is_empty());
+    assert_eq!(egg.state.as_ref().unwrap().pid, 100);
+
+    // a crashed instance is re-spawned while the rest keep running
+    egg.set_instance_errored(0, "Exited with code 1".to_string());
+    assert_eq!(egg.instances_to_spawn(), vec![0]);
+    assert_eq!(egg.first_instance_pid(), 101);
+
+    // scaling up spawns the new instances
+    egg.set_instance_running(0, 103, None);
+    egg.instances = Some(4);
+    assert_eq!(egg.instances_to_spawn(), vec![3]);
+
+    // stopping the egg forgets about its instances
+    egg.set_as_stopped();
+    assert!(egg.state.as_ref().unwrap().instances.is_empty());
+}
+
+#[test]
+fn test_egg_instances_retry_policy() {
+    let mut egg = Egg {
+        name: "api".to_string(),
+        command: "node".to_string(),
+        instances: Some(2),
+        restart: Some(EggRestart {
+            policy: RestartPolicy::Never,
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+
+    egg.set_instance_running(0, 100, None);
+    egg.set_instance_running(1, 101, None);
+    egg.set_as_running(100);
+
+    // errored instances aren't re-spawned when the policy doesn't allow it
+    egg.set_instance_errored(1, "Exited with code 1".to_string());
+    assert!(egg.instances_to_spawn().is_empty());
+    assert!(!egg.all_instances_finished());
+
+    egg.set_instance_finished(0, "Exited with code 0".to_string());
+    egg.set_instance_finished(1, "Exited with code 0".to_string());
+    assert!(egg.all_instances_finished());
+}
+
+#[test]
+fn test_egg_replace_config() {
+    let mut egg = Egg {
+        name: "api".to_string(),
+        command: "python".to_string(),
+        id: Some(7),
+        ..Default::default()
+    };
+    egg.set_as_running(1234);
+
+    let config = Egg {
+        name: "api".to_string(),
+        command: "python3".to_string(),
+        args: Some(vec!["main.py".to_string()]),
+        id: Some(99),
+        ..Default::default()
+    };
+
+    egg.replace_config(config);
+
+    // the config is replaced, but the egg keeps its id and state
+    assert_eq!(egg.command, "python3");
+    assert_eq!(egg.args, Some(vec!["main.py".to_string()]));
+    assert_eq!(egg.id, Some(7));
+    assert_eq!(egg.state.as_ref().unwrap().pid, 1234);
+}
+
+#[test]
+fn test_egg_recollect() {
+    let config = || Egg {
+        name: "api".to_string(),
+        command: "python3".to_string(),
+        ..Default::default()
+    };
+
+    // a running egg is restarted with its new config
+    let mut egg = Egg {
+        name: "api".to_string(),
+        command: "python".to_string(),
+        ..Default::default()
+    };
+    egg.set_as_running(1234);
+    egg.recollect(config());
+    assert_eq!(egg.command, "python3");
+    assert!(egg.is_in_status(EggStatus::Restarting));
+
+    // a stopped egg just gets its new config
+    egg.set_as_stopped();
+    egg.recollect(config());
+    assert!(egg.is_stopped());
+
+    // so does an egg that is being stopped
+    egg.set_as_running(1234);
+    egg.set_status(EggStatus::Stopped);
+    egg.set_as_stopping();
+    egg.recollect(config());
+    egg.finish_stopping();
+    assert!(egg.is_stopped());
+
+    // while an egg stopping for a restart still restarts
+    egg.set_as_running(1234);
+    egg.set_status(EggStatus::Restarting);
+    egg.set_as_stopping();
+    egg.recollect(config());
+    egg.finish_stopping();
+    assert!(egg.is_in_status(EggStatus::Pending));
+}
+
+#[test]
+fn test_egg_config_diff() {
+    let old = Egg {
+        name: "api".to_string(),
+        command: "python".to_string(),
+        args: Some(vec!["main.py".to_string()]),
+        env: Some(HashMap::from([
+            ("PORT".to_string(), "80".to_string()),
+            ("DEBUG".to_string(), "1".to_string()),
+        ])),
+        id: Some(1),
+        ..Default::default()
+    };
+
+    let mut new = Egg {
+        command: "python3".to_string(),
+        args: None,
+        env: Some(HashMap::from([
+            ("PORT".to_string(), "8080".to_string()),
+            ("HOST".to_string(), "0.0.0.0".to_string()),
+        ])),
+        cwd: Some("/srv".into()),
+        id: Some(2),
+        ..old.clone()
+    };
+    new.set_as_running(1);
+
+    let change = |field: &str, old: Option<&str>, new: Option<&str>| ConfigChange {
+        field: field.to_string(),
+        old: old.map(str::to_string),
+        new: new.map(str::to_string),
+    };
+
+    // runtime fields (id, state) are not part of the config
+    assert_eq!(
+        old.config_diff(&new),
+        vec![
+            change("args", Some(r#"["main.py"]"#), None),
+            change("command", Some("python"), Some("python3")),
+            change("cwd", None, Some("/srv")),
+            change("env.DEBUG", Some("1"), None),
+            change("env.HOST", None, Some("0.0.0.0")),
+            change("env.PORT", Some("80"), Some("8080")),
+        ]
+    );
+
+    assert!(old.config_diff(&old).is_empty());
+}
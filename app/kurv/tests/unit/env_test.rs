@@ -0,0 +1,171 @@
+use {
+    indoc::indoc,
+    kurv::{
+        common::dotenv,
+        kurv::{
+            Egg,
+            egg::{InheritEnv, interpolate},
+        },
+    },
+    std::{collections::HashMap, ffi::OsString, fs, path::Path},
+    tempfile::TempDir,
+};
+
+fn vars(name: &str) -> Option<String> {
+    match name {
+        "HOST" => Some("db.local".to_string()),
+        "EMPTY" => Some(String::new()),
+        _ => None,
+    }
+}
+
+#[test]
+fn test_interpolate() {
+    assert_eq!(
+        interpolate("postgres://${HOST}:5432", vars).unwrap(),
+        "postgres://db.local:5432"
+    );
+    assert_eq!(interpolate("${PORT:-8080}", vars).unwrap(), "8080");
+    assert_eq!(interpolate("${EMPTY:-fallback}", vars).unwrap(), "fallback");
+    assert_eq!(interpolate("${EMPTY-fallback}", vars).unwrap(), "");
+    assert_eq!(interpolate("${PORT-}", vars).unwrap(), "");
+    assert_eq!(interpolate("$${HOST} costs $5", vars).unwrap(), "${HOST} costs $5");
+    assert_eq!(interpolate("no references", vars).unwrap(), "no references");
+}
+
+#[test]
+fn test_interpolate_errors() {
+    assert!(interpolate("${PORT}", vars).unwrap_err().contains("PORT is not set"));
+    assert!(interpolate("${HOST", vars).is_err());
+    assert!(interpolate("${}", vars).is_err());
+    assert!(interpolate("${1VAR}", vars).is_err());
+    assert!(interpolate("${HOST:?missing}", vars).is_err());
+}
+
+#[test]
+fn test_dotenv_parse() {
+    let vars = dotenv::parse(indoc! {r#"
+        # database
+        DB_HOST=localhost
+        export DB_PORT = 5432
+        DB_PASS='p@ss #word'
+        GREETING="hello\nworld"
+        EMPTY=
+        NAME=kurv # the name
+    "#})
+    .unwrap();
+
+    let expected: Vec<(String, String)> = [
+        ("DB_HOST", "localhost"),
+        ("DB_PORT", "5432"),
+        ("DB_PASS", "p@ss #word"),
+        ("GREETING", "hello\nworld"),
+        ("EMPTY", ""),
+        ("NAME", "kurv"),
+    ]
+    .iter()
+    .map(|(key, value)| (key.to_string(), value.to_string()))
+    .collect();
+
+    assert_eq!(vars, expected);
+}
+
+#[test]
+fn test_dotenv_parse_errors() {
+    assert!(dotenv::parse("JUST_A_NAME\n").is_err());
+    assert!(dotenv::parse("A B=c\n").is_err());
+    assert!(dotenv::parse("A=\"unclosed\n").is_err());
+}
+
+#[test]
+fn test_egg_resolve() {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join(".env"), "A=from-env-file\nB=from-env-file\n").unwrap();
+    fs::write(dir.path().join(".env.production"), "B=from-production\nC=3\n").unwrap();
+
+    let egg = Egg {
+        name: "api".to_string(),
+        command: "${BIN:-node}".to_string(),
+        args: Some(vec!["--host".to_string(), "${HOST}".to_string()]),
+        cwd: Some(dir.path().to_path_buf()),
+        env: Some(HashMap::from([("C".to_string(), "${HOST}".to_string())])),
+        env_file: Some(vec![".env".into(), ".env.production".into()]),
+        ..Default::default()
+    };
+
+    let resolved = egg.resolve_with(Path::new("/"), vars).unwrap();
+
+    assert_eq!(resolved.command, "node");
+    assert_eq!(resolved.args, vec!["--host", "db.local"]);
+    assert_eq!(resolved.cwd, dir.path());
+
+    // later env files win, and the env of the egg wins over all of them
+    assert_eq!(resolved.env.get("A").map(String::as_str), Some("from-env-file"));
+    assert_eq!(resolved.env.get("B").map(String::as_str), Some("from-production"));
+    assert_eq!(resolved.env.get("C").map(String::as_str), Some("db.local"));
+}
+
+#[test]
+fn test_egg_resolve_problems() {
+    let egg = Egg {
+        name: "api".to_string(),
+        command: "node".to_string(),
+        args: Some(vec!["${MISSING}".to_string()]),
+        ..Default::default()
+    };
+
+    let problem = egg.resolve_with(Path::new("/"), vars).unwrap_err();
+    assert_eq!(problem.field, "args.0");
+
+    let egg = Egg {
+        name: "api".to_string(),
+        command: "node".to_string(),
+        env_file: Some(vec!["surely-not-a-file.env".into()]),
+        ..Default::default()
+    };
+
+    let problem = egg.resolve_with(Path::new("/"), vars).unwrap_err();
+    assert_eq!(problem.field, "env_file.0");
+}
+
+#[test]
+fn test_inherit_env() {
+    let server_env = || {
+        [
+            ("PATH", "/usr/bin"),
+            ("HOME", "/root"),
+            ("LC_ALL", "C"),
+            ("KURV_SERVER", "true"),
+        ]
+        .map(|(key, value)| (OsString::from(key), OsString::from(value)))
+    };
+    let inherited = |yaml: &str| {
+        let egg: Egg = serde_saphyr::from_str(yaml).unwrap();
+        let mut keys: Vec<String> = egg
+            .inherited_env(server_env())
+            .into_iter()
+            .map(|(key, _)| key.to_string_lossy().to_string())
+            .collect();
+        keys.sort();
+        keys
+    };
+
+    assert_eq!(inherited("name: a\ncommand: a\n").len(), 4);
+    assert_eq!(inherited("name: a\ncommand: a\ninherit_env: all\n").len(), 4);
+    assert!(inherited("name: a\ncommand: a\ninherit_env: none\n").is_empty());
+    assert_eq!(
+        inherited("name: a\ncommand: a\ninherit_env: [PATH, lc_*]\n"),
+        vec!["LC_ALL", "PATH"]
+    );
+    assert!(serde_saphyr::from_str::<Egg>("name: a\ncommand: a\ninherit_env: some\n").is_err());
+}
+
+#[test]
+fn test_inherit_env_serialization() {
+    let only = InheritEnv::Only(vec!["PATH".to_string()]);
+
+    assert_eq!(serde_json::to_string(&InheritEnv::None).unwrap(), "\"none\"");
+    assert_eq!(serde_json::to_string(&only).unwrap(), "[\"PATH\"]");
+    assert_eq!(serde_json::from_str::<InheritEnv>("[\"PATH\"]").unwrap(), only);
+    assert_eq!(serde_json::from_str::<InheritEnv>("\"all\"").unwrap(), InheritEnv::All);
+}
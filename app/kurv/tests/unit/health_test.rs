@@ -0,0 +1,227 @@
+use {
+    indoc::indoc,
+    kurv::kurv::{Egg, EggStatus, HealthCheck, HealthStatus, egg::InheritEnv},
+    std::{
+        collections::HashMap,
+        fs,
+        io::{Read, Write},
+        net::TcpListener,
+        path::{Path, PathBuf},
+        thread,
+        time::Duration,
+    },
+    tempfile::TempDir,
+};
+
+fn egg_with_health(health: &str) -> Egg {
+    serde_saphyr::from_str(&format!("name: api\ncommand: node\nhealth:\n{health}")).unwrap()
+}
+
+#[test]
+fn test_health_config_parsing() {
+    let egg = egg_with_health("  http:\n    port: 8080\n    path: /health\n  interval: 5s\n");
+    let health = egg.health.unwrap();
+
+    assert_eq!(
+        health.check,
+        HealthCheck::Http {
+            port: 8080,
+            host: "127.0.0.1".to_string(),
+            path: "/health".to_string(),
+        }
+    );
+    assert_eq!(health.interval.0.as_secs(), 5);
+    assert_eq!(health.timeout.0.as_secs(), 2);
+    assert_eq!(health.failure_threshold, 3);
+
+    let egg = egg_with_health("  exec:\n    command: pg_isready\n  failure_threshold: 1\n");
+    let health = egg.health.unwrap();
+    assert!(
+        matches!(health.check, HealthCheck::Exec { ref command, .. } if command == "pg_isready")
+    );
+    assert_eq!(health.failure_threshold, 1);
+}
+
+#[test]
+fn test_health_failure_threshold() {
+    let mut egg = egg_with_health("  tcp:\n    port: 5432\n  failure_threshold: 2\n");
+
+    // eggs that aren't running are never checked
+    assert!(!egg.is_health_check_due());
+    assert_eq!(egg.health_status(), HealthStatus::Unknown);
+
+    egg.set_as_running(1234);
+
+    assert!(!egg.record_health_check(Ok(())));
+    assert_eq!(egg.health_status(), HealthStatus::Healthy);
+
+    // the egg is restarted once it fails `failure_threshold` consecutive checks
+    assert!(!egg.record_health_check(Err("connection refused".to_string())));
+    assert_eq!(egg.health_status(), HealthStatus::Unhealthy);
+    assert!(egg.record_health_check(Err("connection refused".to_string())));
+
+    // a restart resets the health, but keeps the reason of the failure
+    egg.set_as_running(1235);
+    let health = egg.state.as_ref().unwrap().health.clone().unwrap();
+    assert_eq!(health.status, HealthStatus::Unknown);
+    assert_eq!(health.failures, 0);
+    assert_eq!(health.message.as_deref(), Some("connection refused"));
+}
+
+#[test]
+fn test_health_start_period() {
+    let mut egg =
+        egg_with_health("  tcp:\n    port: 5432\n  failure_threshold: 1\n  start_period: 1m\n");
+    egg.set_as_running(1234);
+
+    // failures while the egg is starting don't count
+    assert!(!egg.record_health_check(Err("connection refused".to_string())));
+    assert_eq!(egg.health_status(), HealthStatus::Unknown);
+}
+
+#[test]
+fn test_health_check_due() {
+    let mut egg = egg_with_health("  tcp:\n    port: 5432\n  interval: 0s\n");
+    egg.set_as_running(1234);
+    assert!(egg.is_health_check_due());
+
+    let mut egg = egg_with_health("  tcp:\n    port: 5432\n  interval: 1h\n");
+    egg.set_as_running(1234);
+
+    // the first check happens one interval after the egg started
+    assert!(!egg.is_health_check_due());
+}
+
+#[test]
+fn test_unhealthy_egg_follows_restart_policy() {
+    let mut egg: Egg = serde_saphyr::from_str(indoc! {"
+        name: api
+        command: node
+        restart:
+          max_retries: 1
+          backoff:
+            initial_delay: 1m
+        health:
+          tcp:
+            port: 5432
+    "})
+    .unwrap();
+    egg.set_as_running(1234);
+
+    // an unhealthy egg counts as a failed try and waits for its backoff
+    egg.set_as_unhealthy("unhealthy: connection refused".to_string());
+    assert!(egg.is_in_status(EggStatus::Errored));
+    assert_eq!(egg.state.as_ref().unwrap().try_count, 1);
+    assert!(!egg.is_backoff_elapsed());
+
+    // once stopped, it stays errored and keeps its try count
+    egg.set_as_stopping();
+    egg.finish_stopping();
+    assert!(egg.is_in_status(EggStatus::Errored));
+    assert_eq!(egg.state.as_ref().unwrap().try_count, 1);
+    assert!(!egg.should_spawn());
+
+    // and its restart policy gives up on it after `max_retries`
+    egg.set_as_running(1235);
+    egg.set_as_unhealthy("unhealthy: connection refused".to_string());
+    assert!(!egg.can_retry());
+}
+
+#[test]
+fn test_http_health_check_in_pieces() {
+    let egg = Egg::default();
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    let server = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut request = [0u8; 1024];
+        let _ = stream.read(&mut request).unwrap();
+
+        for piece in ["HTTP/1.1 2", "04 No Content", "\r\n\r\n"] {
+            stream.write_all(piece.as_bytes()).unwrap();
+            stream.flush().unwrap();
+            thread::sleep(Duration::from_millis(20));
+        }
+    });
+
+    let check = HealthCheck::Http {
+        port,
+        host: "127.0.0.1".to_string(),
+        path: "/health".to_string(),
+    };
+
+    assert!(check.run(&egg, Path::new("."), Duration::from_secs(1)).is_ok());
+    server.join().unwrap();
+}
+
+#[test]
+fn test_health_checks_run() {
+    let egg = Egg::default();
+    let timeout = Duration::from_secs(1);
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let host = "127.0.0.1".to_string();
+
+    assert!(
+        HealthCheck::Tcp {
+            port,
+            host: host.clone()
+        }
+        .run(&egg, Path::new("."), timeout)
+        .is_ok()
+    );
+
+    drop(listener);
+    assert!(HealthCheck::Tcp { port, host }.run(&egg, Path::new("."), timeout).is_err());
+
+    let exec = |command: &str| HealthCheck::Exec {
+        command: command.to_string(),
+        args: vec![],
+    };
+
+    assert!(exec("true").run(&egg, Path::new("."), timeout).is_ok());
+    assert!(exec("false").run(&egg, Path::new("."), timeout).is_err());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_exec_health_check_env() {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join(".env"), "FROM_FILE=yes\n").unwrap();
+
+    let egg = Egg {
+        env_file: Some(vec![PathBuf::from(".env")]),
+        env: Some(HashMap::from([("GREETING".to_string(), "${HOME}!".to_string())])),
+        ..Default::default()
+    };
+
+    // it runs in the same cwd and env as the egg
+    let check = HealthCheck::Exec {
+        command: "sh".to_string(),
+        args: vec![
+            "-c".to_string(),
+            "test \"$FROM_FILE\" = yes && test \"$GREETING\" = \"$HOME!\"".to_string(),
+        ],
+    };
+
+    assert!(check.run(&egg, dir.path(), Duration::from_secs(1)).is_ok());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_exec_health_check_inherit_env() {
+    let egg = Egg {
+        inherit_env: Some(InheritEnv::None),
+        ..Default::default()
+    };
+
+    // it doesn't get more of the env of kurv than the egg
+    let check = HealthCheck::Exec {
+        command: "/bin/sh".to_string(),
+        args: vec!["-c".to_string(), "test -z \"$HOME\"".to_string()],
+    };
+
+    assert!(check.run(&egg, Path::new("."), Duration::from_secs(1)).is_ok());
+}
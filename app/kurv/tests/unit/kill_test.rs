@@ -0,0 +1,61 @@
+use {
+    command_group::{CommandGroup, GroupChild},
+    kurv::{
+        common::Info,
+        kurv::{Egg, EggStatus, Kurv, KurvState},
+    },
+    std::{
+        process::Command,
+        sync::{Arc, Mutex},
+    },
+};
+
+#[cfg(unix)]
+fn sleeper() -> GroupChild {
+    Command::new("sleep").arg("30").group_spawn().unwrap()
+}
+
+#[cfg(unix)]
+#[test]
+fn test_remove_egg_with_stopping_dependents() {
+    let mut state = KurvState {
+        eggs: Default::default(),
+    };
+
+    let db = Egg {
+        name: "db".to_string(),
+        command: "sleep".to_string(),
+        ..Default::default()
+    };
+    let api = Egg {
+        name: "api".to_string(),
+        command: "sleep".to_string(),
+        depends_on: Some(vec!["db".to_string()]),
+        ..Default::default()
+    };
+
+    let db_id = state.collect(&db);
+    let api_id = state.collect(&api);
+    state.get_mut(db_id).unwrap().set_status(EggStatus::PendingRemoval);
+    state.get_mut(api_id).unwrap().set_as_stopping();
+
+    let mut kurv = Kurv::new(Arc::new(Mutex::new(Info::new())), Arc::new(Mutex::new(state)));
+    kurv.workers.add_child(Some("db"), "0".to_string(), db_id, sleeper());
+    kurv.workers.add_child(Some("api"), "0".to_string(), api_id, sleeper());
+
+    kurv.check_stopped_eggs();
+    kurv.check_removal_pending_eggs();
+
+    // db waits for api to stop, it can't be removed while its process is around
+    assert!(kurv.state.lock().unwrap().get(db_id).is_some());
+    assert!(kurv.workers.has_group("db"));
+
+    for group in ["db", "api"] {
+        if let Some(pool) = kurv.workers.get_group_mut(group) {
+            for (_, (_, child)) in pool.iter_mut() {
+                let _ = child.kill();
+                let _ = child.wait();
+            }
+        }
+    }
+}
@@ -0,0 +1,72 @@
+use {
+    indoc::indoc,
+    kurv::{
+        common::size::ByteSize,
+        kurv::{
+            Egg,
+            egg::limits::{EggLimits, Umask},
+        },
+    },
+};
+
+#[test]
+fn test_parse_limits() {
+    let egg: Egg = serde_saphyr::from_str(indoc! {"
+        name: api
+        command: node
+        limits:
+          memory: 512M
+          open_files: 4096
+          core_size: 0
+          nice: 10
+          umask: 027
+    "})
+    .unwrap();
+
+    let limits = egg.limits();
+    assert_eq!(limits.memory, Some(ByteSize(512 * 1024 * 1024)));
+    assert_eq!(limits.open_files, Some(4096));
+    assert_eq!(limits.processes, None);
+    assert_eq!(limits.core_size, Some(ByteSize(0)));
+    assert_eq!(limits.nice, Some(10));
+    assert_eq!(limits.umask, Some(Umask(0o027)));
+
+    assert_eq!(
+        limits.describe(),
+        vec![
+            ("memory", "512M".to_string()),
+            ("open files", "4096".to_string()),
+            ("core size", "0B".to_string()),
+            ("nice", "10".to_string()),
+            ("umask", "0027".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn test_umask() {
+    let parse = |value: &str| serde_json::from_str::<Umask>(value);
+
+    assert_eq!(parse("\"0027\"").unwrap(), Umask(0o027));
+    assert_eq!(parse("\"0o22\"").unwrap(), Umask(0o022));
+    assert_eq!(parse("77").unwrap(), Umask(0o077));
+    assert!(parse("\"089\"").is_err());
+    assert!(parse("\"1777\"").is_err());
+
+    assert_eq!(serde_json::to_string(&Umask(0o022)).unwrap(), "\"0022\"");
+}
+
+#[test]
+fn test_validate_limits() {
+    let limits = EggLimits {
+        nice: Some(-21),
+        ..Default::default()
+    };
+
+    let problems = limits.validate();
+    assert_eq!(problems.len(), 1);
+    assert_eq!(problems[0].field, "limits.nice");
+
+    assert!(EggLimits::default().is_empty());
+    assert!(!limits.is_empty());
+}
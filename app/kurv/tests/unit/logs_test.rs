@@ -0,0 +1,207 @@
+use {
+    chrono::Local,
+    kurv::{
+        common::rotation::LogRotation,
+        kurv::{
+            JsonLogFields, JsonLogLine, LogFollower, LogOptions, create_log_files, egg::LogCapture,
+            pipe_to_log, stdio::MAX_LINE_LENGTH, tail_lines,
+        },
+    },
+    std::{
+        fs,
+        io::{Cursor, Write},
+        thread::sleep,
+        time::{Duration, Instant},
+    },
+    tempfile::TempDir,
+};
+
+#[test]
+fn test_tail_lines() {
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.path().join("api.stdout");
+
+    // a missing file has no lines
+    assert!(tail_lines(&path, 10).unwrap().is_empty());
+
+    fs::write(&path, "").unwrap();
+    assert!(tail_lines(&path, 10).unwrap().is_empty());
+
+    let content: String = (1..=5000).map(|i| format!("line {i}\n")).collect();
+    fs::write(&path, content).unwrap();
+
+    assert_eq!(tail_lines(&path, 3).unwrap(), vec!["line 4998", "line 4999", "line 5000"]);
+    assert_eq!(tail_lines(&path, 6000).unwrap().len(), 5000);
+    assert!(tail_lines(&path, 0).unwrap().is_empty());
+
+    // the last line doesn't need a line break
+    fs::write(&path, "first\r\nsecond\nthird").unwrap();
+    assert_eq!(tail_lines(&path, 2).unwrap(), vec!["second", "third"]);
+}
+
+#[test]
+fn test_log_follower() {
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.path().join("api.stdout");
+    fs::write(&path, "old line\n").unwrap();
+
+    // only what is written after it starts following is returned
+    let mut follower = LogFollower::new(&path);
+    assert!(follower.read_lines().unwrap().is_empty());
+
+    let mut file = fs::OpenOptions::new().append(true).open(&path).unwrap();
+    file.write_all(b"new line\nhalf a ").unwrap();
+    assert_eq!(follower.read_lines().unwrap(), vec!["new line"]);
+
+    file.write_all(b"line\n").unwrap();
+    assert_eq!(follower.read_lines().unwrap(), vec!["half a line"]);
+
+    // after a rotation, the new file is read from the start
+    fs::rename(&path, temp_dir.path().join("api.stdout.1")).unwrap();
+    fs::write(&path, "rotated\n").unwrap();
+    assert_eq!(follower.read_lines().unwrap(), vec!["rotated"]);
+}
+
+#[test]
+fn test_timestamp_format_validation() {
+    let capture = LogCapture::default();
+    assert_eq!(capture.timestamp_format().unwrap(), "%Y-%m-%d %H:%M:%S%.3f");
+
+    let capture = LogCapture {
+        timestamp_format: Some("%H:%M:%Q".to_string()),
+        ..Default::default()
+    };
+    assert!(capture.timestamp_format().is_err());
+}
+
+#[test]
+fn test_prefixed_and_combined_logs() {
+    let temp_dir = TempDir::new().unwrap();
+    let rotation = LogRotation::default();
+
+    let options = LogOptions {
+        timestamp_format: Some("%Y".to_string()),
+        combined: true,
+        ..Default::default()
+    };
+
+    let (paths, stdout, stderr) =
+        create_log_files("api", temp_dir.path(), &rotation, &options).unwrap();
+
+    let combined = paths.combined.clone().unwrap();
+    assert_eq!(combined, temp_dir.path().join("api.log"));
+
+    pipe_to_log(Cursor::new(b"hello\nworld".to_vec()), stdout);
+    pipe_to_log(Cursor::new(b"oops\n".to_vec()), stderr);
+
+    // the pipes are read on their own threads
+    let started = Instant::now();
+    while tail_lines(&combined, 10).unwrap().len() < 3 && started.elapsed().as_secs() < 5 {
+        sleep(Duration::from_millis(10));
+    }
+
+    let year = Local::now().format("%Y").to_string();
+    assert_eq!(
+        tail_lines(&paths.stdout, 10).unwrap(),
+        vec![format!("{year} [out] hello"), format!("{year} [out] world")]
+    );
+    assert_eq!(tail_lines(&paths.stderr, 10).unwrap(), vec![format!("{year} [err] oops")]);
+
+    let mut combined_lines = tail_lines(&combined, 10).unwrap();
+    combined_lines.sort();
+    assert_eq!(
+        combined_lines,
+        vec![
+            format!("{year} [err] oops"),
+            format!("{year} [out] hello"),
+            format!("{year} [out] world")
+        ]
+    );
+}
+
+#[test]
+fn test_long_lines_in_pieces() {
+    let temp_dir = TempDir::new().unwrap();
+    let options = LogOptions {
+        combined: true,
+        ..Default::default()
+    };
+
+    let (paths, stdout, _) =
+        create_log_files("api", temp_dir.path(), &LogRotation::default(), &options).unwrap();
+    let combined = paths.combined.clone().unwrap();
+
+    let long = "a".repeat(MAX_LINE_LENGTH as usize + 10);
+    pipe_to_log(Cursor::new(format!("{long}\nshort\n").into_bytes()), stdout);
+
+    let started = Instant::now();
+    while tail_lines(&combined, 10).unwrap().len() < 3 && started.elapsed().as_secs() < 5 {
+        sleep(Duration::from_millis(10));
+    }
+
+    // the raw log keeps the line whole, the tagged ones get a line per piece
+    assert_eq!(tail_lines(&paths.stdout, 10).unwrap(), vec![long.clone(), "short".to_string()]);
+    assert_eq!(
+        tail_lines(&combined, 10).unwrap(),
+        vec![
+            format!("[out] {}", &long[..MAX_LINE_LENGTH as usize]),
+            format!("[out] {}", &long[MAX_LINE_LENGTH as usize..]),
+            "[out] short".to_string()
+        ]
+    );
+}
+
+#[test]
+fn test_raw_logs_by_default() {
+    let temp_dir = TempDir::new().unwrap();
+    let (paths, stdout, _) =
+        create_log_files("api", temp_dir.path(), &LogRotation::default(), &LogOptions::default())
+            .unwrap();
+
+    assert!(paths.combined.is_none());
+    pipe_to_log(Cursor::new(b"as is\n".to_vec()), stdout);
+
+    let started = Instant::now();
+    while tail_lines(&paths.stdout, 10).unwrap().is_empty() && started.elapsed().as_secs() < 5 {
+        sleep(Duration::from_millis(10));
+    }
+
+    assert_eq!(tail_lines(&paths.stdout, 10).unwrap(), vec!["as is"]);
+}
+
+#[test]
+fn test_json_logs() {
+    let temp_dir = TempDir::new().unwrap();
+    let options = LogOptions {
+        json: Some(JsonLogFields {
+            egg: "api".to_string(),
+            id: 3,
+            instance: 1,
+            pid: 0,
+        }),
+        ..Default::default()
+    };
+
+    let (paths, _, mut stderr) =
+        create_log_files("api.1", temp_dir.path(), &LogRotation::default(), &options).unwrap();
+
+    stderr.set_pid(1234);
+    pipe_to_log(Cursor::new(b"something \"failed\"\r\n".to_vec()), stderr);
+
+    let started = Instant::now();
+    while tail_lines(&paths.stderr, 10).unwrap().is_empty() && started.elapsed().as_secs() < 5 {
+        sleep(Duration::from_millis(10));
+    }
+
+    let lines = tail_lines(&paths.stderr, 10).unwrap();
+    assert_eq!(lines.len(), 1);
+
+    let line: JsonLogLine = serde_json::from_str(&lines[0]).unwrap();
+    assert_eq!(line.egg, "api");
+    assert_eq!(line.id, 3);
+    assert_eq!(line.instance, 1);
+    assert_eq!(line.stream, "stderr");
+    assert_eq!(line.pid, 1234);
+    assert_eq!(line.msg, "something \"failed\"");
+    assert!(chrono::DateTime::parse_from_rfc3339(&line.ts).is_ok());
+}
@@ -0,0 +1,191 @@
+use {
+    indoc::indoc,
+    kurv::{
+        common::{
+            duration::HumanDuration,
+            size::{ByteSize, humanize_size},
+            tcp::Severity,
+        },
+        kurv::{
+            Egg, EggState, EggStatus, ProcessMetrics,
+            egg::{EggInstance, MEMORY_RESTART_MSG, cpu_percent},
+            metrics::{ProcStat, parse_stat},
+        },
+    },
+};
+
+#[test]
+fn test_parse_stat() {
+    // the command name can have spaces and parentheses
+    let stat = "8722 (my (weird) app) R 8716 8700 8716 0 -1 4194304 79 0 0 0 120 30 0 0 20 0 4 \
+                0 676728 2703360 273 18446744073709551615 94594690801664";
+
+    assert_eq!(
+        parse_stat(stat),
+        Some(ProcStat {
+            pgrp: 8700,
+            ticks: 150,
+            threads: 4,
+            rss_pages: 273,
+        })
+    );
+
+    assert_eq!(parse_stat("8722 (cat) R 8716"), None);
+    assert_eq!(parse_stat(""), None);
+}
+
+#[test]
+fn test_cpu_percent() {
+    // 50 ticks of 100 per second in 1 second: half a cpu
+    assert_eq!(cpu_percent(50, 100, 1.0), 50.0);
+    assert_eq!(cpu_percent(300, 100, 2.0), 150.0);
+    assert_eq!(cpu_percent(1, 100, 3.0), 0.3);
+    assert_eq!(cpu_percent(10, 100, 0.0), 0.0);
+    assert_eq!(cpu_percent(10, 0, 1.0), 0.0);
+}
+
+#[test]
+fn test_egg_metrics() {
+    let metrics = |memory, cpu| ProcessMetrics {
+        memory,
+        cpu,
+        threads: 2,
+        fds: 5,
+        processes: 1,
+    };
+
+    let instance = |index, status, metrics| EggInstance {
+        index,
+        status,
+        pid: 100 + index as u32,
+        metrics,
+        ..Default::default()
+    };
+
+    let mut egg = Egg {
+        name: "api".to_string(),
+        state: Some(EggState {
+            instances: vec![
+                instance(0, EggStatus::Running, Some(metrics(1024, 12.5))),
+                instance(1, EggStatus::Running, Some(metrics(2048, 30.0))),
+                instance(2, EggStatus::Errored, Some(metrics(4096, 99.0))),
+            ],
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+
+    // only the running instances count
+    egg.update_metrics();
+    assert_eq!(
+        egg.state.as_ref().unwrap().metrics,
+        Some(ProcessMetrics {
+            memory: 3072,
+            cpu: 42.5,
+            threads: 4,
+            fds: 10,
+            processes: 2,
+        })
+    );
+
+    egg.set_instance_metrics(0, None);
+    egg.set_instance_metrics(1, None);
+    egg.update_metrics();
+    assert_eq!(egg.state.as_ref().unwrap().metrics, None);
+}
+
+#[test]
+fn test_humanize_size() {
+    assert_eq!(humanize_size(512), "512B");
+    assert_eq!(humanize_size(1536), "1.5K");
+    assert_eq!(humanize_size(35_861_299), "34.2M");
+    assert_eq!(humanize_size(2 * 1024 * 1024 * 1024), "2.0G");
+}
+
+#[test]
+fn test_parse_max_memory_restart() {
+    let egg: Egg = serde_saphyr::from_str(indoc! {"
+        name: api
+        command: node
+        max_memory_restart: 512M
+        max_memory_period: 1m
+    "})
+    .unwrap();
+
+    assert_eq!(egg.max_memory_restart, Some(ByteSize(512 * 1024 * 1024)));
+    assert_eq!(egg.max_memory_period(), HumanDuration::from_secs(60));
+
+    let egg = Egg {
+        max_memory_restart: Some(ByteSize(0)),
+        ..Default::default()
+    };
+    assert_eq!(egg.max_memory_period(), HumanDuration::from_secs(30));
+
+    let problems = egg.validate();
+    let problem = problems.iter().find(|problem| problem.field == "max_memory_restart").unwrap();
+    assert_eq!(problem.severity, Severity::Error);
+
+    let egg = Egg {
+        max_memory_period: Some(HumanDuration::from_secs(5)),
+        ..Default::default()
+    };
+    let problems = egg.validate();
+    let problem = problems.iter().find(|problem| problem.field == "max_memory_period").unwrap();
+    assert_eq!(problem.severity, Severity::Warning);
+}
+
+#[test]
+fn test_max_memory_restart_without_sampling() {
+    let egg = Egg {
+        max_memory_restart: Some(ByteSize(512 * 1024 * 1024)),
+        ..Default::default()
+    };
+
+    // with `KURV_METRICS_INTERVAL=0` the memory of the eggs is never sampled
+    let problem = egg.check_memory_sampling(None).unwrap();
+    assert_eq!(problem.field, "max_memory_restart");
+    assert_eq!(problem.severity, Severity::Warning);
+
+    assert_eq!(egg.check_memory_sampling(Some(HumanDuration::from_secs(5))), None);
+    assert_eq!(Egg::default().check_memory_sampling(None), None);
+}
+
+#[test]
+fn test_restart_because() {
+    let mut egg = Egg {
+        name: "api".to_string(),
+        state: Some(EggState {
+            status: EggStatus::Running,
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+
+    egg.restart_because(MEMORY_RESTART_MSG.to_string());
+
+    let state = egg.state.as_ref().unwrap();
+    assert_eq!(state.status, EggStatus::Restarting);
+    assert_eq!(egg.history().len(), 1);
+    assert_eq!(egg.history()[0].message, MEMORY_RESTART_MSG);
+
+    // the reason outlives the restart, but only the first run after it
+    egg.reset_state();
+    egg.set_as_running(1234);
+    assert_eq!(egg.state.as_ref().unwrap().error.as_deref(), Some(MEMORY_RESTART_MSG));
+
+    egg.set_as_running(1234);
+    assert_eq!(egg.state.as_ref().unwrap().error.as_deref(), Some(""));
+}
+
+#[test]
+fn test_history_is_capped() {
+    let mut egg = Egg::default();
+
+    for number in 0..25 {
+        egg.record_event(format!("event {number}"));
+    }
+
+    assert_eq!(egg.history().len(), 20);
+    assert_eq!(egg.history()[0].message, "event 5");
+    assert_eq!(egg.history()[19].message, "event 24");
+}
@@ -1,6 +1,22 @@
 // Unit tests for kurv
+mod basket_test;
+mod cgroup_test;
+mod check_test;
+mod cron_test;
+mod duration_test;
 mod egg_test;
+mod env_test;
+mod health_test;
+mod kill_test;
+mod limits_test;
+mod logs_test;
+mod metrics_test;
 mod plugin_test;
+mod prometheus_test;
+mod rotation_test;
+mod schedule_test;
+mod secret_test;
 mod state_test;
 mod tcp_test;
 mod theme_test;
+mod workers_test;
@@ -0,0 +1,89 @@
+use {
+    kurv::{
+        api::metrics::render,
+        common::Info,
+        kurv::{Egg, EggState, EggStatus, KurvState, ProcessMetrics, egg::EggInstance},
+    },
+    std::collections::BTreeMap,
+};
+
+fn state() -> KurvState {
+    let api = Egg {
+        name: "api".to_string(),
+        command: "node".to_string(),
+        id: Some(1),
+        state: Some(EggState {
+            status: EggStatus::Running,
+            pid: 100,
+            restarts: 3,
+            exit_code: Some(1),
+            instances: vec![EggInstance {
+                index: 0,
+                status: EggStatus::Running,
+                pid: 100,
+                ..Default::default()
+            }],
+            metrics: Some(ProcessMetrics {
+                memory: 1024,
+                cpu: 12.5,
+                threads: 4,
+                fds: 7,
+                processes: 1,
+            }),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+
+    let plugin = Egg {
+        name: "kurv-\"ui\"".to_string(),
+        command: "kurv-ui".to_string(),
+        id: Some(2),
+        plugin: Some(true),
+        ..Default::default()
+    };
+
+    let mut eggs = BTreeMap::new();
+    eggs.insert(api.name.clone(), api);
+    eggs.insert(plugin.name.clone(), plugin);
+
+    KurvState { eggs }
+}
+
+#[test]
+fn test_render_metrics() {
+    let info = Info::new();
+    let text = render(&info, &state());
+    let lines: Vec<&str> = text.lines().collect();
+
+    let has = |line: &str| lines.contains(&line);
+
+    assert!(has(&format!(
+        "kurv_info{{version=\"{}\",version_name=\"{}\"}} 1",
+        info.version,
+        info.version_name.unwrap_or("")
+    )));
+    assert!(has("kurv_eggs 1"));
+    assert!(has("kurv_plugins 1"));
+
+    assert!(has("# TYPE kurv_egg_up gauge"));
+    assert!(has("kurv_egg_up{egg=\"api\",kind=\"egg\"} 1"));
+    assert!(has("kurv_egg_up{egg=\"kurv-\\\"ui\\\"\",kind=\"plugin\"} 0"));
+
+    assert!(has("kurv_egg_status{egg=\"api\",kind=\"egg\",status=\"running\"} 1"));
+    assert!(has("kurv_egg_status{egg=\"api\",kind=\"egg\",status=\"stopped\"} 0"));
+    assert!(has("kurv_egg_instances{egg=\"api\",kind=\"egg\"} 1"));
+
+    assert!(has("# TYPE kurv_egg_restarts_total counter"));
+    assert!(has("kurv_egg_restarts_total{egg=\"api\",kind=\"egg\"} 3"));
+    assert!(has("kurv_egg_last_exit_code{egg=\"api\",kind=\"egg\"} 1"));
+
+    assert!(has("kurv_egg_cpu_percent{egg=\"api\",kind=\"egg\"} 12.5"));
+    assert!(has("kurv_egg_memory_bytes{egg=\"api\",kind=\"egg\"} 1024"));
+    assert!(has("kurv_egg_threads{egg=\"api\",kind=\"egg\"} 4"));
+    assert!(has("kurv_egg_open_fds{egg=\"api\",kind=\"egg\"} 7"));
+
+    // eggs without an exit code or metrics don't get those samples
+    assert!(!text.contains("kurv_egg_last_exit_code{egg=\"kurv-"));
+    assert!(!text.contains("kurv_egg_cpu_percent{egg=\"kurv-"));
+}
@@ -0,0 +1,97 @@
+use {
+    flate2::read::GzDecoder,
+    kurv::{
+        common::{rotation::LogRotation, size::ByteSize},
+        kurv::stdio::{LogFile, rotated_path},
+    },
+    std::{fs, io::Read},
+    tempfile::TempDir,
+};
+
+#[test]
+fn test_byte_size_parsing() {
+    assert_eq!("512".parse::<ByteSize>().unwrap(), ByteSize(512));
+    assert_eq!("10K".parse::<ByteSize>().unwrap(), ByteSize(10 * 1024));
+    assert_eq!("10mb".parse::<ByteSize>().unwrap(), ByteSize(10 * 1024 * 1024));
+    assert_eq!("1G".parse::<ByteSize>().unwrap(), ByteSize(1024 * 1024 * 1024));
+    assert!("10X".parse::<ByteSize>().is_err());
+    assert!("M".parse::<ByteSize>().is_err());
+
+    assert_eq!(ByteSize(512 * 1024 * 1024).to_string(), "512M");
+    assert_eq!(ByteSize(1000).to_string(), "1000B");
+}
+
+#[test]
+fn test_log_rotation_override() {
+    let global = LogRotation {
+        max_size: Some(ByteSize(100)),
+        max_files: Some(3),
+        ..Default::default()
+    };
+
+    let egg = LogRotation {
+        max_files: Some(10),
+        compress: Some(true),
+        ..Default::default()
+    };
+
+    let rotation = egg.or(&global);
+    assert_eq!(rotation.max_size, Some(ByteSize(100)));
+    assert_eq!(rotation.max_files(), 10);
+    assert!(rotation.compress());
+
+    assert_eq!(LogRotation::default().max_files(), 5);
+    assert!(!LogRotation::default().compress());
+}
+
+#[test]
+fn test_log_file_rotates_by_size() {
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.path().join("api.stdout");
+
+    let rotation = LogRotation {
+        max_size: Some(ByteSize(10)),
+        max_files: Some(2),
+        ..Default::default()
+    };
+
+    let mut log = LogFile::open(&path, rotation).unwrap();
+
+    for line in ["first line\n", "second line\n", "third line\n", "fourth line\n"] {
+        log.write_line(line.as_bytes()).unwrap();
+    }
+
+    // only the last `max_files` rotated files are kept
+    assert_eq!(fs::read_to_string(&path).unwrap(), "fourth line\n");
+    assert_eq!(fs::read_to_string(rotated_path(&path, 1, false)).unwrap(), "third line\n");
+    assert_eq!(fs::read_to_string(rotated_path(&path, 2, false)).unwrap(), "second line\n");
+    assert!(!rotated_path(&path, 3, false).exists());
+}
+
+#[test]
+fn test_log_file_rotation_compress() {
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.path().join("api.stderr");
+
+    let rotation = LogRotation {
+        compress: Some(true),
+        ..Default::default()
+    };
+
+    // without a size or interval, files are only rotated on demand
+    let mut log = LogFile::open(&path, rotation).unwrap();
+    log.write_line(b"boom\n").unwrap();
+    log.write_line(b"boom again\n").unwrap();
+    assert!(!rotated_path(&path, 1, true).exists());
+
+    log.rotate().unwrap();
+    log.wait_for_compression();
+
+    let mut content = String::new();
+    let gz = fs::File::open(rotated_path(&path, 1, true)).unwrap();
+    GzDecoder::new(gz).read_to_string(&mut content).unwrap();
+
+    assert_eq!(content, "boom\nboom again\n");
+    assert!(!rotated_path(&path, 1, false).exists());
+    assert_eq!(fs::read_to_string(&path).unwrap(), "");
+}
@@ -0,0 +1,118 @@
+use {
+    chrono::{Duration, Local},
+    indoc::indoc,
+    kurv::{
+        common::{cron::CronExpr, tcp::Severity},
+        kurv::{CRON_RESTART_MSG, Egg, EggRestart, EggState, EggStatus},
+    },
+};
+
+fn scheduled_egg() -> Egg {
+    serde_saphyr::from_str(indoc! {"
+        name: backup
+        command: ./backup.sh
+        schedule: '0 3 * * *'
+    "})
+    .unwrap()
+}
+
+#[test]
+fn test_scheduled_egg_waits_for_its_time() {
+    let mut egg = scheduled_egg();
+
+    // a new scheduled egg isn't spawned right away
+    assert!(!egg.should_spawn());
+
+    egg.set_as_scheduled();
+    let state = egg.state.as_ref().unwrap();
+    assert_eq!(state.status, EggStatus::Scheduled);
+    assert!(state.next_run_at.unwrap() > Local::now());
+    assert!(!egg.should_spawn());
+
+    egg.state.as_mut().unwrap().next_run_at = Some(Local::now() - Duration::seconds(1));
+    assert!(egg.should_spawn());
+
+    // eggs without a schedule are spawned as soon as they are added
+    let egg = Egg::default();
+    assert!(egg.should_spawn());
+}
+
+#[test]
+fn test_finish_run() {
+    let mut egg = scheduled_egg();
+    egg.set_as_running(1234);
+
+    let started_at = Local::now() - Duration::seconds(90);
+    egg.set_start_time(Some(started_at));
+    egg.set_exit_code(Some(3));
+    egg.finish_run("Exited with code 3".to_string());
+
+    let state = egg.state.as_ref().unwrap();
+    assert_eq!(state.status, EggStatus::Scheduled);
+    assert_eq!(state.error.as_deref(), Some("Exited with code 3"));
+    assert_eq!(state.pid, 0);
+    assert!(state.next_run_at.is_some());
+
+    // a failed run is just recorded, not retried
+    let last_run = state.last_run.clone().unwrap();
+    assert_eq!(last_run.started_at, started_at);
+    assert_eq!(last_run.exit_code, Some(3));
+    assert!(last_run.duration.0.as_secs() >= 90);
+}
+
+#[test]
+fn test_cron_restart_due() {
+    let mut egg = Egg {
+        name: "web".to_string(),
+        cron_restart: Some("0 3 * * *".parse().unwrap()),
+        state: Some(EggState::default()),
+        ..Default::default()
+    };
+    egg.set_as_running(1234);
+
+    // the first time it's just scheduled
+    assert!(!egg.cron_restart_due());
+    assert!(egg.state.as_ref().unwrap().next_restart_at.unwrap() > Local::now());
+    assert!(!egg.cron_restart_due());
+
+    egg.state.as_mut().unwrap().next_restart_at = Some(Local::now() - Duration::seconds(1));
+    assert!(egg.cron_restart_due());
+
+    egg.restart_because(CRON_RESTART_MSG.to_string());
+    assert_eq!(egg.state.as_ref().unwrap().status, EggStatus::Restarting);
+
+    // the next one is scheduled once it's running again
+    egg.reset_state();
+    egg.set_as_running(1235);
+    assert_eq!(egg.state.as_ref().unwrap().next_restart_at, None);
+    assert_eq!(egg.state.as_ref().unwrap().error.as_deref(), Some(CRON_RESTART_MSG));
+}
+
+#[test]
+fn test_validate_schedule() {
+    let never: CronExpr = "0 0 31 2 *".parse().unwrap();
+
+    let egg = Egg {
+        schedule: Some(never.clone()),
+        cron_restart: Some(never),
+        restart: Some(EggRestart::default()),
+        ..Default::default()
+    };
+
+    let problems: Vec<(String, Severity)> = egg
+        .validate()
+        .into_iter()
+        .filter(|problem| ["schedule", "cron_restart", "restart"].contains(&problem.field.as_str()))
+        .map(|problem| (problem.field, problem.severity))
+        .collect();
+
+    assert_eq!(
+        problems,
+        vec![
+            ("cron_restart".to_string(), Severity::Error),
+            ("schedule".to_string(), Severity::Error),
+            ("cron_restart".to_string(), Severity::Warning),
+            ("restart".to_string(), Severity::Warning),
+        ]
+    );
+}
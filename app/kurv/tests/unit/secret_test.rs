@@ -0,0 +1,128 @@
+use {
+    indoc::indoc,
+    kurv::{
+        api::Context,
+        common::{Info, tcp::Request},
+        kurv::{Egg, KurvState, egg::secret::SECRET_MASK},
+    },
+    std::{
+        collections::{BTreeMap, HashMap},
+        sync::{Arc, Mutex},
+    },
+};
+
+fn egg(yaml: &str) -> Egg {
+    serde_saphyr::from_str(yaml).unwrap()
+}
+
+#[test]
+fn test_default_secret_patterns() {
+    let egg = egg("name: api\ncommand: node\n");
+
+    assert!(egg.is_secret_env("DB_PASSWORD"));
+    assert!(egg.is_secret_env("github_token"));
+    assert!(egg.is_secret_env("STRIPE_API_KEY"));
+    assert!(egg.is_secret_env("AWS_SECRET_ACCESS_KEY"));
+    assert!(!egg.is_secret_env("PORT"));
+    assert!(!egg.is_secret_env("NODE_ENV"));
+}
+
+#[test]
+fn test_custom_secret_env() {
+    let egg = egg(indoc! {"
+        name: api
+        command: node
+        secret_env:
+          - DATABASE_URL
+          - stripe_*
+    "});
+
+    assert!(egg.is_secret_env("DATABASE_URL"));
+    assert!(egg.is_secret_env("database_url"));
+    assert!(egg.is_secret_env("STRIPE_WEBHOOK"));
+    assert!(!egg.is_secret_env("DATABASE_URL_REPLICA"));
+    assert!(!egg.is_secret_env("MY_STRIPE"));
+}
+
+#[test]
+fn test_masked_egg() {
+    let egg = egg(indoc! {"
+        name: api
+        command: node
+        secret_env: [DATABASE_URL]
+        env:
+          PORT: '8080'
+          DATABASE_URL: postgres://user:pass@db/app
+          JWT_SECRET: s3cr3t
+    "});
+
+    let masked = egg.masked();
+    let env = masked.env.unwrap();
+
+    assert_eq!(env["PORT"], "8080");
+    assert_eq!(env["DATABASE_URL"], SECRET_MASK);
+    assert_eq!(env["JWT_SECRET"], SECRET_MASK);
+
+    // the egg itself keeps its values
+    assert_eq!(egg.env.unwrap()["JWT_SECRET"], "s3cr3t");
+}
+
+#[test]
+fn test_masked_config_diff() {
+    let old = egg(indoc! {"
+        name: api
+        command: node
+        env:
+          PORT: '8080'
+          JWT_SECRET: old
+    "});
+    let new = egg(indoc! {"
+        name: api
+        command: node
+        env:
+          PORT: '9090'
+          JWT_SECRET: new
+          API_TOKEN: added
+    "});
+
+    let changes = old.masked_config_diff(&new);
+    let change = |field: &str| changes.iter().find(|change| change.field == field).unwrap();
+
+    assert_eq!(change("env.PORT").old.as_deref(), Some("8080"));
+    assert_eq!(change("env.PORT").new.as_deref(), Some("9090"));
+    assert_eq!(change("env.JWT_SECRET").old.as_deref(), Some(SECRET_MASK));
+    assert_eq!(change("env.JWT_SECRET").new.as_deref(), Some(SECRET_MASK));
+    assert_eq!(change("env.API_TOKEN").old, None);
+    assert_eq!(change("env.API_TOKEN").new.as_deref(), Some(SECRET_MASK));
+}
+
+#[test]
+fn test_changes_require_the_api_token() {
+    let request = |headers: Vec<&str>| Request {
+        method: "PUT".to_string(),
+        path: "/eggs/api".to_string(),
+        version: "HTTP/1.1".to_string(),
+        headers: headers.into_iter().map(String::from).collect(),
+        body: "".to_string(),
+        query_params: HashMap::new(),
+        path_params: HashMap::new(),
+    };
+
+    let mut info = Info::new();
+    info.api_token = None;
+
+    let ctx = Context {
+        state: Arc::new(Mutex::new(KurvState {
+            eggs: BTreeMap::new(),
+        })),
+        info: Arc::new(Mutex::new(info)),
+    };
+
+    // without a token, anyone can change eggs
+    assert!(ctx.can_change(&request(vec![])));
+
+    ctx.info.lock().unwrap().api_token = Some("s3cr3t".to_string());
+    assert!(!ctx.can_change(&request(vec![])));
+    assert!(!ctx.can_change(&request(vec!["Authorization: Bearer nope"])));
+    assert!(ctx.can_change(&request(vec!["authorization: Bearer s3cr3t"])));
+}
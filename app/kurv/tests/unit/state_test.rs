@@ -21,14 +21,11 @@ fn test_state_save_and_load() {
             try_count: 0,
             error: None,
             pid: 1234,
-            uncsynced: false,
+            ..Default::default()
         }),
         args: Some(vec!["hello".to_string()]),
         cwd: Some(PathBuf::from("/tmp")),
-        env: None,
-        paths: None,
-        plugin: None,
-        plugin_path: None,
+        ..Default::default()
     };
 
     eggs.insert("test-egg".to_string(), egg);
@@ -77,13 +74,7 @@ fn test_state_assigns_ids() {
             name: "egg1".to_string(),
             command: "echo".to_string(),
             id: None,
-            state: None,
-            args: None,
-            cwd: None,
-            env: None,
-            paths: None,
-            plugin: None,
-            plugin_path: None,
+            ..Default::default()
         },
     );
     eggs.insert(
@@ -92,13 +83,7 @@ fn test_state_assigns_ids() {
             name: "egg2".to_string(),
             command: "ls".to_string(),
             id: None,
-            state: None,
-            args: None,
-            cwd: None,
-            env: None,
-            paths: None,
-            plugin: None,
-            plugin_path: None,
+            ..Default::default()
         },
     );
 
@@ -129,13 +114,7 @@ fn test_state_multiple_eggs() {
                 name: format!("egg{}", i),
                 command: "echo".to_string(),
                 id: Some(i),
-                state: None,
-                args: None,
-                cwd: None,
-                env: None,
-                paths: None,
-                plugin: None,
-                plugin_path: None,
+                ..Default::default()
             },
         );
     }
@@ -153,3 +132,101 @@ fn test_state_multiple_eggs() {
         assert_eq!(loaded.eggs.get(&key).unwrap().id, Some(i));
     }
 }
+
+#[test]
+fn test_state_get_by_instance_pid() {
+    let mut state = KurvState {
+        eggs: BTreeMap::new(),
+    };
+
+    let mut egg = Egg {
+        name: "api".to_string(),
+        command: "node".to_string(),
+        instances: Some(2),
+        ..Default::default()
+    };
+
+    egg.set_instance_running(0, 100, None);
+    egg.set_instance_running(1, 101, None);
+    egg.set_as_running(100);
+    state.collect(&egg);
+
+    // any of the instances' pids resolves to the egg
+    assert_eq!(state.get_by_pid(101).map(|egg| egg.name.as_str()), Some("api"));
+    assert_eq!(state.get_id_by_token("101"), Some(1));
+}
+
+fn egg_depending_on(name: &str, deps: &[&str]) -> Egg {
+    Egg {
+        name: name.to_string(),
+        command: "node".to_string(),
+        depends_on: Some(deps.iter().map(|dep| dep.to_string()).collect()),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_state_dependency_cycles() {
+    let mut state = KurvState {
+        eggs: BTreeMap::new(),
+    };
+
+    state.collect(&egg_depending_on("db-proxy", &[]));
+    state.collect(&egg_depending_on("worker", &["db-proxy"]));
+    state.collect(&egg_depending_on("api", &["worker", "db-proxy"]));
+
+    assert_eq!(state.find_dependency_cycle(&egg_depending_on("web", &["api"])), None);
+
+    // replacing an egg so it depends on one of its dependents closes a cycle
+    assert_eq!(
+        state.find_dependency_cycle(&egg_depending_on("db-proxy", &["api"])),
+        Some(vec![
+            "db-proxy".to_string(),
+            "api".to_string(),
+            "worker".to_string(),
+            "db-proxy".to_string()
+        ])
+    );
+
+    // depending on itself is a cycle too
+    assert_eq!(
+        state.find_dependency_cycle(&egg_depending_on("cron", &["cron"])),
+        Some(vec!["cron".to_string(), "cron".to_string()])
+    );
+}
+
+#[test]
+fn test_state_pending_dependencies() {
+    let mut state = KurvState {
+        eggs: BTreeMap::new(),
+    };
+
+    let api = egg_depending_on("api", &["worker", "db-proxy"]);
+    state.collect(&egg_depending_on("worker", &[]));
+    state.collect(&api);
+
+    // missing and not running dependencies are both pending
+    assert_eq!(state.pending_dependencies(&api), vec!["worker", "db-proxy"]);
+
+    state.eggs.get_mut("worker").unwrap().set_as_running(100);
+    assert_eq!(state.pending_dependencies(&api), vec!["db-proxy"]);
+    assert_eq!(state.dependents_of("worker"), vec!["api"]);
+}
+
+#[test]
+fn test_state_missing_dependencies() {
+    let mut state = KurvState {
+        eggs: BTreeMap::new(),
+    };
+
+    state.collect(&egg_depending_on("worker", &[]));
+
+    let api = egg_depending_on("api", &["worker", "db-proxy", "db-proxy", "api"]);
+    let problems = state.missing_dependencies(&api);
+
+    // reported once, and depending on itself is an error of `Egg::validate`
+    assert_eq!(problems.len(), 1);
+    assert_eq!(problems[0].field, "depends_on.1");
+    assert_eq!(problems[0].message, "db-proxy isn't collected, the egg will wait for it");
+    assert!(!problems[0].is_error());
+}
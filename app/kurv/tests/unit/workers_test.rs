@@ -0,0 +1,24 @@
+use {
+    command_group::CommandGroup,
+    kurv::kurv::workers::{DEFAULT_GROUP, Workers},
+    std::process::Command,
+};
+
+#[cfg(unix)]
+#[test]
+fn test_egg_group_named_like_the_old_default() {
+    let mut workers = Workers::new();
+    let child = Command::new("true").group_spawn().unwrap();
+    workers.add_child(Some("default_kurv"), "0".to_string(), 1, child);
+    assert!(workers.has_group("default_kurv"));
+
+    // once its worker is gone, the group of the egg goes away too
+    if let Some(pool) = workers.get_group_mut("default_kurv") {
+        let (_, mut child) = pool.remove("0").unwrap();
+        let _ = child.wait();
+    }
+
+    workers.remove_group_if_empty("default_kurv");
+    assert!(!workers.0.contains_key("default_kurv"));
+    assert!(workers.0.contains_key(DEFAULT_GROUP));
+}